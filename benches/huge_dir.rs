@@ -0,0 +1,29 @@
+//! The first rotation in a directory with a huge number of rotated files.
+//!
+//! With `FileLimit::Unlimited`, rotation only looks for the newest rotated file while reading the
+//! directory, while listing the files has to hold all of them.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use file_rotate::suffix::{EpochSuffix, FileLimit, SuffixScheme};
+use std::fs::File;
+use tempdir::TempDir;
+
+const ROTATED_FILES: u64 = 100_000;
+
+fn huge_dir(c: &mut Criterion) {
+    let tmp_dir = TempDir::new("file-rotate-bench").unwrap();
+    for idx in 0..ROTATED_FILES {
+        File::create(tmp_dir.path().join(format!("log.{}", 1_600_000_000 + idx))).unwrap();
+    }
+    let basepath = tmp_dir.path().join("log");
+    let scheme = || EpochSuffix::new(FileLimit::Unlimited);
+    let mut group = c.benchmark_group("100k rotated files");
+    group.sample_size(10);
+    // `rotate` only picks the suffix, the files stay as they are
+    group.bench_function("first rotation", |b| b.iter(|| scheme().rotate(&basepath)));
+    group.bench_function("list", |b| b.iter(|| scheme().log_paths(&basepath)));
+    group.finish();
+}
+
+criterion_group!(benches, huge_dir);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! Cost of `ContentLimit::Lines` on large writes.
+//!
+//! Compare the default build with `--features memchr`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use file_rotate::{suffix::CountSuffix, ContentLimit, FileRotate};
+use std::io::Write;
+use tempdir::TempDir;
+
+const BUFFER_SIZE: usize = 1 << 20;
+
+fn write_lines(c: &mut Criterion, name: &str, line_length: usize) {
+    let tmp_dir = TempDir::new("file-rotate-bench").unwrap();
+    let mut buf = vec![b'x'; BUFFER_SIZE];
+    for idx in (line_length - 1..BUFFER_SIZE).step_by(line_length) {
+        buf[idx] = b'\n';
+    }
+    let lines_per_file = 4 * BUFFER_SIZE / line_length;
+    let mut log = FileRotate::new(
+        tmp_dir.path().join("log"),
+        CountSuffix::new(2),
+        ContentLimit::Lines(lines_per_file),
+    );
+    let mut group = c.benchmark_group("1 MB writes");
+    group.throughput(Throughput::Bytes(BUFFER_SIZE as u64));
+    group.bench_function(name, |b| b.iter(|| log.write_all(&buf).unwrap()));
+    group.finish();
+}
+
+fn lines(c: &mut Criterion) {
+    write_lines(c, "sparse line breaks", 64 * 1024);
+    write_lines(c, "dense line breaks", 16);
+}
+
+criterion_group!(benches, lines);
+criterion_main!(benches);
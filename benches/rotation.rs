@@ -0,0 +1,59 @@
+//! Cost of a rotation with many rotated files around, and of finding the rotated files among
+//! many other files.
+//!
+//! `CountSuffix` renames every file on each rotation, `SequenceSuffix` renames only the current log
+//! file.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use file_rotate::{
+    suffix::{CountSuffix, SequenceSuffix, SuffixScheme},
+    template::NameTemplate,
+    ContentLimit, FileRotate,
+};
+use std::{fs::File, io::Write};
+use tempdir::TempDir;
+
+const MAX_FILES: usize = 200;
+
+fn rotate_full<S: SuffixScheme>(c: &mut Criterion, name: &str, scheme: impl Fn() -> S) {
+    let tmp_dir = TempDir::new("file-rotate-bench").unwrap();
+    let mut log = FileRotate::new(tmp_dir.path().join("log"), scheme(), ContentLimit::Bytes(1));
+    // Fill up all the slots first
+    for _ in 0..MAX_FILES {
+        log.write_all(b"x").unwrap();
+    }
+    c.bench_function(name, |b| b.iter(|| log.write_all(b"x").unwrap()));
+}
+
+fn rotation(c: &mut Criterion) {
+    rotate_full(c, "rotate CountSuffix", || CountSuffix::new(MAX_FILES));
+    rotate_full(c, "rotate SequenceSuffix", || {
+        SequenceSuffix::new(MAX_FILES)
+    });
+}
+
+const OTHER_FILES: usize = 10_000;
+
+fn scan_crowded<S: SuffixScheme>(c: &mut Criterion, name: &str, scheme: impl Fn() -> S) {
+    let tmp_dir = TempDir::new("file-rotate-bench").unwrap();
+    for idx in 0..OTHER_FILES {
+        File::create(tmp_dir.path().join(format!("other.{}", idx))).unwrap();
+    }
+    let mut log = FileRotate::new(tmp_dir.path().join("log"), scheme(), ContentLimit::Bytes(1));
+    for _ in 0..10 {
+        log.write_all(b"x").unwrap();
+    }
+    let basepath = tmp_dir.path().join("log");
+    c.bench_function(name, |b| b.iter(|| scheme().log_paths(&basepath)));
+}
+
+fn scan(c: &mut Criterion) {
+    scan_crowded(c, "scan CountSuffix", || CountSuffix::new(MAX_FILES));
+    scan_crowded(c, "scan CountSuffix with template", || {
+        CountSuffix::new(MAX_FILES)
+            .with_template("{base}-{suffix}".parse::<NameTemplate>().unwrap())
+    });
+}
+
+criterion_group!(benches, rotation, scan);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Cost of many small writes with `ContentLimit::Bytes`, where the bookkeeping of `FileRotate`
+//! rather than the file system is what takes time.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use file_rotate::{suffix::CountSuffix, ContentLimit, FileRotate};
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+};
+use tempdir::TempDir;
+
+const WRITES: usize = 10_000;
+const LINE: &[u8] = b"0123456789abcde\n";
+
+fn small_writes(c: &mut Criterion) {
+    let tmp_dir = TempDir::new("file-rotate-bench").unwrap();
+    let mut group = c.benchmark_group("10k small writes");
+    group.throughput(Throughput::Elements(WRITES as u64));
+
+    let mut log = FileRotate::builder(
+        tmp_dir.path().join("sink"),
+        CountSuffix::new(2),
+        ContentLimit::Bytes(1 << 20),
+    )
+    .writer_factory(|_| Ok(io::sink()))
+    .build();
+    group.bench_function("to io::sink", |b| {
+        b.iter(|| {
+            for _ in 0..WRITES {
+                log.write_all(LINE).unwrap();
+            }
+        })
+    });
+
+    let mut log = FileRotate::builder(
+        tmp_dir.path().join("buffered"),
+        CountSuffix::new(2),
+        ContentLimit::Bytes(1 << 20),
+    )
+    .writer_factory(|path| {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(BufWriter::with_capacity(1 << 16, file))
+    })
+    .build();
+    group.bench_function("to BufWriter", |b| {
+        b.iter(|| {
+            for _ in 0..WRITES {
+                log.write_all(LINE).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, small_writes);
+criterion_main!(benches);
@@ -0,0 +1,119 @@
+//! Write to a [FileRotate] from async code, with any runtime.
+//!
+//! ```
+//! use file_rotate::{asynchronous::AsyncFileRotate, suffix::CountSuffix, ContentLimit, FileRotate};
+//! use futures::io::AsyncWriteExt;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let log = FileRotate::new(directory.join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(100));
+//! let mut log = AsyncFileRotate::new(log, 1000);
+//! # futures::executor::block_on(async {
+//! log.write_all(b"Hello World!\n").await.unwrap();
+//! log.close().await.unwrap();
+//! # });
+//! ```
+
+use crate::{fs_ops::FsOps, suffix::SuffixScheme, FileRotate};
+use futures::{
+    channel::{mpsc, oneshot},
+    executor,
+    io::AsyncWrite,
+    ready, Future,
+};
+use std::{
+    io::{self, Write},
+    pin::Pin,
+    task::{Context, Poll},
+    thread,
+};
+
+enum Message {
+    Write(Vec<u8>),
+    Flush(oneshot::Sender<io::Result<()>>),
+}
+
+/// An [AsyncWrite] that hands the written bytes to a thread that owns the [FileRotate], so that
+/// writing, rotating and deleting files never blocks the executor.
+///
+/// Errors of the [FileRotate] are returned by the next flush. Dropping it lets the thread write
+/// what is still queued in the background; [close](futures::io::AsyncWriteExt::close) waits for
+/// that.
+pub struct AsyncFileRotate {
+    sender: mpsc::Sender<Message>,
+    flushing: Option<oneshot::Receiver<io::Result<()>>>,
+}
+
+impl AsyncFileRotate {
+    /// Move `file_rotate` to a new thread, with room for `capacity` writes in the queue.
+    pub fn new<S, W, Fs>(mut file_rotate: FileRotate<S, W, Fs>, capacity: usize) -> Self
+    where
+        S: SuffixScheme + Send + 'static,
+        W: Write + Send + 'static,
+        Fs: FsOps + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(capacity);
+        thread::spawn(move || {
+            // The first error since the last flush
+            let mut error = None;
+            for message in executor::block_on_stream(receiver) {
+                match message {
+                    Message::Write(buf) => {
+                        if let Err(err) = file_rotate.write_all(&buf) {
+                            error.get_or_insert(err);
+                        }
+                    }
+                    Message::Flush(reply) => {
+                        let result = match error.take() {
+                            Some(err) => Err(err),
+                            None => file_rotate.flush(),
+                        };
+                        let _ = reply.send(result);
+                    }
+                }
+            }
+        });
+        Self {
+            sender,
+            flushing: None,
+        }
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the writer thread has stopped")
+}
+
+impl AsyncWrite for AsyncFileRotate {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.sender.poll_ready(cx)).map_err(|_| stopped())?;
+        self.sender
+            .start_send(Message::Write(buf.to_vec()))
+            .map_err(|_| stopped())?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        if self.flushing.is_none() {
+            ready!(self.sender.poll_ready(cx)).map_err(|_| stopped())?;
+            let (reply, result) = oneshot::channel();
+            self.sender
+                .start_send(Message::Flush(reply))
+                .map_err(|_| stopped())?;
+            self.flushing = Some(result);
+        }
+        let result = ready!(Pin::new(self.flushing.as_mut().unwrap()).poll(cx));
+        self.flushing = None;
+        Poll::Ready(result.map_err(|_| stopped())?)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        self.sender.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,95 @@
+//! Write to a [FileRotate] through shared references, within one thread.
+//!
+//! ```
+//! use file_rotate::{cell::FileRotateCell, suffix::CountSuffix, ContentLimit, FileRotate};
+//! use std::io::Write;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let log = FileRotateCell::new(FileRotate::new(
+//!     directory.join("my-log-file"),
+//!     CountSuffix::new(3),
+//!     ContentLimit::Lines(100),
+//! ));
+//! let (mut a, mut b) = (&log, &log);
+//! writeln!(a, "Hello").unwrap();
+//! writeln!(b, "World!").unwrap();
+//! ```
+//!
+//! Across threads, put the [FileRotate] in a `Mutex` instead, or use
+//! [crate::non_blocking::NonBlockingFileRotate].
+
+use crate::{
+    fs_ops::{FsOps, RealFs},
+    suffix::SuffixScheme,
+    FileRotate,
+};
+use std::{
+    cell::{RefCell, RefMut},
+    fs::File,
+    io::{self, IoSlice, Write},
+};
+
+/// A [FileRotate] that implements [Write] for `&FileRotateCell`, so that it can be written to from
+/// several places at once.
+///
+/// Each call writes (and rotates) as a whole. Writing to the cell while it is already in use, e.g.
+/// from a writer or callback that is called by the [FileRotate] itself, fails with
+/// [io::ErrorKind::WouldBlock] instead of panicking.
+pub struct FileRotateCell<S, W: Write = File, Fs: FsOps = RealFs> {
+    inner: RefCell<FileRotate<S, W, Fs>>,
+}
+
+impl<S, W: Write, Fs: FsOps> FileRotateCell<S, W, Fs> {
+    /// Wrap `file_rotate`.
+    pub fn new(file_rotate: FileRotate<S, W, Fs>) -> Self {
+        Self {
+            inner: RefCell::new(file_rotate),
+        }
+    }
+    /// Access the [FileRotate], e.g. for [FileRotate::log_paths]. Fails like writing if the cell
+    /// is in use.
+    pub fn borrow_mut(&self) -> io::Result<RefMut<'_, FileRotate<S, W, Fs>>> {
+        self.inner.try_borrow_mut().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "FileRotateCell is already in use",
+            )
+        })
+    }
+    /// The [FileRotate] itself.
+    pub fn get_mut(&mut self) -> &mut FileRotate<S, W, Fs> {
+        self.inner.get_mut()
+    }
+    /// Unwrap the [FileRotate].
+    pub fn into_inner(self) -> FileRotate<S, W, Fs> {
+        self.inner.into_inner()
+    }
+}
+
+impl<S: SuffixScheme, W: Write, Fs: FsOps> Write for &FileRotateCell<S, W, Fs> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.borrow_mut()?.write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.borrow_mut()?.write_vectored(bufs)
+    }
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.borrow_mut()?.write_all(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.borrow_mut()?.flush()
+    }
+}
+
+impl<S: SuffixScheme, W: Write, Fs: FsOps> Write for FileRotateCell<S, W, Fs> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.get_mut().write(buf)
+    }
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.get_mut().write_vectored(bufs)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.get_mut().flush()
+    }
+}
@@ -0,0 +1,114 @@
+//! Where [TimestampSuffix](crate::suffix::TimestampSuffix) and
+//! [EpochSuffix](crate::suffix::EpochSuffix) get the current time from.
+//!
+//! By default this is the system clock. Tests can use a [ManualClock] (feature `test-util`) to
+//! step time instead of sleeping.
+
+use chrono::{DateTime, FixedOffset, Local, Utc};
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_os = "wasi"))]
+use std::{
+    panic::{self, UnwindSafe},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// The current local time.
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// The system clock, `chrono::Local::now()`.
+///
+/// If the local time zone can't be determined, e.g. because of a broken time zone database, the
+/// time is given in UTC instead, and a warning is logged the first time (feature
+/// `log-internals`). On WASI, which has no time zones, it is always in UTC, like [UtcClock].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[cfg(not(target_os = "wasi"))]
+    fn now(&self) -> DateTime<Local> {
+        now_or_utc(Local::now)
+    }
+    #[cfg(target_os = "wasi")]
+    fn now(&self) -> DateTime<Local> {
+        UtcClock.now()
+    }
+}
+
+/// `now()`, or the time in UTC if it panics, e.g. because the local time zone can't be
+/// determined. Only the first fallback is logged, since it would recur at every rotation.
+#[cfg(not(target_os = "wasi"))]
+pub(crate) fn now_or_utc(now: impl FnOnce() -> DateTime<Local> + UnwindSafe) -> DateTime<Local> {
+    static WARNED: AtomicBool = AtomicBool::new(false);
+    panic::catch_unwind(now).unwrap_or_else(|_| {
+        if !WARNED.swap(true, Ordering::Relaxed) {
+            event!(
+                Warn,
+                "can't determine the local time zone, using UTC instead"
+            );
+        }
+        UtcClock.now()
+    })
+}
+
+/// The system clock, in UTC regardless of the local time zone.
+///
+/// Useful where the local time zone isn't configured, like minimal container images.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UtcClock;
+
+impl Clock for UtcClock {
+    fn now(&self) -> DateTime<Local> {
+        DateTime::from_naive_utc_and_offset(
+            Utc::now().naive_utc(),
+            FixedOffset::east_opt(0).unwrap(),
+        )
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so keep one clone to step the time of a suffix scheme that was
+/// given another.
+///
+/// ```
+/// use chrono::{Duration, Local};
+/// use file_rotate::{clock::{Clock, ManualClock}, suffix::{EpochSuffix, FileLimit}};
+///
+/// let clock = ManualClock::new(Local::now());
+/// let suffix = EpochSuffix::new(FileLimit::age(Duration::days(1))).with_clock(clock.clone());
+/// clock.advance(Duration::days(2));
+/// ```
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    now: Arc<Mutex<DateTime<Local>>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+    /// Create a clock showing `now`.
+    pub fn new(now: DateTime<Local>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+    /// Set the time.
+    pub fn set(&self, now: DateTime<Local>) {
+        *self.now.lock().unwrap() = now;
+    }
+    /// Move the time forwards (or backwards, with a negative duration).
+    pub fn advance(&self, duration: chrono::Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,479 @@
+//! Configuration types that can be turned into a [FileRotate] whose suffix scheme is chosen at
+//! runtime. With feature `serde` they can be deserialized, e.g. from a configuration file.
+//!
+//! Byte sizes can be given as numbers or as strings like `"100MB"` or `"1 GiB"`, durations as
+//! strings like `"7d"` or `"12h"` (or as a number of seconds).
+//!
+//! ```
+//! # #[cfg(feature = "serde")] {
+//! use file_rotate::config::RotationConfig;
+//! use std::io::Write;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path().display().to_string().replace('\\', "/");
+//! # let config = r#"
+//! {
+//!     "path": "DIR/my-log-file",
+//!     "suffix": { "scheme": "count", "max_files": 3 },
+//!     "content_limit": { "bytes": "10 MB" }
+//! }
+//! # "#.replace("DIR", &directory);
+//! let config: RotationConfig = serde_json::from_str(&config).unwrap();
+//! let mut log = config.build().unwrap();
+//! writeln!(log, "Hello World!");
+//! # }
+//! ```
+//!
+//! The configuration can also be read from environment variables, see
+//! [RotationConfig::from_env].
+
+#[cfg(feature = "chrono04")]
+use crate::suffix::renders_separator;
+#[cfg(feature = "chrono04")]
+use crate::suffix::{DateFrom, EpochSuffix, FileLimit, TimestampSuffix};
+use crate::{
+    suffix::{CountSuffix, SequenceSuffix, SuffixScheme, DEFAULT_SEPARATOR},
+    ContentLimit, FileRotate,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fmt, path::PathBuf};
+
+/// Configuration of a [FileRotate].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RotationConfig {
+    /// Path of the current log file
+    pub path: PathBuf,
+    /// How to name rotated files and which of them to keep
+    pub suffix: SuffixConfig,
+    /// When to rotate
+    pub content_limit: ContentLimit,
+}
+
+impl RotationConfig {
+    /// Create the [FileRotate], or fail with a [ConfigError] if the configuration can't be used,
+    /// e.g. because a limit is zero.
+    pub fn build(self) -> Result<FileRotate<Box<dyn SuffixScheme + Send>>, ConfigError> {
+        let limit = match self.content_limit {
+            ContentLimit::Bytes(limit)
+            | ContentLimit::Lines(limit)
+            | ContentLimit::BytesSurpassed(limit)
+            | ContentLimit::Writes(limit) => limit,
+        };
+        if limit == 0 {
+            return Err(ConfigError::new("content_limit", "the limit can't be zero"));
+        }
+        let suffix_scheme = self.suffix.build()?;
+        Ok(FileRotate::new(
+            self.path,
+            suffix_scheme,
+            self.content_limit,
+        ))
+    }
+
+    /// Read the configuration from the environment variables `{prefix}_LOG_*`, e.g. with prefix
+    /// `MYAPP`:
+    ///
+    /// - `MYAPP_LOG_PATH`: Path of the current log file, `myapp.log` (the prefix in lower case)
+    ///   by default.
+    /// - `MYAPP_LOG_ROTATE`: When to rotate, like `"100MB"` or `"10000 lines"`, see
+    ///   [ContentLimit]'s `FromStr`. `"10MB"` by default.
+    /// - `MYAPP_LOG_MAX_BYTES`: The same as a byte size only; set only one of them.
+    /// - `MYAPP_LOG_KEEP`: Which rotated files to keep, a number of files like `"7"`, or an age
+    ///   like `"30d"` (feature `chrono04`). `"10"` by default.
+    /// - `MYAPP_LOG_SCHEME`: `count`, `sequence`, `timestamp` or `epoch` (the last two with
+    ///   feature `chrono04`). `count` by default, `timestamp` if files are kept by age.
+    ///
+    /// Other variables starting with `MYAPP_LOG_`, and values that can't be used, are rejected
+    /// with a [ConfigError] naming the variable. `MYAPP_LOG_COMPRESS` is recognized but
+    /// rejected, since this crate doesn't compress.
+    pub fn from_env(prefix: &str) -> Result<Self, ConfigError> {
+        let start = format!("{}_LOG_", prefix);
+        let mut vars = BTreeMap::new();
+        for (name, value) in std::env::vars_os() {
+            let name = match name.to_str() {
+                Some(name) if name.starts_with(&start) => name.to_string(),
+                _ => continue,
+            };
+            let value = value
+                .into_string()
+                .map_err(|_| ConfigError::new(&name, "the value is not valid UTF-8"))?;
+            vars.insert(name[start.len()..].to_string(), value);
+        }
+        Self::from_vars(prefix, vars)
+    }
+
+    /// [RotationConfig::from_env], given the variables without `{prefix}_LOG_`.
+    pub(crate) fn from_vars(
+        prefix: &str,
+        vars: BTreeMap<String, String>,
+    ) -> Result<Self, ConfigError> {
+        let name = |key: &str| format!("{}_LOG_{}", prefix, key);
+        if let Some(key) = vars.keys().find(|key| !ENV_KEYS.contains(&key.as_str())) {
+            let expected = ENV_KEYS
+                .iter()
+                .map(|key| name(key))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(ConfigError::new(
+                &name(key),
+                format_args!("unknown variable, expected one of {}", expected),
+            ));
+        }
+        let get = |key: &str| vars.get(key).map(String::as_str);
+        if get("COMPRESS").is_some() {
+            return Err(ConfigError::new(
+                &name("COMPRESS"),
+                "compression is not supported",
+            ));
+        }
+
+        let path = match get("PATH") {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(format!("{}.log", prefix.to_lowercase())),
+        };
+
+        let content_limit = match (get("ROTATE"), get("MAX_BYTES")) {
+            (Some(_), Some(_)) => {
+                return Err(ConfigError::new(
+                    &name("MAX_BYTES"),
+                    format_args!("set either {} or this", name("ROTATE")),
+                ));
+            }
+            (Some(rotate), None) => rotate
+                .parse::<ContentLimit>()
+                .map_err(|err| ConfigError::new(&name("ROTATE"), err))?,
+            (None, Some(bytes)) => ContentLimit::bytes_from_str(bytes)
+                .map_err(|err| ConfigError::new(&name("MAX_BYTES"), err))?,
+            (None, None) => ContentLimit::Bytes(DEFAULT_MAX_BYTES),
+        };
+        let limit = match content_limit {
+            ContentLimit::Bytes(limit)
+            | ContentLimit::Lines(limit)
+            | ContentLimit::BytesSurpassed(limit)
+            | ContentLimit::Writes(limit) => limit,
+        };
+        if limit == 0 {
+            let key = if get("ROTATE").is_some() {
+                "ROTATE"
+            } else {
+                "MAX_BYTES"
+            };
+            return Err(ConfigError::new(&name(key), "the limit can't be zero"));
+        }
+
+        let keep = get("KEEP").unwrap_or(DEFAULT_KEEP);
+        let max_files = keep.trim().parse::<usize>().ok();
+        #[cfg(feature = "chrono04")]
+        let age = match max_files {
+            Some(_) => None,
+            None => Some(
+                FileLimit::age_from_str(keep)
+                    .map_err(|err| ConfigError::new(&name("KEEP"), err))?,
+            ),
+        };
+        #[cfg(not(feature = "chrono04"))]
+        if max_files.is_none() {
+            return Err(ConfigError::new(
+                &name("KEEP"),
+                format_args!("{:?} is not a number of files", keep),
+            ));
+        }
+
+        let scheme = match get("SCHEME") {
+            Some(scheme) => scheme,
+            None if max_files.is_some() => "count",
+            None => "timestamp",
+        };
+        let separator = default_separator();
+        let suffix = match (scheme, max_files) {
+            ("count", Some(max_files)) => SuffixConfig::Count(CountConfig {
+                max_files,
+                separator,
+            }),
+            ("sequence", Some(max_files)) => SuffixConfig::Sequence(CountConfig {
+                max_files,
+                separator,
+            }),
+            ("count", None) | ("sequence", None) => {
+                return Err(ConfigError::new(
+                    &name("KEEP"),
+                    format_args!("{} keeps a number of files, not {:?}", scheme, keep),
+                ));
+            }
+            #[cfg(feature = "chrono04")]
+            ("timestamp", _) => SuffixConfig::Timestamp(TimestampConfig {
+                format: default_format(),
+                file_limit: age.unwrap_or(FileLimit::MaxFiles(max_files.unwrap_or_default())),
+                date_from: default_date_from(),
+                separator,
+            }),
+            #[cfg(feature = "chrono04")]
+            ("epoch", _) => SuffixConfig::Epoch(EpochConfig {
+                file_limit: age.unwrap_or(FileLimit::MaxFiles(max_files.unwrap_or_default())),
+                separator,
+            }),
+            _ => {
+                return Err(ConfigError::new(
+                    &name("SCHEME"),
+                    format_args!("unknown scheme {:?}", scheme),
+                ));
+            }
+        };
+        Ok(Self {
+            path,
+            suffix,
+            content_limit,
+        })
+    }
+}
+
+impl FileRotate<Box<dyn SuffixScheme + Send>> {
+    /// Create a [FileRotate] configured by environment variables, see
+    /// [RotationConfig::from_env].
+    pub fn from_env(prefix: &str) -> Result<Self, ConfigError> {
+        RotationConfig::from_env(prefix)?.build()
+    }
+}
+
+/// The variables read by [RotationConfig::from_env], without `{prefix}_LOG_`.
+const ENV_KEYS: &[&str] = &["PATH", "ROTATE", "MAX_BYTES", "KEEP", "SCHEME", "COMPRESS"];
+
+/// The content limit of [RotationConfig::from_env] if none is set.
+const DEFAULT_MAX_BYTES: usize = 10_000_000;
+
+/// The rotated files kept by [RotationConfig::from_env] if nothing else is set.
+const DEFAULT_KEEP: &str = "10";
+
+/// Error from [RotationConfig::build] or [RotationConfig::from_env].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    setting: String,
+    message: String,
+}
+
+impl ConfigError {
+    fn new(setting: &str, message: impl fmt::Display) -> Self {
+        Self {
+            setting: setting.to_string(),
+            message: message.to_string(),
+        }
+    }
+    /// The setting that's wrong, like `"suffix.separator"`, or the environment variable with
+    /// [RotationConfig::from_env].
+    pub fn setting(&self) -> &str {
+        &self.setting
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.setting, self.message)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Which suffix scheme to use, and its settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "scheme", rename_all = "snake_case"))]
+pub enum SuffixConfig {
+    /// [CountSuffix]
+    Count(CountConfig),
+    /// [SequenceSuffix]
+    Sequence(CountConfig),
+    /// [TimestampSuffix]
+    #[cfg(feature = "chrono04")]
+    Timestamp(TimestampConfig),
+    /// [EpochSuffix]
+    #[cfg(feature = "chrono04")]
+    Epoch(EpochConfig),
+}
+
+impl SuffixConfig {
+    /// Create the suffix scheme, or fail with a [ConfigError] if the separator is empty or the
+    /// timestamp format can't be used, see [TimestampSuffix::try_with_format].
+    pub fn build(self) -> Result<Box<dyn SuffixScheme + Send>, ConfigError> {
+        let separator = match self {
+            SuffixConfig::Count(ref config) | SuffixConfig::Sequence(ref config) => {
+                &config.separator
+            }
+            #[cfg(feature = "chrono04")]
+            SuffixConfig::Timestamp(ref config) => &config.separator,
+            #[cfg(feature = "chrono04")]
+            SuffixConfig::Epoch(ref config) => &config.separator,
+        };
+        if separator.is_empty() {
+            return Err(ConfigError::new("suffix.separator", "can't be empty"));
+        }
+        Ok(match self {
+            SuffixConfig::Count(config) => {
+                Box::new(CountSuffix::new(config.max_files).with_separator(&config.separator))
+            }
+            SuffixConfig::Sequence(config) => {
+                Box::new(SequenceSuffix::new(config.max_files).with_separator(&config.separator))
+            }
+            #[cfg(feature = "chrono04")]
+            SuffixConfig::Timestamp(config) => {
+                let suffix =
+                    TimestampSuffix::try_with_format(config.format.clone(), config.file_limit)
+                        .map_err(|err| ConfigError::new("suffix.format", err))?;
+                if renders_separator(&config.format, &config.separator) {
+                    let message = "the timestamp format renders it";
+                    return Err(ConfigError::new("suffix.separator", message));
+                }
+                Box::new(
+                    suffix
+                        .with_date_from(config.date_from)
+                        .with_separator(&config.separator),
+                )
+            }
+            #[cfg(feature = "chrono04")]
+            SuffixConfig::Epoch(config) => {
+                Box::new(EpochSuffix::new(config.file_limit).with_separator(&config.separator))
+            }
+        })
+    }
+}
+
+/// Settings of [CountSuffix] and [SequenceSuffix].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CountConfig {
+    /// Number of rotated files to keep
+    pub max_files: usize,
+    /// Separator between base name and suffix, `"."` by default
+    #[cfg_attr(feature = "serde", serde(default = "default_separator"))]
+    pub separator: String,
+}
+
+/// Settings of [TimestampSuffix].
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimestampConfig {
+    /// Timestamp format, `"%Y%m%dT%H%M%S"` by default
+    #[cfg_attr(feature = "serde", serde(default = "default_format"))]
+    pub format: String,
+    /// Which files to keep
+    pub file_limit: FileLimit,
+    /// Which point in time the timestamp is taken from, [DateFrom::Now] by default
+    #[cfg_attr(feature = "serde", serde(default = "default_date_from"))]
+    pub date_from: DateFrom,
+    /// Separator between base name and suffix, `"."` by default
+    #[cfg_attr(feature = "serde", serde(default = "default_separator"))]
+    pub separator: String,
+}
+
+/// Settings of [EpochSuffix].
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EpochConfig {
+    /// Which files to keep
+    pub file_limit: FileLimit,
+    /// Separator between base name and suffix, `"."` by default
+    #[cfg_attr(feature = "serde", serde(default = "default_separator"))]
+    pub separator: String,
+}
+
+fn default_separator() -> String {
+    DEFAULT_SEPARATOR.to_string()
+}
+
+#[cfg(feature = "chrono04")]
+fn default_format() -> String {
+    "%Y%m%dT%H%M%S".to_string()
+}
+
+#[cfg(feature = "chrono04")]
+fn default_date_from() -> DateFrom {
+    DateFrom::Now
+}
+
+/// (De)serialize a byte size, from either a number or a string like `"100MB"`.
+#[cfg(feature = "serde")]
+pub(crate) mod bytes {
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(bytes: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(*bytes as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+        struct Visitor;
+        impl de::Visitor<'_> for Visitor {
+            type Value = usize;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of bytes or a string like \"100MB\"")
+            }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<usize, E> {
+                Ok(value as usize)
+            }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<usize, E> {
+                if value < 0 {
+                    Err(E::custom("byte size can't be negative"))
+                } else {
+                    Ok(value as usize)
+                }
+            }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<usize, E> {
+                crate::parse::parse_bytes(value).map_err(E::custom)
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// (De)serialize a `chrono::Duration`, from either a number of seconds or a string like `"7d"`.
+#[cfg(all(feature = "chrono04", feature = "serde"))]
+pub(crate) mod duration {
+    use chrono::Duration;
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&crate::parse::format_duration(duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        struct Visitor;
+        impl de::Visitor<'_> for Visitor {
+            type Value = Duration;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of seconds or a string like \"7d\"")
+            }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Duration, E> {
+                self.visit_str(&value.to_string())
+            }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Duration, E> {
+                self.visit_str(&value.to_string())
+            }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Duration, E> {
+                crate::parse::parse_duration(value).map_err(E::custom)
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// (De)serialize a `std::time::Duration` like [duration], which can't be negative.
+#[cfg(all(feature = "chrono04", feature = "serde"))]
+pub(crate) mod std_duration {
+    use serde::{de::Error, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        let duration = chrono::Duration::from_std(*duration).map_err(serde::ser::Error::custom)?;
+        super::duration::serialize(&duration, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        super::duration::deserialize(deserializer)?
+            .to_std()
+            .map_err(|_| D::Error::custom("duration can't be negative"))
+    }
+}
@@ -0,0 +1,179 @@
+//! Errors with the context of the file operation that failed.
+
+use std::{error, fmt, io, path::PathBuf};
+
+/// Error from a file operation, with the path(s) it was operating on.
+///
+/// The [Write](std::io::Write) implementation of [FileRotate](crate::FileRotate) converts it into
+/// an [io::Error] of the same kind and message.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Opening the log file failed.
+    Open {
+        /// The log file
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Moving the log file during a rotation failed.
+    Rotate {
+        /// The log file
+        from: PathBuf,
+        /// Where it was to be moved
+        to: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Writing to the log file failed.
+    Write {
+        /// The log file
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Flushing the log file failed.
+    Flush {
+        /// The log file
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Reading the metadata of the log file failed.
+    Metadata {
+        /// The log file
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Deleting a file, like a rotated log file that is too old, failed.
+    Remove {
+        /// The file
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Setting the modification time of a copied log file failed.
+    SetModified {
+        /// The copy
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Pointing the symbolic link of [RotateMechanics::DateInName](crate::RotateMechanics::DateInName) at the
+    /// current log file failed.
+    Link {
+        /// The symbolic link
+        path: PathBuf,
+        /// The cause
+        source: io::Error,
+    },
+    /// Someone else changed the size of the log file, see
+    /// [FileRotateBuilder::check_size_every](crate::FileRotateBuilder::check_size_every).
+    SizeChanged {
+        /// The log file
+        path: PathBuf,
+        /// The sizes on disk and as counted
+        source: io::Error,
+    },
+    /// A write doesn't fit into
+    /// [FileRotateBuilder::max_total_bytes](crate::FileRotateBuilder::max_total_bytes), even
+    /// after deleting the rotated files.
+    QuotaExceeded {
+        /// The log file
+        path: PathBuf,
+        /// The sizes
+        source: io::Error,
+    },
+    /// Writing to or flushing the writer of [FileRotateBuilder::tee](crate::FileRotateBuilder::tee)
+    /// failed.
+    Tee {
+        /// The cause
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// The underlying [io::Error].
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            Error::Open { source, .. }
+            | Error::Rotate { source, .. }
+            | Error::Write { source, .. }
+            | Error::Flush { source, .. }
+            | Error::Metadata { source, .. }
+            | Error::Remove { source, .. }
+            | Error::SetModified { source, .. }
+            | Error::Link { source, .. }
+            | Error::SizeChanged { source, .. }
+            | Error::QuotaExceeded { source, .. }
+            | Error::Tee { source } => source,
+        }
+    }
+    /// The kind of the underlying [io::Error].
+    pub fn kind(&self) -> io::ErrorKind {
+        self.io_error().kind()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Open { path, source } => {
+                write!(f, "failed to open {}: {}", path.display(), source)
+            }
+            Error::Rotate { from, to, source } => write!(
+                f,
+                "failed to move {} to {}: {}",
+                from.display(),
+                to.display(),
+                source
+            ),
+            Error::Write { path, source } => {
+                write!(f, "failed to write to {}: {}", path.display(), source)
+            }
+            Error::Flush { path, source } => {
+                write!(f, "failed to flush {}: {}", path.display(), source)
+            }
+            Error::Metadata { path, source } => {
+                write!(
+                    f,
+                    "failed to read metadata of {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Error::Remove { path, source } => {
+                write!(f, "failed to delete {}: {}", path.display(), source)
+            }
+            Error::SetModified { path, source } => write!(
+                f,
+                "failed to set the modification time of {}: {}",
+                path.display(),
+                source
+            ),
+            Error::Link { path, source } => {
+                write!(f, "failed to link {}: {}", path.display(), source)
+            }
+            Error::SizeChanged { path, source } => {
+                write!(f, "size of {} changed: {}", path.display(), source)
+            }
+            Error::QuotaExceeded { path, source } => {
+                write!(f, "no room for writing to {}: {}", path.display(), source)
+            }
+            Error::Tee { source } => write!(f, "failed to write to tee: {}", source),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
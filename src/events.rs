@@ -0,0 +1,228 @@
+//! Receive what a [FileRotate](crate::FileRotate) does on another thread, e.g. to upload rotated
+//! files, see [FileRotate::subscribe](crate::FileRotate::subscribe).
+//!
+//! ```
+//! use file_rotate::{events::RotationEvent, suffix::CountSuffix, ContentLimit, FileRotate};
+//! use std::io::Write;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let mut log = FileRotate::new(directory.join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(1));
+//! let events = log.subscribe();
+//! let uploader = std::thread::spawn(move || {
+//!     for event in events.iter() {
+//!         if let RotationEvent::Rotated { path, .. } = event {
+//!             println!("upload {}", path.display());
+//!         }
+//!     }
+//! });
+//! writeln!(log, "Hello World!").unwrap();
+//! // Dropping the `FileRotate` ends the iteration
+//! drop(log);
+//! uploader.join().unwrap();
+//! ```
+
+use crate::Error;
+use std::{
+    collections::VecDeque,
+    io,
+    path::PathBuf,
+    sync::{
+        mpsc::{RecvError, RecvTimeoutError, TryRecvError},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// How many events an [EventReceiver] holds. When it's full, the oldest are dropped.
+pub const EVENT_CAPACITY: usize = 1024;
+
+/// Something a [FileRotate](crate::FileRotate) did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RotationEvent {
+    /// The log file was rotated to `path`.
+    Rotated {
+        /// Where the log file is now
+        path: PathBuf,
+        /// Its size in bytes
+        size: u64,
+        /// Its suffix, if the suffix scheme knows it
+        suffix: Option<String>,
+    },
+    /// A file was deleted, by the suffix scheme or e.g. for the quota.
+    Deleted {
+        /// The file
+        path: PathBuf,
+        /// Why, like `"more than 3 files"`
+        reason: String,
+    },
+    /// Something failed that writing carried on after, like those passed to
+    /// [FileRotateBuilder::on_error](crate::FileRotateBuilder::on_error).
+    Error {
+        /// The kind of the underlying error
+        kind: io::ErrorKind,
+        /// The error as text
+        message: String,
+    },
+}
+
+impl From<&Error> for RotationEvent {
+    fn from(err: &Error) -> Self {
+        RotationEvent::Error {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// What the [FileRotate](crate::FileRotate) and an [EventReceiver] share.
+#[derive(Default)]
+struct Queue {
+    state: Mutex<State>,
+    not_empty: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    events: VecDeque<RotationEvent>,
+    dropped: usize,
+    /// The [FileRotate](crate::FileRotate) is gone
+    disconnected: bool,
+    /// The [EventReceiver] is gone
+    closed: bool,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Receives the events of a [FileRotate](crate::FileRotate), like a [std::sync::mpsc::Receiver].
+///
+/// Sending never waits: If the receiver falls behind by more than [EVENT_CAPACITY] events, the
+/// oldest are dropped and counted in [EventReceiver::dropped]. Once the [FileRotate] is dropped,
+/// the events left can still be received, and then receiving fails.
+///
+/// [FileRotate]: crate::FileRotate
+pub struct EventReceiver {
+    queue: Arc<Queue>,
+}
+
+impl EventReceiver {
+    /// Wait for the next event.
+    pub fn recv(&self) -> Result<RotationEvent, RecvError> {
+        let mut state = self.queue.lock();
+        loop {
+            if let Some(event) = state.events.pop_front() {
+                return Ok(event);
+            }
+            if state.disconnected {
+                return Err(RecvError);
+            }
+            state = self
+                .queue
+                .not_empty
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+    /// The next event, if there is one.
+    pub fn try_recv(&self) -> Result<RotationEvent, TryRecvError> {
+        let mut state = self.queue.lock();
+        match state.events.pop_front() {
+            Some(event) => Ok(event),
+            None if state.disconnected => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+    /// Wait for the next event, for at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<RotationEvent, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.queue.lock();
+        loop {
+            if let Some(event) = state.events.pop_front() {
+                return Ok(event);
+            }
+            if state.disconnected {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = self
+                .queue
+                .not_empty
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+    /// Wait for events until the [FileRotate](crate::FileRotate) is dropped.
+    pub fn iter(&self) -> impl Iterator<Item = RotationEvent> + '_ {
+        std::iter::from_fn(move || self.recv().ok())
+    }
+    /// The events there are, without waiting.
+    pub fn try_iter(&self) -> impl Iterator<Item = RotationEvent> + '_ {
+        std::iter::from_fn(move || self.try_recv().ok())
+    }
+    /// How many events were dropped because the receiver fell behind.
+    pub fn dropped(&self) -> usize {
+        self.queue.lock().dropped
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        let mut state = self.queue.lock();
+        state.closed = true;
+        state.events.clear();
+    }
+}
+
+/// The receivers of the events of a [FileRotate](crate::FileRotate).
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    queues: Vec<Arc<Queue>>,
+}
+
+impl Subscribers {
+    pub(crate) fn subscribe(&mut self) -> EventReceiver {
+        // Forget the receivers that were dropped
+        self.queues.retain(|queue| !queue.lock().closed);
+        let queue = Arc::new(Queue::default());
+        self.queues.push(queue.clone());
+        EventReceiver { queue }
+    }
+    /// Pass `event` to all receivers, dropping their oldest event if they are full.
+    pub(crate) fn send(&self, event: impl FnOnce() -> RotationEvent) {
+        if self.queues.is_empty() {
+            return;
+        }
+        let event = event();
+        for queue in &self.queues {
+            let mut state = queue.lock();
+            if state.closed {
+                continue;
+            }
+            if state.events.len() >= EVENT_CAPACITY {
+                state.events.pop_front();
+                state.dropped += 1;
+            }
+            state.events.push_back(event.clone());
+            queue.not_empty.notify_one();
+        }
+    }
+}
+
+impl Drop for Subscribers {
+    fn drop(&mut self) {
+        for queue in &self.queues {
+            queue.lock().disconnected = true;
+            queue.not_empty.notify_all();
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! The file system operations of [FileRotate](crate::FileRotate): Opening, moving, copying,
+//! syncing and deleting the log files, reading their metadata, listing and creating their
+//! directory, and the symbolic link of [RotateMechanics::DateInName](crate::RotateMechanics).
+//!
+//! By default these are the functions of [std::fs]. Tests can use a [FaultyFs] (feature
+//! `test-util`) to make a chosen call fail, e.g. the rename of a rotation. The built-in suffix
+//! schemes scan, rename and delete the rotated files through the same operations (see
+//! [SuffixScheme::use_fs_ops](crate::suffix::SuffixScheme::use_fs_ops)). Only the writers of
+//! [FileRotateBuilder::writer_factory](crate::FileRotateBuilder::writer_factory), and
+//! [suffix::scan](crate::suffix::scan) and [suffix::scan_report](crate::suffix::scan_report)
+//! when called without a [FileRotate](crate::FileRotate), use [std::fs] directly.
+//!
+//! ```
+//! # #[cfg(feature = "test-util")] {
+//! use file_rotate::{fs_ops::{FaultyFs, FsOp}, suffix::CountSuffix, ContentLimit, FileRotate};
+//! use std::io::{self, Write};
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let fs = FaultyFs::new();
+//! let mut log = FileRotate::builder(directory.join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(1))
+//!     .fs_ops(fs.clone())
+//!     .build();
+//! fs.fail(FsOp::Rename, 1, io::ErrorKind::PermissionDenied);
+//! writeln!(log, "Hello").unwrap();
+//! assert_eq!(io::ErrorKind::PermissionDenied, log.take_last_error().unwrap().kind());
+//! # }
+//! ```
+
+#[cfg(any(test, feature = "test-util"))]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use std::{
+    fs::{self, File, Metadata, OpenOptions, ReadDir},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The file system operations used by [FileRotate](crate::FileRotate).
+pub trait FsOps: Send + Sync {
+    /// Open `path` with `options`.
+    fn open(&self, path: &Path, options: &OpenOptions) -> io::Result<File>;
+    /// Rename `from` to `to`, replacing `to` if it exists.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Copy the content and permissions of `from` to `to`.
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64>;
+    /// Delete the file `path`.
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    /// The metadata of `path`, following symbolic links.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+    /// The metadata of `path` itself, even if it is a symbolic link.
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata>;
+    /// Where the symbolic link `path` points to.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+    /// Create the symbolic link `link`, pointing to `target`. Only used on Unix.
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()>;
+    /// Create the directory `path` and its missing parents.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// The entries of the directory `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<ReadDir>;
+    /// Write the content and metadata of `file` to disk, e.g. a copy before the original is
+    /// removed.
+    fn sync_all(&self, file: &File) -> io::Result<()>;
+}
+
+/// The functions of [std::fs].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFs;
+
+impl FsOps for RealFs {
+    fn open(&self, path: &Path, options: &OpenOptions) -> io::Result<File> {
+        options.open(path)
+    }
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        fs::copy(from, to)
+    }
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::metadata(path)
+    }
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::symlink_metadata(path)
+    }
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+    #[cfg(unix)]
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(not(unix))]
+    fn symlink(&self, _target: &Path, _link: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+    fn read_dir(&self, path: &Path) -> io::Result<ReadDir> {
+        fs::read_dir(path)
+    }
+    fn sync_all(&self, file: &File) -> io::Result<()> {
+        file.sync_all()
+    }
+}
+
+/// An operation of [FsOps], to choose which calls of [FaultyFs] fail.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FsOp {
+    /// [FsOps::open]
+    Open,
+    /// [FsOps::rename]
+    Rename,
+    /// [FsOps::copy]
+    Copy,
+    /// [FsOps::remove_file]
+    Remove,
+    /// [FsOps::metadata]
+    Metadata,
+    /// [FsOps::symlink_metadata]
+    SymlinkMetadata,
+    /// [FsOps::read_link]
+    ReadLink,
+    /// [FsOps::symlink]
+    Symlink,
+    /// [FsOps::create_dir_all]
+    CreateDirAll,
+    /// [FsOps::read_dir]
+    ReadDir,
+    /// [FsOps::sync_all]
+    SyncAll,
+}
+
+/// [RealFs], except for the calls that are told to fail.
+///
+/// Clones share the calls counted and the failures to come, so keep one clone to script the one
+/// given to the [FileRotate](crate::FileRotate).
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Clone, Debug, Default)]
+pub struct FaultyFs {
+    state: Arc<Mutex<FaultState>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+struct FaultState {
+    calls: HashMap<FsOp, usize>,
+    /// The number of the call to fail, counted since the start, and with which error
+    failures: Vec<(FsOp, usize, io::Error)>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FaultyFs {
+    /// Create a [FaultyFs] that doesn't fail yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Fail the `nth` call of `op` from now on (1 for the next one) with an error of `kind`.
+    pub fn fail(&self, op: FsOp, nth: usize, kind: io::ErrorKind) {
+        let call = self.calls(op) + nth;
+        self.fail_with(
+            op,
+            nth,
+            io::Error::new(kind, format!("{:?} number {} failed", op, call)),
+        );
+    }
+    /// Like [FaultyFs::fail], with `error`, e.g. an OS error from
+    /// [io::Error::from_raw_os_error].
+    pub fn fail_with(&self, op: FsOp, nth: usize, error: io::Error) {
+        assert!(nth > 0);
+        let mut state = self.state.lock().unwrap();
+        let call = state.calls.get(&op).copied().unwrap_or(0) + nth;
+        state.failures.push((op, call, error));
+    }
+    /// How many times `op` has been called.
+    pub fn calls(&self, op: FsOp) -> usize {
+        let state = self.state.lock().unwrap();
+        state.calls.get(&op).copied().unwrap_or(0)
+    }
+    /// Count a call of `op`, and fail it if it's one of the failures.
+    fn call(&self, op: FsOp) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let calls = state.calls.entry(op).or_insert(0);
+        *calls += 1;
+        let call = *calls;
+        match state
+            .failures
+            .iter()
+            .position(|failure| failure.0 == op && failure.1 == call)
+        {
+            Some(idx) => Err(state.failures.remove(idx).2),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FsOps for FaultyFs {
+    fn open(&self, path: &Path, options: &OpenOptions) -> io::Result<File> {
+        self.call(FsOp::Open)?;
+        RealFs.open(path, options)
+    }
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.call(FsOp::Rename)?;
+        RealFs.rename(from, to)
+    }
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        self.call(FsOp::Copy)?;
+        RealFs.copy(from, to)
+    }
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.call(FsOp::Remove)?;
+        RealFs.remove_file(path)
+    }
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.call(FsOp::Metadata)?;
+        RealFs.metadata(path)
+    }
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.call(FsOp::SymlinkMetadata)?;
+        RealFs.symlink_metadata(path)
+    }
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.call(FsOp::ReadLink)?;
+        RealFs.read_link(path)
+    }
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        self.call(FsOp::Symlink)?;
+        RealFs.symlink(target, link)
+    }
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.call(FsOp::CreateDirAll)?;
+        RealFs.create_dir_all(path)
+    }
+    fn read_dir(&self, path: &Path) -> io::Result<ReadDir> {
+        self.call(FsOp::ReadDir)?;
+        RealFs.read_dir(path)
+    }
+    fn sync_all(&self, file: &File) -> io::Result<()> {
+        self.call(FsOp::SyncAll)?;
+        RealFs.sync_all(file)
+    }
+}
+
+/// Whether `path` exists, following symbolic links like [Path::exists].
+pub(crate) fn exists<Fs: FsOps + ?Sized>(fs: &Fs, path: &Path) -> bool {
+    fs.metadata(path).is_ok()
+}
@@ -62,9 +62,12 @@
 //!
 //! # Rotation Method #
 //!
-//! Two rotation methods are provided, but any behaviour can be implemented with the `SuffixScheme`
+//! Four rotation methods are provided, but any behaviour can be implemented with the `SuffixScheme`
 //! trait.
 //!
+//! Suffixes are separated from the base name with a `.` by default. Both schemes accept another
+//! separator through `with_separator`, for example to name files `my-log-file-1`.
+//!
 //! ## Basic count ##
 //!
 //! With `CountSuffix`, when the limit is reached in the main log file, the file is moved with
@@ -107,6 +110,29 @@
 //! assert_eq!("E", fs::read_to_string(&log_path).unwrap());
 //! ```
 //!
+//! ## Sequence ##
+//!
+//! `CountSuffix` renames every rotated file on each rotation. With many files, or on a slow file
+//! system, `SequenceSuffix` is cheaper: rotated files are numbered in increasing order, so that a
+//! rotation is a single rename plus at most one deletion. The greatest number is the newest file.
+//!
+//! ```
+//! use file_rotate::{FileRotate, ContentLimit, suffix::SequenceSuffix};
+//! use std::{fs, io::Write};
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let log_path = directory.join("my-log-file");
+//!
+//! let mut log = FileRotate::new(log_path.clone(), SequenceSuffix::new(2), ContentLimit::Bytes(1));
+//!
+//! write!(log, "ABCD");
+//! assert_eq!("B", fs::read_to_string(directory.join("my-log-file.2")).unwrap());
+//! assert_eq!("C", fs::read_to_string(directory.join("my-log-file.3")).unwrap());
+//! assert_eq!("D", fs::read_to_string(&log_path).unwrap());
+//! assert!(!directory.join("my-log-file.1").exists());
+//! ```
+//!
 //! ## Timestamp suffix ##
 //!
 //! With `TimestampSuffix`, when the limit is reached in the main log file, the file is moved with
@@ -124,6 +150,7 @@
 //! their timestamp (`FileLimit::Age`), or just maximum number of files (`FileLimit::MaxFiles`).
 //!
 //! ```
+//! # #[cfg(feature = "chrono04")] {
 //! use file_rotate::{FileRotate, ContentLimit, suffix::{TimestampSuffix, FileLimit}};
 //! use std::{fs, io::Write};
 //!
@@ -149,22 +176,78 @@
 //! assert_eq!("B", fs::read_to_string(&log.log_paths()[0]).unwrap());
 //! assert_eq!("C", fs::read_to_string(&log.log_paths()[1]).unwrap());
 //! assert_eq!("D", fs::read_to_string(&log_path).unwrap());
+//! # }
 //! ```
 //!
+//! ## Epoch suffix ##
+//!
+//! `EpochSuffix` works like `TimestampSuffix`, but the suffix is the number of seconds since the
+//! Unix epoch, like `my-log-file.1717243200`, which is independent of time zones and easy to parse.
+//!
+//! `IntervalSuffix` names each file after the interval it covers, like
+//! `my-log-file.20240601T120000-20240601T130000`, and the current file after when it was opened,
+//! like `my-log-file.20240601T130000.part`. It's for [RotateMechanics::DateInName].
+//!
 //! If you use timestamps as suffix, you can also configure files to be removed as they reach a
 //! certain age. For example:
 //! ```rust
-//! TimestampSuffix::default(FileLimit::Age(chrono::Duration::weeks(1)))
+//! # #[cfg(feature = "chrono04")] {
+//! # use file_rotate::suffix::{TimestampSuffix, FileLimit};
+//! # use std::time::Duration;
+//! TimestampSuffix::default(FileLimit::Age(Duration::from_secs(7 * 24 * 3600)));
+//! # }
+//! ```
+//!
+//! All of them need the `chrono04` feature, which is enabled by default. Without it, the crate doesn't
+//! depend on `chrono`.
+//!
+//! # Custom writers #
+//!
+//! Log files are written as plain files by default. To write through another [std::io::Write],
+//! e.g. one that encrypts or compresses, pass a factory to [FileRotateBuilder::writer_factory]:
+//!
+//! ```
+//! use file_rotate::{FileRotate, ContentLimit, suffix::CountSuffix};
+//! use std::{fs::File, io::{BufWriter, Write}};
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let mut log = FileRotate::builder(directory.join("my-log-file"), CountSuffix::new(2), ContentLimit::Lines(3))
+//!     .writer_factory(|path| File::create(path).map(BufWriter::new))
+//!     .build();
+//!
+//! writeln!(log, "Hello World!");
 //! ```
 //!
+//! # Non-blocking writes #
+//!
+//! [non_blocking::NonBlockingFileRotate] moves a [FileRotate] to its own thread, so that writes
+//! only queue the data. With the `async` feature, [asynchronous::AsyncFileRotate] does the same
+//! for async code, as a `futures::io::AsyncWrite`.
+//!
 //! # Filesystem Errors #
 //!
 //! If the directory containing the logs is deleted or somehow made inaccessible then the rotator
 //! will simply continue operating without fault. When a rotation occurs, it attempts to open a
-//! file in the directory. If it can, it will just continue logging. If it can't then the written
-//! date is sent to the void.
+//! file in the directory. If it can, it will just continue logging. If it can't, `write` returns
+//! the error and opening the file is retried on the next write.
+//!
+//! If the current log file can't be moved during a rotation, logging continues in that file and
+//! the rotation is retried on the next write. Data that `write` reports as written is never
+//! discarded.
+//!
+//! Errors name the file operation that failed and the paths involved, see [Error]. `write` and
+//! `flush` return them as [io::Error]s with the same kind and message.
 //!
 //! This logger never panics.
+//!
+//! # Debugging #
+//!
+//! With the `log-internals` feature, the crate logs what it does through the `log` crate, with
+//! target `file_rotate`: Rotations with the paths involved, deleted files with the reason, the
+//! number of rotated files found by scans, and errors. While such an event is logged, further
+//! events of the same thread are dropped, so a logger that writes to a [FileRotate] doesn't
+//! recurse.
 
 #![deny(
     missing_docs,
@@ -176,344 +259,6648 @@
 )]
 
 use std::{
-    fs::{self, File},
-    io::{self, Write},
+    collections::{HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, IoSlice, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Instant, SystemTime},
 };
 
+// First, so that its macro can be used by the others
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod cell;
+#[cfg(feature = "chrono04")]
+pub mod clock;
+pub mod config;
+mod error;
+pub mod events;
+pub mod fs_ops;
+pub mod non_blocking;
+mod parse;
+#[cfg(feature = "slog")]
+pub mod slog;
 /// Suffix scheme etc
 pub mod suffix;
+pub mod template;
+
+pub use error::Error;
+pub use parse::{ParseError, ParseErrorKind};
+
+use events::{EventReceiver, RotationEvent, Subscribers};
+use fs_ops::{exists, FsOps, RealFs};
 
 // ---
 
 /// When to move files: Condition on which a file is rotated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum ContentLimit {
     /// Cut the log at the exact size in bytes.
-    Bytes(usize),
+    ///
+    /// This splits write calls, unless [FileRotateBuilder::split_at] says otherwise: With
+    /// [SplitPolicy::LineBoundary] lines aren't split, with [SplitPolicy::WholeWrites] write calls
+    /// aren't.
+    Bytes(#[cfg_attr(feature = "serde", serde(with = "config::bytes"))] usize),
     /// Cut the log file at line breaks.
+    ///
+    /// This splits write calls that contain several lines, but never a line.
     Lines(usize),
     /// Cut the log file after surpassing size in bytes (but having written a complete buffer from a write call.)
-    BytesSurpassed(usize),
+    ///
+    /// Records are never split as long as each write call contains complete records. Unlike
+    /// [ContentLimit::Bytes] with [SplitPolicy::WholeWrites], a file can exceed the limit by up to
+    /// one write call.
+    BytesSurpassed(#[cfg_attr(feature = "serde", serde(with = "config::bytes"))] usize),
+    /// Cut the log file after a number of write calls, regardless of their content.
+    ///
+    /// A write call is never split, so this suits binary records written one per call. The count
+    /// of an existing file can't be known, so it starts at zero with [OpenBehavior::Append].
+    Writes(usize),
     // TODO: Custom(Fn(suffix: &str) -> bool)
     // Which can be used to test age in case of timestamps.
 }
 
-/// The main writer used for rotating logs.
-pub struct FileRotate<S> {
-    basepath: PathBuf,
-    file: Option<File>,
-    content_limit: ContentLimit,
-    count: usize,
-    suffix_scheme: S,
+impl ContentLimit {
+    /// Parse a byte size like `"500 MB"` or `"100MiB"` into [ContentLimit::Bytes].
+    ///
+    /// Units are case-insensitive; `KB`, `MB`, `GB` and `TB` are powers of 1000, `KiB`, `MiB`,
+    /// `GiB` and `TiB` powers of 1024. A bare number is a number of bytes.
+    pub fn bytes_from_str(input: &str) -> Result<Self, ParseError> {
+        parse::parse_bytes(input).map(ContentLimit::Bytes)
+    }
 }
 
-fn create_parent_dir(path: &Path) {
-    if let Some(dirname) = path.parent() {
-        if !dirname.exists() {
-            fs::create_dir_all(dirname).expect("create dir");
+/// Parses either a byte size like `"100MiB"` (see [ContentLimit::bytes_from_str]; a bare number
+/// means bytes), a number of lines like `"10000 lines"` or a number of write calls like
+/// `"100000 writes"`.
+///
+/// Time-based limits like `"daily"` are not supported and result in
+/// [ParseErrorKind::Unsupported].
+impl FromStr for ContentLimit {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let (number, unit) = parse::split_number(input.trim());
+        match unit.to_ascii_lowercase().as_str() {
+            "line" | "lines" => number
+                .parse::<usize>()
+                .map(ContentLimit::Lines)
+                .map_err(|_| ParseError::new(input, ParseErrorKind::InvalidNumber)),
+            "write" | "writes" => number
+                .parse::<usize>()
+                .map(ContentLimit::Writes)
+                .map_err(|_| ParseError::new(input, ParseErrorKind::InvalidNumber)),
+            "hourly" | "daily" | "weekly" | "monthly" if number.is_empty() => {
+                Err(ParseError::new(input, ParseErrorKind::Unsupported))
+            }
+            _ => Self::bytes_from_str(input),
         }
     }
 }
 
-impl<S: suffix::SuffixScheme> FileRotate<S> {
-    /// Create a new [FileRotate].
+/// What to do when writing to the secondary writer of [FileRotateBuilder::tee] fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TeeErrorPolicy {
+    /// Carry on, only the log files matter.
+    Ignore,
+    /// Return the error from `write` or `flush`. The data has been written to the log file at that
+    /// point, so retrying the write duplicates it in the log file.
+    Propagate,
+}
+
+/// What to do when a write doesn't fit into [FileRotateBuilder::max_total_bytes], even after
+/// deleting all rotated files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaPolicy {
+    /// Rotate the current log file early and delete it too, so that the write starts a new file.
+    /// A write that is larger than the quota by itself still fails.
+    RotateEarly,
+    /// Fail the write with [Error::QuotaExceeded].
+    Fail,
+}
+
+/// Where [ContentLimit::Bytes] may cut the log. See [FileRotateBuilder::split_at].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitPolicy {
+    /// At the exact byte limit, possibly in the middle of a line.
+    Exact,
+    /// After the last line break before the limit, so that lines are never split across files.
     ///
-    /// The basename of the `path` is used to create new log files by appending an extension of the
-    /// form `.N`, where N is `0..=max_files`.
+    /// A line longer than the limit goes entirely into one file, which then exceeds the limit.
+    LineBoundary,
+    /// Before a write call that doesn't fit into the file anymore, so that write calls are never
+    /// split, e.g. for binary records written with one `write_all` each.
     ///
-    /// `content_limit` specifies the limits for rotating a file.
+    /// A write call larger than the limit goes into a file of its own, which then exceeds the limit.
+    WholeWrites,
+}
+
+/// What to do with a log file that already exists when the [FileRotate] is created. See
+/// [FileRotateBuilder::open_behavior].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenBehavior {
+    /// Continue writing at its end. Its size (or number of lines) counts towards the
+    /// [ContentLimit].
+    Append,
+    /// Start with an empty file, discarding the old content.
+    Truncate,
+    /// Rotate it like a full file, then start with an empty file.
+    RotateExisting,
+}
+
+/// How the log file is opened, see [FileRotateBuilder::open_options]. It implements the
+/// `OpenOptionsExt` traits of `std::os::unix::fs` and `std::os::windows::fs`, e.g. for
+/// `custom_flags`.
+///
+/// The log file is always created if missing and opened for appending, since rotation depends on
+/// that. So unlike [OpenOptions], it can't make the file read-only, truncate it (see
+/// [OpenBehavior::Truncate] instead) or require it to be new. Custom flags and Windows access modes
+/// are passed on as they are, so they must not do that either.
+#[derive(Clone, Debug)]
+pub struct LogOpenOptions(OpenOptions);
+
+impl LogOpenOptions {
+    /// Options to create the log file if missing and open it for appending.
+    pub fn new() -> Self {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        Self(options)
+    }
+    /// Open the log file for reading too, `false` by default.
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.0.read(read);
+        self
+    }
+}
+
+impl Default for LogOpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::fs::OpenOptionsExt for LogOpenOptions {
+    fn mode(&mut self, mode: u32) -> &mut Self {
+        self.0.mode(mode);
+        self
+    }
+    fn custom_flags(&mut self, flags: i32) -> &mut Self {
+        self.0.custom_flags(flags);
+        self
+    }
+}
+
+#[cfg(windows)]
+impl std::os::windows::fs::OpenOptionsExt for LogOpenOptions {
+    fn access_mode(&mut self, access: u32) -> &mut Self {
+        self.0.access_mode(access);
+        self
+    }
+    fn share_mode(&mut self, val: u32) -> &mut Self {
+        self.0.share_mode(val);
+        self
+    }
+    fn custom_flags(&mut self, flags: u32) -> &mut Self {
+        self.0.custom_flags(flags);
+        self
+    }
+    fn attributes(&mut self, val: u32) -> &mut Self {
+        self.0.attributes(val);
+        self
+    }
+    fn security_qos_flags(&mut self, flags: u32) -> &mut Self {
+        self.0.security_qos_flags(flags);
+        self
+    }
+}
+
+/// How the current log file is moved aside when rotating. See [FileRotateBuilder::rotate_mechanics].
+///
+/// All of them count the content limit, call [FileRotateBuilder::on_rotated] and handle failures
+/// the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotateMechanics {
+    /// Close the file, rename it and open a new one. Renaming an open file fails on Windows, so
+    /// this is the default there.
+    CloseRenameReopen,
+    /// Rename the file while it's still open, then close it and open a new one. Everything written
+    /// before the rename stays in the rotated file, even if the writer buffers. The default except
+    /// on Windows.
+    RenameKeepFdThenReopen,
+    /// Copy the file to its rotated name, then truncate it and keep writing to it.
     ///
-    /// # Panics
+    /// This is for programs that keep the log file open and can't be told to reopen it, at the
+    /// cost of copying. Writes from other processes between the copy and the truncation are lost.
+    /// The writer has to append, like the default one does, or it keeps writing at its old
+    /// position after the truncation.
+    CopyTruncate,
+    /// Never rename: The log file is the newest rotated file, like `my-log-file.20240601T000000`
+    /// with [suffix::TimestampSuffix], and rotating closes it and continues in a new one.
     ///
-    /// Panics if `bytes == 0` or `lines == 0`.
-    pub fn new<P: AsRef<Path>>(path: P, suffix_scheme: S, content_limit: ContentLimit) -> Self {
-        match content_limit {
-            ContentLimit::Bytes(bytes) => {
-                assert!(bytes > 0);
-            }
-            ContentLimit::Lines(lines) => {
-                assert!(lines > 0);
-            }
-            ContentLimit::BytesSurpassed(bytes) => {
-                assert!(bytes > 0);
-            }
-        };
-
-        let basepath = path.as_ref().to_path_buf();
-        create_parent_dir(&basepath);
+    /// No file is ever moved while another program, like a log shipper, reads it, and a rotation
+    /// can't fail halfway. The suffix scheme names and deletes the files as usual, but counts the
+    /// current file among them, so it shouldn't be [suffix::CountSuffix], which renames its
+    /// files. [FileRotate::current_path] is the current file. With [OpenBehavior::Append], the
+    /// newest existing file is continued, otherwise the [FileRotate] starts with a new file.
+    DateInName {
+        /// Keep a symbolic link at the base path that points to the current file (on Unix only)
+        symlink: bool,
+    },
+}
 
-        Self {
-            file: File::create(&basepath).ok(),
-            basepath,
-            content_limit,
-            count: 0,
-            suffix_scheme,
+impl Default for RotateMechanics {
+    fn default() -> Self {
+        if cfg!(windows) {
+            RotateMechanics::CloseRenameReopen
+        } else {
+            RotateMechanics::RenameKeepFdThenReopen
         }
     }
-    /// Get paths of rotated log files (excluding the original/current log file)
-    pub fn log_paths(&mut self) -> Vec<PathBuf> {
-        self.suffix_scheme.log_paths(&self.basepath)
-    }
+}
 
-    fn rotate(&mut self) -> io::Result<()> {
-        let suffix = self.suffix_scheme.rotate(&self.basepath);
-        let path = PathBuf::from(format!("{}.{}", self.basepath.display(), suffix));
+/// What to do with a rotated log file, returned by the callback of
+/// [FileRotateBuilder::on_rotated].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveDecision {
+    /// Leave the file to the suffix scheme.
+    KeepLocal,
+    /// Delete the file, e.g. because it has been uploaded.
+    DeleteNow,
+    /// Ask again at the next rotation, e.g. because the upload is still running.
+    Pending,
+}
 
-        create_parent_dir(&path);
+/// What to do when the rotated name of the log file is taken already, returned by the callback
+/// of [FileRotateBuilder::on_collision].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CollisionAction {
+    /// Replace the existing file.
+    Overwrite,
+    /// Rotate the suffix scheme once more, which moves the existing files further (like
+    /// [suffix::CountSuffix] does) or picks the next suffix, and move the log file to the suffix
+    /// it returns then. If that is the same taken path, the rotation fails like with
+    /// [CollisionAction::Abort]. The default without [FileRotateBuilder::on_collision].
+    Cascade,
+    /// Move the log file to this path instead. The suffix scheme only finds it there if it is
+    /// next to the other rotated files and the scheme recognizes its suffix, and it shouldn't be a
+    /// name the scheme hands out later, or it's replaced then.
+    RenameTo(PathBuf),
+    /// Don't rotate now, and keep writing to the current file. The rotation fails with
+    /// [Error::Rotate] of kind [io::ErrorKind::AlreadyExists], and is retried at the next write.
+    Abort,
+}
 
-        let _ = self.file.take();
+/// How many errors [FileRotate::recent_errors] remembers.
+pub const ERROR_HISTORY: usize = 10;
 
-        // TODO should handle this error (and others)
-        let _ = fs::rename(&self.basepath, &path);
+/// Records longer than this are not carried over, see [FileRotateBuilder::carry_over_records].
+pub const MAX_CARRIED_BYTES: usize = 64 * 1024;
 
-        self.file = Some(File::create(&self.basepath)?);
-        self.count = 0;
+/// An error that writing carried on after, see [FileRotate::recent_errors].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecentError {
+    /// When it happened
+    pub time: SystemTime,
+    /// The error as text
+    pub message: String,
+}
+
+/// A file listed in a [StateSnapshot].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotFile {
+    /// Full path of the file
+    pub path: PathBuf,
+    /// Its suffix, if the suffix scheme knows it
+    pub suffix: Option<String>,
+    /// Its size in bytes, None if it couldn't be read
+    pub size: Option<u64>,
+}
 
+/// How a [FileRotate] sees its files, e.g. to paste into a bug report. See
+/// [FileRotate::snapshot]. It displays as one `name: value` line per field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateSnapshot {
+    /// The [ContentLimit], as debug text
+    pub content_limit: String,
+    /// The [RotateMechanics], as debug text
+    pub rotate_mechanics: String,
+    /// The quota of [FileRotateBuilder::max_total_bytes] and its policy, as debug text
+    pub quota: Option<String>,
+    /// Path of the current log file
+    pub current_path: PathBuf,
+    /// Bytes in the current log file, as far as the [FileRotate] knows
+    pub current_bytes: u64,
+    /// How much of the content limit the current log file uses up, in its unit
+    pub count: usize,
+    /// When the log file was last rotated by this [FileRotate], if at all
+    pub last_rotation: Option<SystemTime>,
+    /// The rotated files, oldest first
+    pub rotated: Vec<SnapshotFile>,
+    /// Where a rotation that failed is retried to
+    pub pending_rotation: Option<PathBuf>,
+    /// Rotated files still pending for [FileRotateBuilder::on_rotated], oldest first
+    pub pending_archive: Vec<PathBuf>,
+    /// Files left to be deleted, see [FileRotateBuilder::defer_deletions]
+    pub pending_deletion: Vec<PathBuf>,
+    /// The last errors that writing carried on after, oldest first
+    pub recent_errors: Vec<RecentError>,
+}
+
+impl std::fmt::Display for StateSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let time = |time: Option<SystemTime>| match time {
+            Some(time) => match time.duration_since(SystemTime::UNIX_EPOCH) {
+                Ok(since) => format!(
+                    "{}.{:03} (Unix time)",
+                    since.as_secs(),
+                    since.subsec_millis()
+                ),
+                Err(_) => format!("{:?}", time),
+            },
+            None => "never".to_string(),
+        };
+        writeln!(f, "content limit: {}", self.content_limit)?;
+        writeln!(f, "rotate mechanics: {}", self.rotate_mechanics)?;
+        writeln!(f, "quota: {}", self.quota.as_deref().unwrap_or("none"))?;
+        writeln!(f, "current path: {}", self.current_path.display())?;
+        writeln!(f, "current bytes: {}", self.current_bytes)?;
+        writeln!(f, "count: {}", self.count)?;
+        writeln!(f, "last rotation: {}", time(self.last_rotation))?;
+        writeln!(f, "rotated files: {}", self.rotated.len())?;
+        for file in &self.rotated {
+            let size = match file.size {
+                Some(size) => format!("{} bytes", size),
+                None => "size unknown".to_string(),
+            };
+            writeln!(f, "  {} ({})", file.path.display(), size)?;
+        }
+        match self.pending_rotation {
+            Some(ref path) => writeln!(f, "pending rotation: to {}", path.display())?,
+            None => writeln!(f, "pending rotation: none")?,
+        }
+        writeln!(f, "pending archive: {}", self.pending_archive.len())?;
+        for path in &self.pending_archive {
+            writeln!(f, "  {}", path.display())?;
+        }
+        writeln!(f, "pending deletion: {}", self.pending_deletion.len())?;
+        for path in &self.pending_deletion {
+            writeln!(f, "  {}", path.display())?;
+        }
+        writeln!(f, "recent errors: {}", self.recent_errors.len())?;
+        for err in &self.recent_errors {
+            writeln!(f, "  {}: {}", time(Some(err.time)), err.message)?;
+        }
         Ok(())
     }
 }
 
-impl<S: suffix::SuffixScheme> Write for FileRotate<S> {
-    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
-        let written = buf.len();
-        match self.content_limit {
-            ContentLimit::Bytes(bytes) => {
-                while self.count + buf.len() > bytes {
-                    let bytes_left = bytes - self.count;
-                    if let Some(ref mut file) = self.file {
-                        file.write_all(&buf[..bytes_left])?;
-                    }
-                    self.rotate()?;
-                    buf = &buf[bytes_left..];
-                }
-                self.count += buf.len();
-                if let Some(ref mut file) = self.file {
-                    file.write_all(&buf)?;
-                }
-            }
-            ContentLimit::Lines(lines) => {
-                while let Some((idx, _)) = buf.iter().enumerate().find(|(_, byte)| *byte == &b'\n')
-                {
-                    if let Some(ref mut file) = self.file {
-                        file.write_all(&buf[..idx + 1])?;
-                    }
-                    self.count += 1;
-                    buf = &buf[idx + 1..];
-                    if self.count >= lines {
-                        self.rotate()?;
-                    }
-                }
-                if let Some(ref mut file) = self.file {
-                    file.write_all(buf)?;
-                }
-            }
-            ContentLimit::BytesSurpassed(bytes) => {
-                if self.count > bytes {
-                    self.rotate()?
-                }
-                if let Some(ref mut file) = self.file {
-                    file.write_all(&buf)?;
+/// What [FileRotate::close] or [FileRotate::into_parts] left behind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseReport {
+    /// Path of the last log file
+    pub path: PathBuf,
+    /// Size of the last log file in bytes (0 if it doesn't exist)
+    pub bytes: u64,
+}
+
+/// Asks a [FileRotate] to rotate or reopen its log file at the next write. See
+/// [FileRotate::control_handle].
+///
+/// Requests only set an atomic flag, so they can be made from a signal handler, e.g. by
+/// registering [ControlHandle::reopen_flag] with `signal_hook::flag::register`:
+///
+/// ```
+/// # use file_rotate::{FileRotate, ContentLimit, suffix::CountSuffix};
+/// # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+/// # let log = FileRotate::new(directory.path().join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(100));
+/// # #[cfg(unix)]
+/// signal_hook::flag::register(signal_hook::consts::SIGHUP, log.control_handle().reopen_flag())
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ControlHandle {
+    rotate: Arc<AtomicBool>,
+    reopen: Arc<AtomicBool>,
+}
+
+impl ControlHandle {
+    /// Rotate the log file at the next write, regardless of the [ContentLimit].
+    pub fn request_rotate(&self) {
+        self.rotate.store(true, Ordering::SeqCst);
+    }
+    /// Close and reopen the log file at the next write, e.g. after an external tool like
+    /// `logrotate` has moved it.
+    pub fn request_reopen(&self) {
+        self.reopen.store(true, Ordering::SeqCst);
+    }
+    /// The flag set by [ControlHandle::request_rotate].
+    pub fn rotate_flag(&self) -> Arc<AtomicBool> {
+        self.rotate.clone()
+    }
+    /// The flag set by [ControlHandle::request_reopen].
+    pub fn reopen_flag(&self) -> Arc<AtomicBool> {
+        self.reopen.clone()
+    }
+}
+
+/// Opens the writer of the current log file, given the file system operations and its path.
+type OpenFn<W> = Box<dyn Fn(&dyn FsOps, &Path) -> io::Result<W> + Send + Sync>;
+
+/// Decides what happens to a rotated log file, given its path.
+type ArchiveFn = Box<dyn Fn(&Path) -> ArchiveDecision + Send + Sync>;
+
+/// Is told about a file the suffix scheme doesn't recognize, given its path.
+type UnrecognizedFn = Box<dyn Fn(&Path) + Send + Sync>;
+
+/// Decides what to do when the rotated name of the log file is taken, given that path.
+type CollisionFn = Box<dyn Fn(&Path) -> CollisionAction + Send + Sync>;
+
+/// Is told about a failure that writing carried on after.
+type ErrorFn = Box<dyn Fn(&Error) + Send + Sync>;
+
+/// Decides whether a line starts a record, given the line without its line break.
+type RecordStartFn = Box<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
+/// The main writer used for rotating logs.
+///
+/// By default the log files are written as plain [File]s. Any other [Write] can be used with
+/// [FileRotateBuilder::writer_factory].
+pub struct FileRotate<S, W: Write = File, Fs: FsOps = RealFs> {
+    basepath: PathBuf,
+    /// The file written to, `basepath` unless rotating with [RotateMechanics::DateInName]
+    active: PathBuf,
+    /// What the suffix scheme appends suffixes to, `basepath` unless there's an archive directory
+    archive_basepath: PathBuf,
+    file: Option<W>,
+    content_limit: ContentLimit,
+    split_at: SplitPolicy,
+    count: usize,
+    shared: bool,
+    check_size_every: usize,
+    /// Write calls since the size of the log file was last compared with `count`
+    writes_since_check: usize,
+    quota: Option<(u64, QuotaPolicy)>,
+    flush_interval: Option<std::time::Duration>,
+    /// Since when the writer may hold data that hasn't been flushed, with a `flush_interval`
+    unflushed_since: Option<Instant>,
+    /// Bytes in the current log file, for the quota
+    active_bytes: u64,
+    /// Bytes in the rotated files, for the quota, or None if they have to be looked up again
+    rotated_bytes: Option<u64>,
+    lazy_recreate: bool,
+    rotate_mechanics: RotateMechanics,
+    /// Identifies the file on disk that `file` writes to
+    file_id: Option<(u64, u64)>,
+    /// Where the current file should have been moved by a rotation that failed
+    pending_rotation: Option<PathBuf>,
+    last_rotation: Option<SystemTime>,
+    min_rotation_interval: std::time::Duration,
+    /// When the last rotation was attempted, for `min_rotation_interval`
+    rotation_attempted: Option<Instant>,
+    /// Rotations for the content limit put off because of `min_rotation_interval`
+    deferred_rotations: u64,
+    record_tail: Option<RecordTail>,
+    rotation_markers: bool,
+    /// The line to start the new log file with, see [FileRotateBuilder::rotation_markers]
+    start_marker: Option<String>,
+    /// The last byte written to the current log file isn't a line break
+    mid_line: bool,
+    suffix_scheme: S,
+    open: OpenFn<W>,
+    fs: Arc<Fs>,
+    tee: Option<(Box<dyn Write + Send>, TeeErrorPolicy)>,
+    control: ControlHandle,
+    on_collision: Option<CollisionFn>,
+    on_rotated: Option<ArchiveFn>,
+    /// Rotated files the callback returned [ArchiveDecision::Pending] for, oldest first
+    pending_archive: VecDeque<PathBuf>,
+    max_pending: usize,
+    /// Files the suffix scheme left to be deleted, see [FileRotateBuilder::defer_deletions]
+    pending_deletion: VecDeque<suffix::Deleted>,
+    on_unrecognized: Option<UnrecognizedFn>,
+    /// Files already passed to `on_unrecognized`
+    reported_unrecognized: HashSet<PathBuf>,
+    delete_unrecognized: bool,
+    on_error: Option<ErrorFn>,
+    subscribers: Subscribers,
+    /// The last errors passed to `report`, oldest first
+    error_history: Mutex<VecDeque<RecentError>>,
+    /// The first error since the last [FileRotate::sync] that writing carried on after
+    deferred_error: Option<Error>,
+}
+
+/// How much of `content_limit` the existing file at `path` uses up.
+fn existing_count<Fs: FsOps + ?Sized>(
+    fs: &Fs,
+    path: &Path,
+    content_limit: &ContentLimit,
+) -> io::Result<usize> {
+    match content_limit {
+        ContentLimit::Bytes(_) | ContentLimit::BytesSurpassed(_) => {
+            Ok(fs.metadata(path)?.len() as usize)
+        }
+        ContentLimit::Lines(_) => {
+            let mut reader = BufReader::new(fs.open(path, OpenOptions::new().read(true))?);
+            let mut lines = 0;
+            loop {
+                let buf = reader.fill_buf()?;
+                if buf.is_empty() {
+                    return Ok(lines);
                 }
-                self.count += buf.len();
+                lines += count_newlines(buf);
+                let len = buf.len();
+                reader.consume(len);
             }
         }
-        Ok(written)
+        ContentLimit::Writes(_) => Ok(0),
     }
+}
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.file
-            .as_mut()
-            .map(|file| file.flush())
-            .unwrap_or(Ok(()))
+/// Make `link` a symbolic link to `target`, replacing an older link but not a file.
+#[cfg(unix)]
+fn link<Fs: FsOps + ?Sized>(fs: &Fs, target: &Path, link: &Path) -> io::Result<()> {
+    // Relative, so that the directory can be moved
+    let target = if target.parent() == link.parent() {
+        Path::new(target.file_name().expect("target.file_name()"))
+    } else {
+        target
+    };
+    match fs.symlink_metadata(link) {
+        Ok(metadata) if !metadata.file_type().is_symlink() => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "not a symbolic link",
+        )),
+        Ok(_) if fs.read_link(link)? == target => Ok(()),
+        Ok(_) => {
+            fs.remove_file(link)?;
+            fs.symlink(target, link)
+        }
+        Err(_) => fs.symlink(target, link),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{suffix::*, *};
-    use tempdir::TempDir;
-
-    // Just useful to debug why test doesn't succeed
-    #[allow(dead_code)]
-    fn list(dir: &Path) {
-        let filenames = fs::read_dir(dir)
-            .unwrap()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.path().is_file())
-            .map(|entry| entry.file_name())
-            .collect::<Vec<_>>();
-        println!("Files on disk: {:?}", filenames);
-    }
+#[cfg(not(unix))]
+fn link<Fs: FsOps + ?Sized>(_fs: &Fs, _target: &Path, _link: &Path) -> io::Result<()> {
+    Ok(())
+}
 
-    #[test]
-    fn timestamp_max_files_rotation() {
-        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
-        let log_path = tmp_dir.path().join("log");
+/// Device and inode number of the file at `path`.
+#[cfg(unix)]
+fn file_id<Fs: FsOps + ?Sized>(fs: &Fs, path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs.metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
 
-        let mut log = FileRotate::new(
-            &log_path,
-            TimestampSuffix::default(FileLimit::MaxFiles(4)),
-            ContentLimit::Lines(2),
-        );
+#[cfg(not(unix))]
+fn file_id<Fs: FsOps + ?Sized>(_fs: &Fs, _path: &Path) -> Option<(u64, u64)> {
+    None
+}
 
-        // Write 9 lines
-        // This should result in 5 files in total (4 rotated files). The main file will have one line.
-        write!(log, "a\nb\nc\nd\ne\nf\ng\nh\ni\n").unwrap();
-        let log_paths = log.log_paths();
-        assert_eq!(log_paths.len(), 4);
+/// Position of the `n`th (counting from zero) line break in `buf`.
+fn nth_newline(buf: &[u8], n: usize) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    return memchr::memchr_iter(b'\n', buf).nth(n);
+    #[cfg(not(feature = "memchr"))]
+    return buf
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(n)
+        .map(|(idx, _)| idx);
+}
 
-        // Log names should be sorted. Low (old timestamp) to high (more recent timestamp)
-        let mut log_paths_sorted = log_paths.clone();
-        log_paths_sorted.sort();
-        assert_eq!(log_paths, log_paths_sorted);
+/// Position of the last line break in `buf`.
+fn last_newline(buf: &[u8]) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    return memchr::memrchr(b'\n', buf);
+    #[cfg(not(feature = "memchr"))]
+    return buf.iter().rposition(|byte| *byte == b'\n');
+}
 
-        assert_eq!("a\nb\n", fs::read_to_string(&log_paths[0]).unwrap());
-        assert_eq!("c\nd\n", fs::read_to_string(&log_paths[1]).unwrap());
-        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[2]).unwrap());
-        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[3]).unwrap());
-        assert_eq!("i\n", fs::read_to_string(&log_path).unwrap());
+fn count_newlines(buf: &[u8]) -> usize {
+    #[cfg(feature = "memchr")]
+    return memchr::memchr_iter(b'\n', buf).count();
+    #[cfg(not(feature = "memchr"))]
+    return buf.iter().filter(|byte| **byte == b'\n').count();
+}
 
-        // Write 4 more lines
-        write!(log, "j\nk\nl\nm\n").unwrap();
-        let log_paths = log.log_paths();
-        assert_eq!(log_paths.len(), 4);
-        let mut log_paths_sorted = log_paths.clone();
-        log_paths_sorted.sort();
-        assert_eq!(log_paths, log_paths_sorted);
+/// The record the log file ends in, to start the next file with, see
+/// [FileRotateBuilder::carry_over_records].
+struct RecordTail {
+    starts_record: RecordStartFn,
+    /// The record written so far, or None if it's longer than [MAX_CARRIED_BYTES]
+    record: Option<Vec<u8>>,
+    /// The last byte written isn't a line break
+    mid_line: bool,
+    /// The record the previous file ended in, until the first write into the new file
+    carried: Option<Vec<u8>>,
+}
 
-        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[0]).unwrap());
-        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[1]).unwrap());
-        assert_eq!("i\nj\n", fs::read_to_string(&log_paths[2]).unwrap());
-        assert_eq!("k\nl\n", fs::read_to_string(&log_paths[3]).unwrap());
-        assert_eq!("m\n", fs::read_to_string(&log_path).unwrap());
+impl RecordTail {
+    fn new(starts_record: RecordStartFn) -> Self {
+        Self {
+            starts_record,
+            record: Some(Vec::new()),
+            mid_line: false,
+            carried: None,
+        }
     }
-    #[test]
+    /// Keep track of the record that `buf` is written into.
+    fn written(&mut self, buf: &[u8]) {
+        for line in buf.split_inclusive(|byte| *byte == b'\n') {
+            if !self.mid_line && (self.starts_record)(line.strip_suffix(b"\n").unwrap_or(line)) {
+                self.record = Some(Vec::new());
+            }
+            if let Some(ref mut record) = self.record {
+                record.extend_from_slice(line);
+                if record.len() > MAX_CARRIED_BYTES {
+                    self.record = None;
+                }
+            }
+            self.mid_line = !line.ends_with(b"\n");
+        }
+    }
+    /// The log file was rotated, so the record continues in a new file.
+    fn rotated(&mut self) {
+        self.carried = self.record.take();
+    }
+    /// What to write into the new log file before `buf`, if `buf` continues the record that the
+    /// previous file ended in.
+    fn carry(&mut self, buf: &[u8]) -> Option<Vec<u8>> {
+        let carried = self.carried.take()?;
+        let first_line = match nth_newline(buf, 0) {
+            Some(idx) => &buf[..idx],
+            None => buf,
+        };
+        let continues = self.mid_line || !(self.starts_record)(first_line);
+        if continues && !carried.is_empty() {
+            self.record = Some(carried.clone());
+            Some(carried)
+        } else {
+            self.record = Some(Vec::new());
+            None
+        }
+    }
+}
+
+/// `time` for humans, like `2024-06-01T12:00:00` in local time, or in Unix time without chrono.
+fn display_time(time: SystemTime) -> String {
     #[cfg(feature = "chrono04")]
-    fn timestamp_age_rotation() {
-        // In order not to have to sleep, and keep it deterministic, let's already create the log files and see how FileRotate
-        // cleans up the old ones.
-        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
-        let dir = tmp_dir.path();
-        let log_path = dir.join("log");
+    return chrono::DateTime::<chrono::Local>::from(time)
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+    #[cfg(not(feature = "chrono04"))]
+    return match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => format!("{} (Unix time)", since.as_secs()),
+        Err(_) => format!("{:?}", time),
+    };
+}
 
-        // One recent file:
-        let recent_file = chrono::offset::Local::now().format("log.%Y%m%dT%H%M%S").to_string();
-        File::create(dir.join(&recent_file)).unwrap();
-        // Two very old files:
-        File::create(dir.join("log.20200825T151133")).unwrap();
-        File::create(dir.join("log.20200825T151133.1")).unwrap();
+/// Drop the first `n` bytes of `bufs`, and the empty buffers then at the front, like
+/// `IoSlice::advance_slices` from Rust 1.81 does.
+fn advance(bufs: &mut Vec<&[u8]>, mut n: usize) {
+    let mut done = 0;
+    for buf in bufs.iter() {
+        if buf.len() > n {
+            break;
+        }
+        n -= buf.len();
+        done += 1;
+    }
+    bufs.drain(..done);
+    if let Some(first) = bufs.first_mut() {
+        *first = &first[n..];
+    }
+}
 
-        let mut log = FileRotate::new(
+/// Report the bytes that made it into the log files, or `err` if there are none.
+fn partially_written(written: usize, err: Error) -> Result<usize, Error> {
+    if written > 0 {
+        Ok(written)
+    } else {
+        Err(err)
+    }
+}
+
+/// Create the directory of `path` if it is missing. The error names the directory.
+fn create_parent_dir<Fs: FsOps + ?Sized>(fs: &Fs, path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dirname) if !exists(fs, dirname) => fs.create_dir_all(dirname).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("failed to create {}: {}", dirname.display(), err),
+            )
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Rename `from` to `to`, or copy and remove it if they are on different file systems. Other
+/// failures of renaming are returned as they are.
+fn move_file<Fs: FsOps + ?Sized>(
+    fs: &Fs,
+    from: &Path,
+    to: &Path,
+    errors: &mut Vec<Error>,
+) -> io::Result<()> {
+    match fs.rename(from, to) {
+        Err(err) if err.raw_os_error() == Some(EXDEV) => {
+            if copy_file(fs, from, to, errors).is_err() {
+                errors.extend(delete_file(fs, to).err());
+                return Err(err);
+            }
+            let removed = fs.remove_file(from);
+            if removed.is_err() {
+                errors.extend(delete_file(fs, to).err());
+            }
+            removed
+        }
+        renamed => renamed,
+    }
+}
+
+/// The OS error of renaming to another file system, which `io::ErrorKind::CrossesDevices` only
+/// stands for from Rust 1.85 on.
+#[cfg(windows)]
+const EXDEV: i32 = 17; // ERROR_NOT_SAME_DEVICE
+#[cfg(target_os = "wasi")]
+const EXDEV: i32 = 75;
+#[cfg(not(any(windows, target_os = "wasi")))]
+const EXDEV: i32 = 18;
+
+/// Copy `from` to `to`, including its modification time as far as possible, so that the copy
+/// doesn't look like it was just written. The copy is synced to disk, since the caller removes or
+/// empties `from` next.
+fn copy_file<Fs: FsOps + ?Sized>(
+    fs: &Fs,
+    from: &Path,
+    to: &Path,
+    errors: &mut Vec<Error>,
+) -> io::Result<()> {
+    let modified = fs.metadata(from).and_then(|metadata| metadata.modified());
+    fs.copy(from, to)?;
+    let file = fs.open(to, OpenOptions::new().write(true))?;
+    if let Ok(modified) = modified {
+        if let Err(source) = file.set_modified(modified) {
+            errors.push(Error::SetModified {
+                path: to.to_path_buf(),
+                source,
+            });
+        }
+    }
+    fs.sync_all(&file)
+}
+
+/// Delete `path`, unless it is gone already.
+pub(crate) fn delete_file<Fs: FsOps + ?Sized>(fs: &Fs, path: &Path) -> Result<(), Error> {
+    match fs.remove_file(path) {
+        Err(source) if source.kind() != io::ErrorKind::NotFound => Err(Error::Remove {
+            path: path.to_path_buf(),
+            source,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Builder for a [FileRotate] with non-default settings. See [FileRotate::builder].
+pub struct FileRotateBuilder<S, W = File, Fs = RealFs> {
+    basepath: PathBuf,
+    content_limit: ContentLimit,
+    split_at: SplitPolicy,
+    open_behavior: OpenBehavior,
+    shared: bool,
+    check_size_every: usize,
+    quota: Option<(u64, QuotaPolicy)>,
+    flush_interval: Option<std::time::Duration>,
+    min_rotation_interval: std::time::Duration,
+    lazy_create: bool,
+    lazy_recreate: bool,
+    rotate_mechanics: RotateMechanics,
+    archive_dir: Option<PathBuf>,
+    suffix_scheme: S,
+    open: OpenFn<W>,
+    fs: Fs,
+    tee: Option<(Box<dyn Write + Send>, TeeErrorPolicy)>,
+    on_collision: Option<CollisionFn>,
+    on_rotated: Option<ArchiveFn>,
+    max_pending: usize,
+    on_unrecognized: Option<UnrecognizedFn>,
+    delete_unrecognized: bool,
+    defer_deletions: bool,
+    starts_record: Option<RecordStartFn>,
+    rotation_markers: bool,
+    on_error: Option<ErrorFn>,
+}
+
+impl<S, W, Fs> FileRotateBuilder<S, W, Fs> {
+    /// Write through the writers returned by `factory` instead of plain files, e.g. to encrypt or
+    /// compress the logs.
+    ///
+    /// `factory` is called with the path of the current log file whenever it has to be (re)opened.
+    /// It should append to an existing file, since the file is reopened when a rotation fails.
+    /// Rotation still renames the files on disk, so the writer should write to that path. It must
+    /// flush everything on drop, since it is dropped when its file is rotated.
+    pub fn writer_factory<W2, F>(self, factory: F) -> FileRotateBuilder<S, W2, Fs>
+    where
+        F: Fn(&Path) -> io::Result<W2> + Send + Sync + 'static,
+    {
+        FileRotateBuilder {
+            basepath: self.basepath,
+            content_limit: self.content_limit,
+            split_at: self.split_at,
+            open_behavior: self.open_behavior,
+            shared: self.shared,
+            check_size_every: self.check_size_every,
+            quota: self.quota,
+            flush_interval: self.flush_interval,
+            min_rotation_interval: self.min_rotation_interval,
+            lazy_create: self.lazy_create,
+            lazy_recreate: self.lazy_recreate,
+            rotate_mechanics: self.rotate_mechanics,
+            archive_dir: self.archive_dir,
+            suffix_scheme: self.suffix_scheme,
+            open: Box::new(move |_, path| factory(path)),
+            fs: self.fs,
+            tee: self.tee,
+            on_collision: self.on_collision,
+            on_rotated: self.on_rotated,
+            max_pending: self.max_pending,
+            on_unrecognized: self.on_unrecognized,
+            delete_unrecognized: self.delete_unrecognized,
+            defer_deletions: self.defer_deletions,
+            starts_record: self.starts_record,
+            rotation_markers: self.rotation_markers,
+            on_error: self.on_error,
+        }
+    }
+    /// Also write everything to `writer`, e.g. to [std::io::stdout].
+    ///
+    /// Rotation only concerns the log files; `writer` just gets the stream of bytes. `policy`
+    /// decides whether errors from `writer` are returned.
+    pub fn tee<T: Write + Send + 'static>(mut self, writer: T, policy: TeeErrorPolicy) -> Self {
+        self.tee = Some((Box::new(writer), policy));
+        self
+    }
+    /// Choose where [ContentLimit::Bytes] cuts the log, [SplitPolicy::Exact] by default.
+    ///
+    /// The other limits don't split lines anyway, and [ContentLimit::BytesSurpassed] and
+    /// [ContentLimit::Writes] don't split write calls either.
+    pub fn split_at(mut self, policy: SplitPolicy) -> Self {
+        self.split_at = policy;
+        self
+    }
+    /// Choose what happens to an existing log file, [OpenBehavior::Truncate] by default.
+    ///
+    /// With [FileRotateBuilder::writer_factory], the file is truncated before `factory` is called,
+    /// and appending counts the bytes (or lines) as they are on disk.
+    pub fn open_behavior(mut self, behavior: OpenBehavior) -> Self {
+        self.open_behavior = behavior;
+        self
+    }
+    /// Let other writers (e.g. other processes) write to the same log file, `false` by default.
+    ///
+    /// The files are opened in append mode (`O_APPEND`, `FILE_APPEND_DATA` on Windows) anyway, so
+    /// that every write goes to the end of the file. With `shared`, the size of the file on disk
+    /// is also checked before every write, so that [ContentLimit::Bytes] and
+    /// [ContentLimit::BytesSurpassed] count the bytes of the other writers too. [ContentLimit::Lines]
+    /// only counts our own lines.
+    ///
+    /// After renaming, other writers keep writing to the rotated file until they reopen the log
+    /// file. On Unix, a shared [FileRotate] notices when another one has rotated the file, and
+    /// reopens it.
+    pub fn shared(mut self, shared: bool) -> Self {
+        self.shared = shared;
+        self
+    }
+    /// Compare the bytes counted towards [ContentLimit::Bytes] or [ContentLimit::BytesSurpassed]
+    /// with the size of the log file on disk every `writes` write calls, and before each rotation
+    /// by size. 0 (never) by default.
+    ///
+    /// If someone else truncated the file (e.g. with `> my-log-file`) or appended to it, the
+    /// size on disk is adopted, so that the file is rotated when it's really full, and the change
+    /// is passed to [FileRotateBuilder::on_error] as [Error::SizeChanged]. The writer is flushed
+    /// before each comparison, and it must not change the size of what it writes, like a
+    /// compressing writer does. With [FileRotateBuilder::shared], the size is adopted before every
+    /// write anyway.
+    pub fn check_size_every(mut self, writes: usize) -> Self {
+        self.check_size_every = writes;
+        self
+    }
+    /// Flush the writer at the next write, or at [FileRotate::flush_if_due], once what was written
+    /// has been waiting for `interval`. A [FileRotate] has no timer of its own; only
+    /// [non_blocking::NonBlockingFileRotate] flushes during a quiet period by itself. Never by
+    /// default.
+    ///
+    /// This is meant for a `BufWriter` from [FileRotateBuilder::writer_factory], so that the last
+    /// lines show up in the file. The flush happens after a write call, never within one. Flush
+    /// errors are kept like those of rotations, see [FileRotate::sync].
+    pub fn flush_interval(mut self, interval: std::time::Duration) -> Self {
+        self.flush_interval = Some(interval);
+        self
+    }
+    /// Rotate at most once per `interval` because of the [ContentLimit], so that a limit that is
+    /// too small for the traffic doesn't make the [FileRotate] rotate all the time. Until
+    /// `interval` has passed since the last rotation, the log file grows beyond the limit, and
+    /// the next write after that rotates. Zero (no minimum) by default.
+    ///
+    /// [FileRotate::deferred_rotations] counts how often a rotation was put off. Rotations
+    /// requested by [ControlHandle::request_rotate] or [FileRotate::rotate_with_name], and retries
+    /// of a rotation that failed, happen regardless.
+    pub fn min_rotation_interval(mut self, interval: std::time::Duration) -> Self {
+        self.min_rotation_interval = interval;
+        self
+    }
+    /// Keep the current log file and the rotated files together within `bytes`, even in the
+    /// middle of a file. Unlimited by default.
+    ///
+    /// Before a write that wouldn't fit, the oldest rotated files are deleted to make room. If
+    /// there are none left, `policy` decides. A file that can't be deleted is reported like a
+    /// failed rotation and skipped for the next-oldest one. The suffix scheme still deletes files by its own
+    /// limit as well. The current log file counts the bytes passed to the writer, plus what the
+    /// file had when it was opened. The rotated files are looked up on disk after each rotation,
+    /// and again whenever the quota seems to be exceeded.
+    pub fn max_total_bytes(mut self, bytes: u64, policy: QuotaPolicy) -> Self {
+        self.quota = Some((bytes, policy));
+        self
+    }
+    /// Only create the log file (and its directory) at the first write, `false` by default.
+    ///
+    /// An existing log file is still truncated or rotated right away, according to
+    /// [FileRotateBuilder::open_behavior].
+    pub fn lazy_create(mut self, lazy_create: bool) -> Self {
+        self.lazy_create = lazy_create;
+        self
+    }
+    /// After a rotation, only create the new log file at the next write, `false` by default.
+    ///
+    /// This avoids leaving an empty log file behind when nothing more is logged, but there is no
+    /// log file at all until then, which programs following the log file have to handle.
+    pub fn lazy_recreate(mut self, lazy_recreate: bool) -> Self {
+        self.lazy_recreate = lazy_recreate;
+        self
+    }
+    /// Choose how the log file is moved aside, see [RotateMechanics] for the default.
+    pub fn rotate_mechanics(mut self, mechanics: RotateMechanics) -> Self {
+        self.rotate_mechanics = mechanics;
+        self
+    }
+    /// Move rotated files into `dir` (created if missing) instead of next to the log file.
+    ///
+    /// The suffix scheme then looks for and deletes rotated files in `dir`, and
+    /// [FileRotate::log_paths] lists the files there. If `dir` is on another file system, rotation
+    /// copies the log file and then removes it.
+    pub fn archive_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.archive_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+    /// Call `callback` when the path that the log file is about to be moved to exists already, to
+    /// decide what to do, see [CollisionAction]. Without it, the rotation cascades
+    /// ([CollisionAction::Cascade]).
+    ///
+    /// `callback` is asked before the log file is moved, but after
+    /// [suffix::SuffixScheme::rotate] returned the taken suffix, since that is how the path is
+    /// known. Files the scheme moved or deleted to make room, like [suffix::CountSuffix] does,
+    /// are moved already then.
+    ///
+    /// The suffix schemes of this crate make room before returning a suffix, so this only happens
+    /// with custom schemes, when moving files aside failed, or when someone else created the file.
+    /// It doesn't apply to [RotateMechanics::DateInName], which doesn't move the log file.
+    pub fn on_collision<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path) -> CollisionAction + Send + Sync + 'static,
+    {
+        self.on_collision = Some(Box::new(callback));
+        self
+    }
+    /// Call `callback` with the path of every rotated log file, e.g. to upload it.
+    ///
+    /// Files that are [ArchiveDecision::Pending] are passed to `callback` again at every rotation,
+    /// until it decides otherwise or they are released with [FileRotate::mark_archived]. Until
+    /// then the suffix scheme doesn't delete them, even when they are over its limit (see
+    /// [suffix::SuffixScheme::protect]), and with [suffix::CountSuffix], which renames the files
+    /// at every rotation, they are followed to their new names. Only
+    /// [FileRotateBuilder::max_pending] limits how many are kept.
+    pub fn on_rotated<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path) -> ArchiveDecision + Send + Sync + 'static,
+    {
+        self.on_rotated = Some(Box::new(callback));
+        self
+    }
+    /// How many files may be [ArchiveDecision::Pending] at once, unlimited by default. The oldest
+    /// pending files are deleted beyond that, so that a stuck upload can't fill the disk.
+    pub fn max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+    /// Call `callback` with every file that is named like a rotated log file, but whose suffix the
+    /// suffix scheme doesn't recognize, like `my-log-file.bak`. See [suffix::scan_report].
+    ///
+    /// The directory is checked when the [FileRotate] is created and after every rotation. Each
+    /// file is only reported once by this [FileRotate].
+    pub fn on_unrecognized<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path) + Send + Sync + 'static,
+    {
+        self.on_unrecognized = Some(Box::new(callback));
+        self
+    }
+    /// Delete files whose suffix the suffix scheme doesn't recognize, `false` by default, to keep
+    /// the directory strictly managed. They are checked for like with
+    /// [FileRotateBuilder::on_unrecognized].
+    ///
+    /// Only files named after the log file, like rotated files with a separator or a
+    /// [template::NameTemplate], whose suffix starts like the suffixes of the scheme are deleted,
+    /// like `app.3.bak` with [suffix::CountSuffix], see [suffix::SuffixScheme::resembles]. Others
+    /// are only reported, like `app.bak` or the log file `app.log` of another log next to `app`.
+    /// Custom suffix schemes have to implement [suffix::SuffixScheme::recognizes] and
+    /// [suffix::SuffixScheme::resembles] for this to have any effect.
+    pub fn delete_unrecognized(mut self, delete_unrecognized: bool) -> Self {
+        self.delete_unrecognized = delete_unrecognized;
+        self
+    }
+    /// Delete the old files that the suffix scheme doesn't keep over the following writes, at
+    /// most one per write, instead of all in the write that rotates, e.g. where deleting is slow.
+    /// `false` by default.
+    ///
+    /// The suffix scheme forgets them right away, so they don't count against its
+    /// [suffix::FileLimit], and [FileRotate::log_paths] doesn't list them. [FileRotate::sync],
+    /// [FileRotate::close] and dropping the [FileRotate] delete the files left. Schemes that
+    /// reuse the names of deleted files, like [suffix::CountSuffix], still delete right away, see
+    /// [suffix::SuffixScheme::defer_deletions].
+    pub fn defer_deletions(mut self, defer_deletions: bool) -> Self {
+        self.defer_deletions = defer_deletions;
+        self
+    }
+    /// Keep multi-line records, like stack traces, readable on their own in each file: If a
+    /// rotation falls within a record, the new log file starts with the part of the record in the
+    /// previous file. Off by default.
+    ///
+    /// `starts_record` tells whether a line starts a record, given the line without its line
+    /// break, or as much of it as is written at once. The lines up to the next line it accepts
+    /// belong to the record. Whether the new file continues a record is decided at the first write
+    /// into it.
+    ///
+    /// ```
+    /// # use file_rotate::{suffix::CountSuffix, ContentLimit, FileRotate};
+    /// # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+    /// # let log_path = directory.path().join("my-log-file");
+    /// // Indented lines continue the record before them
+    /// let log = FileRotate::builder(log_path, CountSuffix::new(3), ContentLimit::Lines(1000))
+    ///     .carry_over_records(|line| !line.starts_with(b" "))
+    ///     .build();
+    /// ```
+    ///
+    /// The carried bytes don't count toward the [ContentLimit] of the new file, but toward
+    /// [FileRotateBuilder::max_total_bytes]. Records longer than [MAX_CARRIED_BYTES] are not
+    /// carried over.
+    pub fn carry_over_records<F>(mut self, starts_record: F) -> Self
+    where
+        F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+    {
+        self.starts_record = Some(Box::new(starts_record));
+        self
+    }
+    /// Mark where the log continues at rotations, for readers of the files. `false` by default.
+    ///
+    /// A rotated file ends with a line like `---- log continues in my-log-file ----`, written
+    /// before it's moved, and the next log file starts with a line like
+    /// `---- continued from my-log-file.1 (rotated 2024-06-01T12:00:00) ----`, written with the
+    /// first write into it. Without the `chrono04` feature, the time is in Unix time.
+    ///
+    /// The markers don't count toward the [ContentLimit], but toward
+    /// [FileRotateBuilder::max_total_bytes]. They are left out when the suffix scheme keeps no
+    /// rotated files, see [suffix::SuffixScheme::discards], and retrying a rotation that failed
+    /// doesn't mark the file again.
+    pub fn rotation_markers(mut self, rotation_markers: bool) -> Self {
+        self.rotation_markers = rotation_markers;
+        self
+    }
+    /// Do the file operations through `ops` instead of [std::fs], e.g. through a
+    /// [fs_ops::FaultyFs] to test what happens when they fail. See [fs_ops] for which operations
+    /// these are. The suffix scheme is given them as well, see
+    /// [suffix::SuffixScheme::use_fs_ops].
+    ///
+    /// `ops` becomes a type parameter of the [FileRotate], which calls it directly. The suffix
+    /// scheme holds it as an `Arc<dyn FsOps>` though, so that schemes can still be boxed as
+    /// `dyn SuffixScheme`, and its calls are dynamically dispatched, even to [fs_ops::RealFs].
+    pub fn fs_ops<Fs2: FsOps>(self, ops: Fs2) -> FileRotateBuilder<S, W, Fs2> {
+        FileRotateBuilder {
+            basepath: self.basepath,
+            content_limit: self.content_limit,
+            split_at: self.split_at,
+            open_behavior: self.open_behavior,
+            shared: self.shared,
+            check_size_every: self.check_size_every,
+            quota: self.quota,
+            flush_interval: self.flush_interval,
+            min_rotation_interval: self.min_rotation_interval,
+            lazy_create: self.lazy_create,
+            lazy_recreate: self.lazy_recreate,
+            rotate_mechanics: self.rotate_mechanics,
+            archive_dir: self.archive_dir,
+            suffix_scheme: self.suffix_scheme,
+            open: self.open,
+            fs: ops,
+            tee: self.tee,
+            on_collision: self.on_collision,
+            on_rotated: self.on_rotated,
+            max_pending: self.max_pending,
+            on_unrecognized: self.on_unrecognized,
+            delete_unrecognized: self.delete_unrecognized,
+            defer_deletions: self.defer_deletions,
+            starts_record: self.starts_record,
+            rotation_markers: self.rotation_markers,
+            on_error: self.on_error,
+        }
+    }
+    /// Call `callback` with the failures that would otherwise go unnoticed, because writing
+    /// carries on after them: Deleting old or unrecognized files, moving the log file (see
+    /// [FileRotate::sync]), setting the modification time of copies, flushing when dropped, and
+    /// changes of the size by others (see [FileRotateBuilder::check_size_every]).
+    /// Failures of writing itself are returned as before.
+    ///
+    /// `callback` is called by the thread that writes, once the failed operation is over. No lock
+    /// of this crate is held then, but with [non_blocking::NonBlockingFileRotate] that thread is
+    /// the worker, so writing to the same queue from `callback` waits for itself once the queue is
+    /// full, unless the [non_blocking::OverflowPolicy] drops writes.
+    pub fn on_error<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Box::new(callback));
+        self
+    }
+}
+
+impl<S, Fs> FileRotateBuilder<S, File, Fs> {
+    /// Open the log file with `options`, e.g. with custom flags, whenever it is (re)opened.
+    /// [FileRotateBuilder::open_behavior] decides what happens to an existing file.
+    ///
+    /// ```
+    /// use file_rotate::{suffix::CountSuffix, ContentLimit, FileRotate, LogOpenOptions};
+    ///
+    /// # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+    /// # let directory = directory.path();
+    /// let mut options = LogOpenOptions::new();
+    /// #[cfg(unix)]
+    /// std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    /// let log = FileRotate::builder(directory.join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(100))
+    ///     .open_options(options)
+    ///     .build();
+    /// ```
+    pub fn open_options(mut self, options: LogOpenOptions) -> Self {
+        self.open = Box::new(move |fs, path| fs.open(path, &options.0));
+        self
+    }
+}
+
+impl<S: suffix::SuffixScheme, W: Write, Fs: FsOps + 'static> FileRotateBuilder<S, W, Fs> {
+    /// Create the [FileRotate].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes == 0`, `lines == 0` or `writes == 0`.
+    pub fn build(self) -> FileRotate<S, W, Fs> {
+        match self.content_limit {
+            ContentLimit::Bytes(bytes) => {
+                assert!(bytes > 0);
+            }
+            ContentLimit::Lines(lines) => {
+                assert!(lines > 0);
+            }
+            ContentLimit::BytesSurpassed(bytes) => {
+                assert!(bytes > 0);
+            }
+            ContentLimit::Writes(writes) => {
+                assert!(writes > 0);
+            }
+        };
+
+        let date_in_name = matches!(self.rotate_mechanics, RotateMechanics::DateInName { .. });
+        // With `DateInName`, the base path is not the log file
+        let exists = !date_in_name
+            && self
+                .fs
+                .metadata(&self.basepath)
+                .map(|metadata| metadata.len() > 0)
+                .unwrap_or(false);
+        let count = match self.open_behavior {
+            OpenBehavior::Append if exists => {
+                existing_count(&self.fs, &self.basepath, &self.content_limit).unwrap_or(0)
+            }
+            OpenBehavior::Truncate if exists => {
+                let _ = self.fs.open(
+                    &self.basepath,
+                    OpenOptions::new().write(true).truncate(true),
+                );
+                0
+            }
+            _ => 0,
+        };
+
+        let archive_basepath = match self.archive_dir {
+            Some(dir) => dir.join(self.basepath.file_name().expect("basepath.file_name()")),
+            None => self.basepath.clone(),
+        };
+        let fs = Arc::new(self.fs);
+        let mut suffix_scheme = self.suffix_scheme;
+        suffix_scheme.use_fs_ops(fs.clone());
+        if self.defer_deletions && !suffix_scheme.defer_deletions() {
+            event!(Debug, "the suffix scheme doesn't defer deletions");
+        }
+        let mut file_rotate = FileRotate {
+            file: None,
+            active: self.basepath.clone(),
+            basepath: self.basepath,
+            archive_basepath,
+            content_limit: self.content_limit,
+            split_at: self.split_at,
+            count,
+            shared: self.shared,
+            check_size_every: self.check_size_every,
+            writes_since_check: 0,
+            quota: self.quota,
+            flush_interval: self.flush_interval,
+            unflushed_since: None,
+            active_bytes: 0,
+            rotated_bytes: None,
+            lazy_recreate: self.lazy_recreate,
+            rotate_mechanics: self.rotate_mechanics,
+            file_id: None,
+            pending_rotation: None,
+            last_rotation: None,
+            min_rotation_interval: self.min_rotation_interval,
+            rotation_attempted: None,
+            deferred_rotations: 0,
+            record_tail: self.starts_record.map(RecordTail::new),
+            rotation_markers: self.rotation_markers,
+            start_marker: None,
+            mid_line: false,
+            suffix_scheme,
+            open: self.open,
+            fs,
+            tee: self.tee,
+            control: ControlHandle::default(),
+            on_collision: self.on_collision,
+            on_rotated: self.on_rotated,
+            pending_archive: VecDeque::new(),
+            max_pending: self.max_pending,
+            pending_deletion: VecDeque::new(),
+            on_unrecognized: self.on_unrecognized,
+            reported_unrecognized: HashSet::new(),
+            delete_unrecognized: self.delete_unrecognized,
+            on_error: self.on_error,
+            subscribers: Subscribers::default(),
+            error_history: Mutex::new(VecDeque::new()),
+            deferred_error: None,
+        };
+        file_rotate.handle_unrecognized();
+        if date_in_name {
+            let newest = match self.open_behavior {
+                // Only a file that isn't finished, see `SuffixScheme::closed_suffix`
+                OpenBehavior::Append => file_rotate
+                    .log_paths()
+                    .pop()
+                    .filter(|newest| file_rotate.closed_path(newest).is_some()),
+                _ => None,
+            };
+            file_rotate.active = match newest {
+                Some(newest) => {
+                    file_rotate.count =
+                        existing_count(&*file_rotate.fs, &newest, &file_rotate.content_limit)
+                            .unwrap_or(0);
+                    newest
+                }
+                None => file_rotate.new_active_path(),
+            };
+        }
+        if self.open_behavior == OpenBehavior::RotateExisting && exists {
+            if let Err(err) = file_rotate.move_current() {
+                file_rotate.report(&err);
+                file_rotate.deferred_error = Some(err);
+            }
+        }
+        if !self.lazy_create {
+            let _ = file_rotate.open_file();
+        }
+        file_rotate
+    }
+}
+
+impl<S: suffix::SuffixScheme> FileRotate<S> {
+    /// Create a new [FileRotate].
+    ///
+    /// The basename of the `path` is used to create new log files by appending an extension of the
+    /// form `.N`, where N is `0..=max_files`.
+    ///
+    /// `content_limit` specifies the limits for rotating a file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes == 0`, `lines == 0` or `writes == 0`.
+    pub fn new<P: AsRef<Path>>(path: P, suffix_scheme: S, content_limit: ContentLimit) -> Self {
+        Self::builder(path, suffix_scheme, content_limit).build()
+    }
+    /// Like [FileRotate::new], but returns a builder to change further settings before creating
+    /// the [FileRotate].
+    pub fn builder<P: AsRef<Path>>(
+        path: P,
+        suffix_scheme: S,
+        content_limit: ContentLimit,
+    ) -> FileRotateBuilder<S> {
+        FileRotateBuilder {
+            basepath: path.as_ref().to_path_buf(),
+            content_limit,
+            split_at: SplitPolicy::Exact,
+            open_behavior: OpenBehavior::Truncate,
+            shared: false,
+            check_size_every: 0,
+            quota: None,
+            flush_interval: None,
+            min_rotation_interval: std::time::Duration::ZERO,
+            lazy_create: false,
+            lazy_recreate: false,
+            rotate_mechanics: RotateMechanics::default(),
+            archive_dir: None,
+            suffix_scheme,
+            open: Box::new(|fs, path| fs.open(path, OpenOptions::new().create(true).append(true))),
+            fs: RealFs,
+            tee: None,
+            on_collision: None,
+            on_rotated: None,
+            max_pending: usize::MAX,
+            on_unrecognized: None,
+            delete_unrecognized: false,
+            defer_deletions: false,
+            starts_record: None,
+            rotation_markers: false,
+            on_error: None,
+        }
+    }
+}
+
+impl<S: suffix::SuffixScheme, W: Write, Fs: FsOps> FileRotate<S, W, Fs> {
+    /// Get paths of rotated log files (excluding the original/current log file)
+    pub fn log_paths(&mut self) -> Vec<PathBuf> {
+        let mut paths = self.suffix_scheme.log_paths(&self.archive_basepath);
+        // With `DateInName`, the current file is one of them
+        paths.retain(|path| {
+            *path != self.active
+                && !self
+                    .pending_deletion
+                    .iter()
+                    .any(|pending| pending.path == *path)
+        });
+        paths
+    }
+
+    /// Path of the current log file, whether it has been created yet or not.
+    pub fn current_path(&self) -> &Path {
+        &self.active
+    }
+
+    /// When the log file was last rotated by this [FileRotate], if at all.
+    pub fn last_rotation(&self) -> Option<SystemTime> {
+        self.last_rotation
+    }
+
+    /// How often a rotation because of the [ContentLimit] was put off, see
+    /// [FileRotateBuilder::min_rotation_interval].
+    pub fn deferred_rotations(&self) -> u64 {
+        self.deferred_rotations
+    }
+
+    /// How many bytes can still be written before the log file is rotated, if the [ContentLimit]
+    /// is in bytes.
+    pub fn bytes_until_rotation(&self) -> Option<usize> {
+        match self.content_limit {
+            ContentLimit::Bytes(bytes) | ContentLimit::BytesSurpassed(bytes) => {
+                Some(bytes.saturating_sub(self.count))
+            }
+            _ => None,
+        }
+    }
+
+    /// How many lines can still be written before the log file is rotated, if the [ContentLimit]
+    /// is in lines.
+    pub fn lines_until_rotation(&self) -> Option<usize> {
+        match self.content_limit {
+            ContentLimit::Lines(lines) => Some(lines.saturating_sub(self.count)),
+            _ => None,
+        }
+    }
+
+    /// The size on disk of the current log file and each rotated file, newest first. Files that
+    /// don't exist (anymore) are left out. Data still buffered by the writer isn't counted.
+    pub fn sizes(&mut self) -> io::Result<Vec<(PathBuf, u64)>> {
+        let mut paths = self.log_paths();
+        paths.push(self.active.clone());
+        let mut sizes = Vec::new();
+        for path in paths.into_iter().rev() {
+            match self.fs.metadata(&path) {
+                Ok(metadata) => sizes.push((path, metadata.len())),
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(sizes)
+    }
+
+    /// The total size on disk of the current log file and all rotated files, see
+    /// [FileRotate::sizes].
+    pub fn total_size(&mut self) -> io::Result<u64> {
+        Ok(self.sizes()?.into_iter().map(|(_, size)| size).sum())
+    }
+
+    /// The last `lines` lines of the log, from the current log file and as many rotated files as
+    /// necessary, oldest first. Invalid UTF-8 is replaced.
+    ///
+    /// At most `max_bytes` are read from the end of each file. If that isn't enough, the older
+    /// files are left out, as well as the incomplete line at the start of what was read. Files
+    /// that are deleted in the meantime are skipped. The files are read as they are on disk, so
+    /// this is only useful if [FileRotateBuilder::writer_factory] writes plain text.
+    pub fn tail(&mut self, lines: usize, max_bytes: u64) -> io::Result<Vec<String>> {
+        if let Some(ref mut file) = self.file {
+            file.flush()?;
+        }
+        let mut paths = self.log_paths();
+        paths.push(self.active.clone());
+        // Newest first
+        let mut chunks = Vec::new();
+        let mut newlines = 0;
+        let mut ends_with_newline = None;
+        'files: for path in paths.iter().rev() {
+            let mut file = match self.fs.open(path, OpenOptions::new().read(true)) {
+                Ok(file) => file,
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            let start = file.metadata()?.len().saturating_sub(max_bytes);
+            let mut pos = file.seek(SeekFrom::End(0))?;
+            while pos > start {
+                let mut chunk = vec![0; (pos - start).min(8192) as usize];
+                pos -= chunk.len() as u64;
+                file.seek(SeekFrom::Start(pos))?;
+                file.read_exact(&mut chunk)?;
+                newlines += count_newlines(&chunk);
+                let ends_with_newline = *ends_with_newline.get_or_insert(chunk.ends_with(b"\n"));
+                chunks.push(chunk);
+                // The lines after the first line break are complete
+                if newlines - usize::from(ends_with_newline) >= lines {
+                    break 'files;
+                }
+            }
+            if start > 0 {
+                let mut before = [0];
+                file.seek(SeekFrom::Start(start - 1))?;
+                file.read_exact(&mut before)?;
+                // Unless it starts after a line break, the first line is incomplete
+                if before[0] != b'\n' {
+                    let mut head = chunks.pop().unwrap_or_default();
+                    if let Some(idx) = nth_newline(&head, 0) {
+                        head.drain(..=idx);
+                    }
+                    chunks.push(head);
+                }
+                break;
+            }
+        }
+        chunks.reverse();
+        let text = String::from_utf8_lossy(&chunks.concat()).into_owned();
+        let all = text.lines().collect::<Vec<_>>();
+        let skip = all.len().saturating_sub(lines);
+        Ok(all[skip..].iter().map(|line| line.to_string()).collect())
+    }
+
+    /// The rotated log files, and the files named like them that the suffix scheme doesn't
+    /// recognize. See [suffix::scan_report].
+    pub fn scan_report(&mut self) -> io::Result<suffix::ScanReport> {
+        suffix::scan_report_with(&*self.fs, &self.archive_basepath, &mut self.suffix_scheme)
+    }
+
+    /// Receive the rotations, deletions and errors from now on, e.g. on another thread. See
+    /// [events]. Each call adds a receiver; sending to them never blocks writing.
+    pub fn subscribe(&mut self) -> EventReceiver {
+        self.subscribers.subscribe()
+    }
+
+    /// A handle to request a rotation or reopening of the log file from elsewhere, e.g. a signal
+    /// handler.
+    pub fn control_handle(&self) -> ControlHandle {
+        self.control.clone()
+    }
+
+    /// Stop passing `path` to the callback of [FileRotateBuilder::on_rotated] and delete it, e.g.
+    /// once its upload has finished. Returns whether `path` was pending.
+    pub fn mark_archived(&mut self, path: &Path) -> bool {
+        match self
+            .pending_archive
+            .iter()
+            .position(|pending| pending == path)
+        {
+            Some(idx) => {
+                self.pending_archive.remove(idx);
+                match delete_file(&*self.fs, path) {
+                    Ok(()) => self.deleted(path, format_args!("archived")),
+                    Err(err) => self.report(&err),
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make sure that everything written so far is where it belongs, and report what went wrong
+    /// since the last call.
+    ///
+    /// Retries a rotation that failed, flushes the writers, deletes the files left by
+    /// [FileRotateBuilder::defer_deletions], and then returns the first error that writing carried
+    /// on after, like a failed rotation, see [FileRotate::take_last_error].
+    pub fn sync(&mut self) -> Result<(), Error> {
+        if self.pending_rotation.is_some() {
+            self.rotate()?;
+        }
+        self.flush_all()?;
+        self.delete_deferred(usize::MAX);
+        match self.deferred_error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Flush if [FileRotateBuilder::flush_interval] has passed since the oldest write that hasn't
+    /// been flushed, e.g. from a timer.
+    pub fn flush_if_due(&mut self) -> Result<(), Error> {
+        match self.flush_due_in() {
+            Some(due_in) if due_in.is_zero() => self.flush_all(),
+            _ => Ok(()),
+        }
+    }
+
+    /// How long until written data is due to be flushed, if there is any with a
+    /// [FileRotateBuilder::flush_interval].
+    pub(crate) fn flush_due_in(&self) -> Option<std::time::Duration> {
+        let since = self.unflushed_since?;
+        Some(self.flush_interval?.saturating_sub(since.elapsed()))
+    }
+
+    /// Note that data has been written: Delete a file left to be deleted, and flush if it's due.
+    fn written(&mut self) {
+        self.delete_deferred(1);
+        if self.flush_interval.is_none() {
+            return;
+        }
+        self.unflushed_since.get_or_insert_with(Instant::now);
+        // Writing carries on after a failed flush, like after a failed rotation
+        if let Err(err) = self.flush_if_due() {
+            self.report(&err);
+            self.deferred_error.get_or_insert(err);
+        }
+    }
+
+    /// Delete up to `max` of the files the suffix scheme left to be deleted.
+    fn delete_deferred(&mut self, max: usize) {
+        for _ in 0..max {
+            let pending = match self.pending_deletion.pop_front() {
+                Some(pending) => pending,
+                None => break,
+            };
+            match delete_file(&*self.fs, &pending.path) {
+                Ok(()) => self.deleted(&pending.path, format_args!("{}", pending.reason)),
+                Err(err) => {
+                    self.report(&err);
+                    self.deferred_error.get_or_insert(err);
+                }
+            }
+        }
+    }
+
+    /// The first error since the last call (or [FileRotate::sync]) that writing carried on after,
+    /// like a failed rotation, which is retried at the next write.
+    pub fn take_last_error(&mut self) -> Option<Error> {
+        self.deferred_error.take()
+    }
+
+    /// The last [ERROR_HISTORY] errors that writing carried on after, oldest first, whether they
+    /// were taken by [FileRotate::take_last_error] or not.
+    pub fn recent_errors(&self) -> Vec<RecentError> {
+        self.error_history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// What this [FileRotate] knows about its configuration and files, e.g. to ask users to paste
+    /// into a bug report. The rotated files are listed like [FileRotate::log_paths] does.
+    pub fn snapshot(&mut self) -> StateSnapshot {
+        let rotated = self
+            .log_paths()
+            .into_iter()
+            .map(|path| SnapshotFile {
+                suffix: path.file_name().and_then(|file_name| {
+                    self.suffix_scheme
+                        .suffix_of(&self.archive_basepath, file_name)
+                }),
+                size: self.fs.metadata(&path).map(|metadata| metadata.len()).ok(),
+                path,
+            })
+            .collect();
+        StateSnapshot {
+            content_limit: format!("{:?}", self.content_limit),
+            rotate_mechanics: format!("{:?}", self.rotate_mechanics),
+            quota: self.quota.map(|quota| format!("{:?}", quota)),
+            current_path: self.active.clone(),
+            current_bytes: self.active_bytes,
+            count: self.count,
+            last_rotation: self.last_rotation,
+            rotated,
+            pending_rotation: self.pending_rotation.clone(),
+            pending_archive: self.pending_archive.iter().cloned().collect(),
+            pending_deletion: self
+                .pending_deletion
+                .iter()
+                .map(|pending| pending.path.clone())
+                .collect(),
+            recent_errors: self.recent_errors(),
+        }
+    }
+
+    /// Move the current log file aside under `name` as suffix, e.g. to `my-log-file.before-upgrade`,
+    /// and continue in a new log file. Returns the path of the snapshot.
+    ///
+    /// An existing file isn't replaced unless `overwrite` is `true`, and `name` can't contain
+    /// path separators. The suffix scheme doesn't rotate, and the [ContentLimit] starts over. If
+    /// the scheme doesn't recognize `name` as one of its suffixes, the snapshot is never deleted
+    /// by it nor listed by [FileRotate::log_paths], but [suffix::scan_report] lists it among the
+    /// unrecognized files (and [FileRotateBuilder::delete_unrecognized] deletes it).
+    pub fn rotate_with_name(&mut self, name: &str, overwrite: bool) -> io::Result<PathBuf> {
+        if name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.chars().any(std::path::is_separator)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid snapshot name {:?}", name),
+            ));
+        }
+        let path = self
+            .suffix_scheme
+            .rotated_path(&self.archive_basepath, name);
+        if !overwrite && exists(&*self.fs, &path) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists already", path.display()),
+            ));
+        }
+        self.flush_all()?;
+        if let Err(source) = create_parent_dir(&*self.fs, &path) {
+            return Err(Error::Rotate {
+                from: self.active.clone(),
+                to: path,
+                source,
+            }
+            .into());
+        }
+        let mut errors = Vec::new();
+        let next = self.active.clone();
+        errors.extend(self.mark_end(&next).err());
+        let moved = match self.rotate_mechanics {
+            RotateMechanics::CopyTruncate => self.copy_truncate(&path, &mut errors),
+            _ => {
+                let _ = self.file.take();
+                move_file(&*self.fs, &self.active, &path, &mut errors)
+            }
+        };
+        for err in &errors {
+            self.report(err);
+        }
+        if let Err(source) = moved {
+            // Carry on in the current file
+            if self.file.is_none() {
+                self.open_file()?;
+            }
+            return Err(Error::Rotate {
+                from: self.active.clone(),
+                to: path,
+                source,
+            }
+            .into());
+        }
+        event!(
+            Info,
+            "moved {} to {}",
+            self.active.display(),
+            path.display()
+        );
+        self.publish_rotated(&path);
+        self.count = 0;
+        self.active_bytes = 0;
+        self.started_over();
+        self.mark_start(&path);
+        if !self.lazy_recreate && self.file.is_none() {
+            self.open_file()?;
+        }
+        Ok(path)
+    }
+
+    /// Flush and close the current log file, reporting any error.
+    ///
+    /// Dropping the [FileRotate] does the same, but has to ignore errors.
+    pub fn close(mut self) -> Result<CloseReport, Error> {
+        self.delete_deferred(usize::MAX);
+        let flushed = self.flush_all();
+        // Drop the writer, so that it is closed
+        let _ = self.file.take();
+        flushed?;
+        let bytes = match self.fs.metadata(&self.active) {
+            Ok(metadata) => metadata.len(),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(source) => {
+                return Err(Error::Metadata {
+                    path: self.active.clone(),
+                    source,
+                })
+            }
+        };
+        Ok(CloseReport {
+            path: self.active.clone(),
+            bytes,
+        })
+    }
+
+    /// Finish up and hand over the writer of the current log file, e.g. to append a report to it
+    /// at shutdown, with the path and size of the file.
+    ///
+    /// Like [FileRotate::sync], a rotation that failed is retried and the first error that writing
+    /// carried on after is returned, so that nothing is left to do for the rotated files. The log
+    /// file is opened if it doesn't exist yet.
+    pub fn into_parts(mut self) -> Result<(W, CloseReport), Error> {
+        self.sync()?;
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => {
+                self.open_file()?;
+                self.file.take().expect("the log file was just opened")
+            }
+        };
+        let bytes = match self.fs.metadata(&self.active) {
+            Ok(metadata) => metadata.len(),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(source) => {
+                return Err(Error::Metadata {
+                    path: self.active.clone(),
+                    source,
+                })
+            }
+        };
+        let report = CloseReport {
+            path: self.active.clone(),
+            bytes,
+        };
+        Ok((file, report))
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        self.rotation_attempted = Some(Instant::now());
+        if let RotateMechanics::DateInName { .. } = self.rotate_mechanics {
+            let switched = self.switch_file();
+            if switched.is_ok() {
+                self.started_over();
+            }
+            return switched;
+        }
+        let moved = self.move_current();
+        if moved.is_ok() {
+            self.started_over();
+        }
+        if !self.lazy_recreate && self.file.is_none() {
+            self.open_file()?;
+        }
+        moved
+    }
+
+    /// Writing continues in a new (or emptied) log file.
+    fn started_over(&mut self) {
+        self.mid_line = false;
+        if let Some(ref mut record_tail) = self.record_tail {
+            record_tail.rotated();
+        }
+    }
+
+    /// The line saying that the log continues in `next`, see [FileRotateBuilder::rotation_markers].
+    fn end_marker(&self, next: &Path) -> String {
+        // Don't append it to an unfinished line
+        let newline = if self.mid_line { "\n" } else { "" };
+        format!("{}---- log continues in {} ----\n", newline, next.display())
+    }
+
+    /// End the current log file with a line saying that the log continues in `next`, see
+    /// [FileRotateBuilder::rotation_markers].
+    fn mark_end(&mut self, next: &Path) -> Result<(), Error> {
+        // Don't create a log file just for the marker
+        if !self.rotation_markers || (self.file.is_none() && !exists(&*self.fs, &self.active)) {
+            return Ok(());
+        }
+        let marker = self.end_marker(next);
+        if self.file.is_none() {
+            self.open_file()?;
+        }
+        if let Some(ref mut file) = self.file {
+            if let Err(source) = file
+                .write_all(marker.as_bytes())
+                .and_then(|()| file.flush())
+            {
+                return Err(Error::Write {
+                    path: self.active.clone(),
+                    source,
+                });
+            }
+            self.active_bytes += marker.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Start the next log file with a line saying that the log continued from `previous`, see
+    /// [FileRotateBuilder::rotation_markers].
+    fn mark_start(&mut self, previous: &Path) {
+        if self.rotation_markers {
+            self.start_marker = Some(format!(
+                "---- continued from {} (rotated {}) ----\n",
+                previous.display(),
+                display_time(SystemTime::now())
+            ));
+        }
+    }
+
+    /// Move the current log file to its rotated name.
+    fn move_current(&mut self) -> Result<(), Error> {
+        if self.suffix_scheme.discards() {
+            return self.discard_current();
+        }
+        let mut errors = Vec::new();
+        let retry = self.pending_rotation.is_some();
+        let path = match self.pending_rotation.take() {
+            Some(path) => path,
+            None => {
+                let suffix = self.rotate_scheme();
+                errors.extend(self.suffix_scheme.take_errors());
+                self.publish_deleted();
+                self.suffix_scheme
+                    .rotated_path(&self.archive_basepath, &suffix)
+            }
+        };
+
+        let (path, moved) = match self.resolve_collision(path.clone(), &mut errors) {
+            // Kept pending like a failed rename if the directory can't be created
+            Ok(path) => match create_parent_dir(&*self.fs, &path) {
+                Err(err) => (path, Err(err)),
+                Ok(()) => {
+                    // A file left to be deleted under that name would take the rotated file with it
+                    if let Some(idx) = self
+                        .pending_deletion
+                        .iter()
+                        .position(|pending| pending.path == path)
+                    {
+                        let pending = self.pending_deletion.remove(idx).expect("idx is in bounds");
+                        match delete_file(&*self.fs, &pending.path) {
+                            Ok(()) => {
+                                self.deleted(&pending.path, format_args!("{}", pending.reason))
+                            }
+                            Err(err) => errors.push(err),
+                        }
+                    }
+
+                    event!(
+                        Debug,
+                        "rotating {} to {} at {} of {:?}",
+                        self.basepath.display(),
+                        path.display(),
+                        self.count,
+                        self.content_limit
+                    );
+                    if !retry {
+                        let next = self.active.clone();
+                        errors.extend(self.mark_end(&next).err());
+                    }
+                    if self.rotate_mechanics == RotateMechanics::CloseRenameReopen {
+                        let _ = self.file.take();
+                    }
+                    let moved = match self.rotate_mechanics {
+                        RotateMechanics::CopyTruncate => self.copy_truncate(&path, &mut errors),
+                        _ => move_file(&*self.fs, &self.basepath, &path, &mut errors),
+                    };
+                    (path, moved)
+                }
+            },
+            Err(err) => (path, Err(err)),
+        };
+        // Including those of cascading for a collision
+        self.publish_deleted();
+        for err in &errors {
+            self.report(err);
+        }
+
+        let renamed = match moved {
+            // The log file is gone (e.g. its directory was deleted), so there is nothing to keep
+            Err(_) if !exists(&*self.fs, &self.basepath) => {
+                let _ = self.file.take();
+                Ok(())
+            }
+            Err(source) => {
+                let err = Error::Rotate {
+                    from: self.basepath.clone(),
+                    to: path.clone(),
+                    source,
+                };
+                // Keep writing to the current file, and retry the rename on the next write
+                self.pending_rotation = Some(path);
+                Err(err)
+            }
+            Ok(()) => {
+                event!(
+                    Info,
+                    "rotated {} to {}",
+                    self.basepath.display(),
+                    path.display()
+                );
+                if self.rotate_mechanics == RotateMechanics::RenameKeepFdThenReopen {
+                    let _ = self.file.take();
+                }
+                self.last_rotation = Some(SystemTime::now());
+                self.mark_start(&path);
+                self.archive(path);
+                self.handle_unrecognized();
+                Ok(())
+            }
+        };
+
+        if renamed.is_ok() {
+            self.count = 0;
+            self.active_bytes = 0;
+            self.rotated_bytes = None;
+        }
+
+        renamed
+    }
+
+    /// Empty the current log file instead of moving it, since the suffix scheme keeps no rotated
+    /// files, see [suffix::SuffixScheme::discards].
+    fn discard_current(&mut self) -> Result<(), Error> {
+        self.pending_rotation = None;
+        // Only to delete leftovers, e.g. from a time when files were kept
+        let _ = self.rotate_scheme();
+        for err in self.suffix_scheme.take_errors() {
+            self.report(&err);
+        }
+        self.publish_deleted();
+        // The writer flushes when dropped, so that it doesn't write into the emptied file later
+        let _ = self.file.take();
+        let emptied = self
+            .fs
+            .open(&self.active, OpenOptions::new().write(true))
+            .and_then(|file| file.set_len(0));
+        self.count = 0;
+        match emptied {
+            Err(source) if source.kind() != io::ErrorKind::NotFound => Err(Error::Write {
+                path: self.active.clone(),
+                source,
+            }),
+            _ => {
+                event!(Info, "discarded the content of {}", self.active.display());
+                self.active_bytes = 0;
+                self.rotated_bytes = None;
+                self.last_rotation = Some(SystemTime::now());
+                Ok(())
+            }
+        }
+    }
+
+    /// Where to move the current log file if `path` exists already, as decided by
+    /// [FileRotateBuilder::on_collision], [CollisionAction::Cascade] without it. Fails with
+    /// [io::ErrorKind::AlreadyExists] if the rotation is aborted.
+    fn resolve_collision(
+        &mut self,
+        mut path: PathBuf,
+        errors: &mut Vec<Error>,
+    ) -> io::Result<PathBuf> {
+        // Taken while rotating the suffix scheme, which needs `self`
+        let callback = self.on_collision.take();
+        let resolved = loop {
+            if !exists(&*self.fs, &path) {
+                break Ok(path);
+            }
+            let action = match callback {
+                Some(ref callback) => callback(&path),
+                None => CollisionAction::Cascade,
+            };
+            event!(Debug, "{} exists already: {:?}", path.display(), action);
+            match action {
+                CollisionAction::Overwrite => break Ok(path),
+                CollisionAction::Cascade => {
+                    let suffix = self.rotate_scheme();
+                    errors.extend(self.suffix_scheme.take_errors());
+                    let next = self
+                        .suffix_scheme
+                        .rotated_path(&self.archive_basepath, &suffix);
+                    // Asking again would give the same answer
+                    if next == path && exists(&*self.fs, &next) {
+                        break Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            "the suffix scheme didn't make room",
+                        ));
+                    }
+                    path = next;
+                }
+                CollisionAction::RenameTo(other) if other == path => break Ok(path),
+                CollisionAction::RenameTo(other) => path = other,
+                CollisionAction::Abort => {
+                    break Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        "rotation aborted by on_collision",
+                    ))
+                }
+            }
+        };
+        self.on_collision = callback;
+        resolved
+    }
+
+    /// Close the current log file and continue in a new one, see [RotateMechanics::DateInName].
+    fn switch_file(&mut self) -> Result<(), Error> {
+        let _ = self.file.take();
+        let previous = self.close_active();
+        self.active = self.new_active_path();
+        if self.rotation_markers && exists(&*self.fs, &previous) {
+            // The old file is closed already, so open it again for the marker
+            let marker = self.end_marker(&self.active);
+            let marked = (self.open)(&*self.fs, &previous).and_then(|mut file| {
+                file.write_all(marker.as_bytes())?;
+                file.flush()
+            });
+            if let Err(source) = marked {
+                self.report(&Error::Write {
+                    path: previous.clone(),
+                    source,
+                });
+            }
+        }
+        self.mark_start(&previous);
+        event!(
+            Info,
+            "switched from {} to {}",
+            previous.display(),
+            self.active.display()
+        );
+        self.count = 0;
+        self.active_bytes = 0;
+        self.rotated_bytes = None;
+        self.last_rotation = Some(SystemTime::now());
+        // It may have never been created
+        if exists(&*self.fs, &previous) {
+            self.archive(previous);
+        }
+        self.handle_unrecognized();
+        if !self.lazy_recreate {
+            self.open_file()?;
+        }
+        Ok(())
+    }
+
+    /// Where the suffix scheme wants the file at `path` once it's closed, or None if the file is
+    /// finished already, see [suffix::SuffixScheme::closed_suffix].
+    fn closed_path(&self, path: &Path) -> Option<PathBuf> {
+        let file_name = path.file_name()?;
+        let suffix = self
+            .suffix_scheme
+            .suffix_of(&self.archive_basepath, file_name)?;
+        let closed = self
+            .suffix_scheme
+            .closed_suffix(&self.archive_basepath, &suffix)?;
+        Some(
+            self.suffix_scheme
+                .rotated_path(&self.archive_basepath, &closed),
+        )
+    }
+
+    /// Rename the current file as the suffix scheme wants it once it's closed, and return where it
+    /// is then. If that fails, it keeps its name.
+    fn close_active(&mut self) -> PathBuf {
+        let path = match self.closed_path(&self.active) {
+            Some(path) if path != self.active && exists(&*self.fs, &self.active) => path,
+            _ => return self.active.clone(),
+        };
+        let mut errors = Vec::new();
+        let moved = move_file(&*self.fs, &self.active, &path, &mut errors);
+        for err in &errors {
+            self.report(err);
+        }
+        match moved {
+            Ok(()) => {
+                event!(
+                    Info,
+                    "renamed {} to {}",
+                    self.active.display(),
+                    path.display()
+                );
+                path
+            }
+            Err(source) => {
+                let err = Error::Rotate {
+                    from: self.active.clone(),
+                    to: path,
+                    source,
+                };
+                self.report(&err);
+                self.deferred_error.get_or_insert(err);
+                self.active.clone()
+            }
+        }
+    }
+
+    /// Let the suffix scheme name a new file to write to, see [RotateMechanics::DateInName].
+    fn new_active_path(&mut self) -> PathBuf {
+        let suffix = self.rotate_scheme();
+        for err in self.suffix_scheme.take_errors() {
+            self.report(&err);
+        }
+        self.publish_deleted();
+        self.suffix_scheme
+            .rotated_path(&self.archive_basepath, &suffix)
+    }
+
+    /// Copy the current log file to `path` and truncate it.
+    fn copy_truncate(&mut self, path: &Path, errors: &mut Vec<Error>) -> io::Result<()> {
+        if let Some(ref mut file) = self.file {
+            file.flush()?;
+        }
+        copy_file(&*self.fs, &self.basepath, path, errors)?;
+        let truncated = self
+            .fs
+            .open(&self.basepath, OpenOptions::new().write(true))
+            .and_then(|file| file.set_len(0));
+        if truncated.is_err() {
+            // Don't leave a second copy of the content behind
+            errors.extend(delete_file(&*self.fs, path).err());
+        }
+        truncated
+    }
+
+    /// Pass the newly rotated file at `path` and the pending ones to the `on_rotated` callback.
+    fn archive(&mut self, path: PathBuf) {
+        self.publish_rotated(&path);
+        let callback = match self.on_rotated {
+            Some(ref callback) => callback,
+            None => return,
+        };
+        self.pending_archive.push_back(path);
+        let fs = &*self.fs;
+        let mut errors = Vec::new();
+        let mut deleted = Vec::new();
+        // Files that are gone (deleted by the suffix scheme) are dropped as well
+        self.pending_archive.retain(|path| {
+            exists(fs, path)
+                && match callback(path) {
+                    ArchiveDecision::KeepLocal => false,
+                    ArchiveDecision::DeleteNow => {
+                        match delete_file(fs, path) {
+                            Ok(()) => deleted.push((path.clone(), "archived".to_string())),
+                            Err(err) => errors.push(err),
+                        }
+                        false
+                    }
+                    ArchiveDecision::Pending => true,
+                }
+        });
+        while self.pending_archive.len() > self.max_pending {
+            if let Some(oldest) = self.pending_archive.pop_front() {
+                match delete_file(fs, &oldest) {
+                    Ok(()) => {
+                        let reason = format!("more than {} pending", self.max_pending);
+                        deleted.push((oldest, reason));
+                    }
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        for (path, reason) in deleted {
+            self.deleted(&path, format_args!("{}", reason));
+        }
+        for err in &errors {
+            self.report(err);
+        }
+    }
+
+    /// Report and delete the files that the suffix scheme doesn't recognize, as configured.
+    fn handle_unrecognized(&mut self) {
+        if self.on_unrecognized.is_none() && !self.delete_unrecognized {
+            return;
+        }
+        for path in suffix::unrecognized(&*self.fs, &self.archive_basepath, &self.suffix_scheme) {
+            if let Some(ref callback) = self.on_unrecognized {
+                if self.reported_unrecognized.insert(path.clone()) {
+                    callback(&path);
+                }
+            }
+            if self.delete_unrecognized && self.resembles_rotated(&path) {
+                match delete_file(&*self.fs, &path) {
+                    Ok(()) => self.deleted(&path, format_args!("unrecognized")),
+                    Err(err) => self.report(&err),
+                }
+            }
+        }
+    }
+
+    /// Whether the suffix scheme takes the unrecognized file at `path` for a leftover of its own.
+    fn resembles_rotated(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(|file_name| {
+                self.suffix_scheme
+                    .suffix_of(&self.archive_basepath, file_name)
+            })
+            .is_some_and(|suffix| self.suffix_scheme.resembles(&suffix))
+    }
+
+    /// Pass `err` to the callback of [FileRotateBuilder::on_error].
+    fn report(&self, err: &Error) {
+        event!(Warn, "{}", err);
+        if let Some(ref callback) = self.on_error {
+            callback(err);
+        }
+        self.subscribers.send(|| err.into());
+        let mut history = self
+            .error_history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if history.len() >= ERROR_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(RecentError {
+            time: SystemTime::now(),
+            message: err.to_string(),
+        });
+    }
+
+    /// Tell that `path` was deleted because of `reason`.
+    fn deleted(&self, path: &Path, reason: std::fmt::Arguments) {
+        event!(Info, "deleted {} ({})", path.display(), reason);
+        self.subscribers.send(|| RotationEvent::Deleted {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Let the suffix scheme make room for a rotated file, keeping the files that are
+    /// [ArchiveDecision::Pending], and follow them where it moves them.
+    fn rotate_scheme(&mut self) -> String {
+        if !self.pending_archive.is_empty() {
+            let pending = self.pending_archive.iter().cloned().collect::<Vec<_>>();
+            if !self.suffix_scheme.protect(&pending) {
+                event!(Debug, "the suffix scheme doesn't keep pending files");
+            }
+        }
+        let suffix = self.suffix_scheme.rotate(&self.archive_basepath);
+        for (from, to) in self.suffix_scheme.take_moved() {
+            for pending in self.pending_archive.iter_mut() {
+                if *pending == from {
+                    *pending = to.clone();
+                }
+            }
+        }
+        suffix
+    }
+
+    /// Tell the subscribers about the files the suffix scheme deleted, which it logged itself, and
+    /// take those it left to be deleted.
+    fn publish_deleted(&mut self) {
+        self.pending_deletion
+            .extend(self.suffix_scheme.take_deferred());
+        for deleted in self.suffix_scheme.take_deleted() {
+            self.subscribers.send(|| RotationEvent::Deleted {
+                path: deleted.path,
+                reason: deleted.reason,
+            });
+        }
+    }
+
+    /// Tell the subscribers that the log file was rotated to `path`.
+    fn publish_rotated(&self, path: &Path) {
+        self.subscribers.send(|| RotationEvent::Rotated {
+            path: path.to_path_buf(),
+            size: self.fs.metadata(path).map_or(0, |metadata| metadata.len()),
+            suffix: path.file_name().and_then(|file_name| {
+                self.suffix_scheme
+                    .suffix_of(&self.archive_basepath, file_name)
+            }),
+        });
+    }
+
+    fn open_file(&mut self) -> Result<(), Error> {
+        match create_parent_dir(&*self.fs, &self.active)
+            .and_then(|()| (self.open)(&*self.fs, &self.active))
+        {
+            Ok(file) => self.file = Some(file),
+            Err(source) => {
+                return Err(Error::Open {
+                    path: self.active.clone(),
+                    source,
+                })
+            }
+        }
+        self.file_id = file_id(&*self.fs, &self.active);
+        self.active_bytes = self
+            .fs
+            .metadata(&self.active)
+            .map_or(0, |metadata| metadata.len());
+        if let RotateMechanics::DateInName { symlink: true } = self.rotate_mechanics {
+            if let Err(source) = link(&*self.fs, &self.active, &self.basepath) {
+                self.report(&Error::Link {
+                    path: self.basepath.clone(),
+                    source,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write to the current log file, creating it if necessary.
+    fn write_file(&mut self, buf: &[u8]) -> Result<(), Error> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if self.file.is_none() {
+            self.open_file()?;
+        }
+        let start_marker = self.start_marker.take();
+        let carried = self
+            .record_tail
+            .as_mut()
+            .and_then(|record_tail| record_tail.carry(buf));
+        if let Some(ref mut file) = self.file {
+            let before = start_marker
+                .as_ref()
+                .map(String::as_bytes)
+                .into_iter()
+                .chain(carried.as_deref());
+            for buf in before.chain(Some(buf)) {
+                if let Err(source) = file.write_all(buf) {
+                    return Err(Error::Write {
+                        path: self.active.clone(),
+                        source,
+                    });
+                }
+                self.active_bytes += buf.len() as u64;
+            }
+        }
+        if let Some(ref mut record_tail) = self.record_tail {
+            record_tail.written(buf);
+        }
+        self.mid_line = !buf.ends_with(b"\n");
+        Ok(())
+    }
+
+    /// Like [FileRotate::write_file], for several buffers at once, which doesn't keep track of
+    /// records or markers.
+    fn write_file_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<(), Error> {
+        if self.file.is_none() {
+            self.open_file()?;
+        }
+        if let Some(ref mut file) = self.file {
+            let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+            let mut bufs = bufs.iter().map(|buf| &**buf).collect::<Vec<&[u8]>>();
+            advance(&mut bufs, 0);
+            // Like `write_all`, which doesn't exist for vectored writes yet
+            while !bufs.is_empty() {
+                let slices = bufs.iter().map(|buf| IoSlice::new(buf)).collect::<Vec<_>>();
+                match file.write_vectored(&slices) {
+                    Ok(0) => {
+                        return Err(Error::Write {
+                            path: self.active.clone(),
+                            source: io::ErrorKind::WriteZero.into(),
+                        })
+                    }
+                    Ok(written) => advance(&mut bufs, written),
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(source) => {
+                        return Err(Error::Write {
+                            path: self.active.clone(),
+                            source,
+                        })
+                    }
+                }
+            }
+            self.active_bytes += len as u64;
+        }
+        Ok(())
+    }
+
+    fn flush_all(&mut self) -> Result<(), Error> {
+        self.unflushed_since = None;
+        if let Some(ref mut file) = self.file {
+            if let Err(source) = file.flush() {
+                return Err(Error::Flush {
+                    path: self.active.clone(),
+                    source,
+                });
+            }
+        }
+        if let Some((ref mut tee, policy)) = self.tee {
+            let result = tee.flush();
+            if policy == TeeErrorPolicy::Propagate {
+                result.map_err(|source| Error::Tee { source })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Catch up with what other writers did to the log file.
+    fn sync_with_disk(&mut self) {
+        if self.file.is_some() && file_id(&*self.fs, &self.active) != self.file_id {
+            // Someone else rotated the file
+            event!(
+                Debug,
+                "{} was replaced by another writer, reopening",
+                self.basepath.display()
+            );
+            let _ = self.file.take();
+            let _ = self.open_file();
+        }
+        if let ContentLimit::Bytes(_) | ContentLimit::BytesSurpassed(_) = self.content_limit {
+            if let Ok(metadata) = self.fs.metadata(&self.active) {
+                self.count = metadata.len() as usize;
+            }
+        }
+    }
+
+    /// Adopt the size of the log file on disk, if someone else changed it.
+    fn check_size(&mut self) {
+        self.writes_since_check = 0;
+        if let ContentLimit::Lines(_) | ContentLimit::Writes(_) = self.content_limit {
+            return;
+        }
+        let flushed = match self.file {
+            Some(ref mut file) => file.flush().is_ok(),
+            None => false,
+        };
+        let size = match self.fs.metadata(&self.active) {
+            Ok(metadata) if flushed => metadata.len() as usize,
+            _ => return,
+        };
+        if size != self.count {
+            let err = Error::SizeChanged {
+                path: self.active.clone(),
+                source: io::Error::other(format!("{} bytes instead of {}", size, self.count)),
+            };
+            self.report(&err);
+            self.count = size;
+        }
+    }
+
+    /// Rotate, carrying on with the current file if only the rename failed. Fails if there is no
+    /// file to write to, and otherwise returns whether writing continues in a new file.
+    fn rotate_or_continue(&mut self) -> Result<bool, Error> {
+        match self.rotate() {
+            Err(err @ Error::Open { .. }) => Err(err),
+            Err(err) => {
+                self.report(&err);
+                self.deferred_error.get_or_insert(err);
+                Ok(false)
+            }
+            Ok(()) => Ok(true),
+        }
+    }
+
+    /// Whether the log file may be rotated now because of the [ContentLimit], see
+    /// [FileRotateBuilder::min_rotation_interval]. Otherwise the rotation is counted as deferred.
+    fn rotation_due(&mut self) -> bool {
+        let too_soon = self
+            .rotation_attempted
+            .is_some_and(|attempted| attempted.elapsed() < self.min_rotation_interval);
+        if too_soon {
+            event!(
+                Debug,
+                "deferring rotation of {}, less than {:?} after the last one",
+                self.basepath.display(),
+                self.min_rotation_interval
+            );
+            self.deferred_rotations += 1;
+        }
+        !too_soon
+    }
+
+    /// Whether `bufs`, `len` bytes in total, can be written without rotating before or within them.
+    fn fits(&self, bufs: &[IoSlice<'_>], len: usize) -> bool {
+        match self.content_limit {
+            ContentLimit::Bytes(bytes) => self.count + len <= bytes,
+            ContentLimit::Lines(lines) => {
+                let newlines = bufs.iter().map(|buf| count_newlines(buf)).sum::<usize>();
+                newlines < lines.saturating_sub(self.count).max(1)
+            }
+            ContentLimit::BytesSurpassed(bytes) => self.count <= bytes,
+            // The rotation follows the write
+            ContentLimit::Writes(_) => true,
+        }
+    }
+
+    /// Write all of `bufs`, which [FileRotate::fits], to the log file in one go.
+    fn write_vectored_limited(&mut self, bufs: &[IoSlice<'_>], len: usize) -> Result<usize, Error> {
+        self.write_file_vectored(bufs)?;
+        match self.content_limit {
+            ContentLimit::Bytes(_) | ContentLimit::BytesSurpassed(_) => self.count += len,
+            ContentLimit::Lines(_) => {
+                self.count += bufs.iter().map(|buf| count_newlines(buf)).sum::<usize>()
+            }
+            ContentLimit::Writes(writes) => {
+                self.count += 1;
+                if self.count >= writes && self.rotation_due() {
+                    if let Err(err) = self.rotate_or_continue() {
+                        return partially_written(len, err);
+                    }
+                }
+            }
+        }
+        Ok(len)
+    }
+
+    /// Get ready to write `len` bytes: Pick up changes on disk and requests of the [ControlHandle].
+    fn prepare_write(&mut self, len: usize) -> Result<(), Error> {
+        if self.shared {
+            self.sync_with_disk();
+        } else if self.check_size_every > 0 {
+            self.writes_since_check += 1;
+            let full = match self.content_limit {
+                ContentLimit::Bytes(bytes) => self.count + len > bytes,
+                ContentLimit::BytesSurpassed(bytes) => self.count > bytes,
+                _ => false,
+            };
+            if full || self.writes_since_check >= self.check_size_every {
+                self.check_size();
+            }
+        }
+        // Reading the flags is much cheaper than clearing them, which matters for many small writes
+        if self.control.reopen.load(Ordering::Relaxed)
+            && self.control.reopen.swap(false, Ordering::SeqCst)
+        {
+            event!(Debug, "reopening {} as requested", self.basepath.display());
+            let _ = self.file.take();
+            self.count = existing_count(&*self.fs, &self.active, &self.content_limit).unwrap_or(0);
+        }
+        if self.file.is_none() {
+            self.open_file()?;
+        }
+        let rotate_requested = self.control.rotate.load(Ordering::Relaxed)
+            && self.control.rotate.swap(false, Ordering::SeqCst);
+        if rotate_requested {
+            event!(Debug, "rotation of {} requested", self.basepath.display());
+        }
+        if rotate_requested || self.pending_rotation.is_some() {
+            self.rotate_or_continue()?;
+        }
+        self.make_room(len)
+    }
+
+    /// Delete the oldest rotated files until `len` more bytes fit into the quota, see
+    /// [FileRotateBuilder::max_total_bytes].
+    fn make_room(&mut self, len: usize) -> Result<(), Error> {
+        let (quota, policy) = match self.quota {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+        let len = len as u64;
+        if let Some(rotated_bytes) = self.rotated_bytes {
+            if self.active_bytes + rotated_bytes + len <= quota {
+                return Ok(());
+            }
+        }
+        // Files that couldn't be deleted, which still take up their space
+        let mut undeletable = Vec::new();
+        // Over the quota as far as we know, so look at the sizes on disk
+        loop {
+            let sizes = self
+                .log_paths()
+                .into_iter()
+                .filter_map(|path| Some((self.fs.metadata(&path).ok()?.len(), path)))
+                .collect::<Vec<_>>();
+            let rotated_bytes = sizes.iter().map(|(size, _)| size).sum::<u64>();
+            self.rotated_bytes = Some(rotated_bytes);
+            let used = self.active_bytes + rotated_bytes;
+            if used + len <= quota {
+                return Ok(());
+            }
+            let oldest = sizes
+                .into_iter()
+                .map(|(_, path)| path)
+                .find(|path| !undeletable.contains(path));
+            match oldest {
+                Some(oldest) => match delete_file(&*self.fs, &oldest) {
+                    Ok(()) => {
+                        self.deleted(&oldest, format_args!("over the quota of {} bytes", quota))
+                    }
+                    // Try the next-oldest one instead
+                    Err(err) => {
+                        self.report(&err);
+                        self.deferred_error.get_or_insert(err);
+                        undeletable.push(oldest);
+                    }
+                },
+                None if policy == QuotaPolicy::RotateEarly && self.active_bytes > 0 => {
+                    event!(Debug, "rotating {} for the quota", self.active.display());
+                    self.rotate()?;
+                }
+                None => {
+                    return Err(Error::QuotaExceeded {
+                        path: self.active.clone(),
+                        source: io::Error::other(format!(
+                            "{} bytes don't fit into the {} of {} bytes left",
+                            len,
+                            quota.saturating_sub(used),
+                            quota
+                        )),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Pass what was written to the log files on to the tee writer.
+    fn tee(&mut self, bufs: &[&[u8]]) -> Result<(), Error> {
+        if let Some((ref mut tee, policy)) = self.tee {
+            let result = bufs.iter().try_for_each(|buf| tee.write_all(buf));
+            if policy == TeeErrorPolicy::Propagate {
+                result.map_err(|source| Error::Tee { source })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write (part of) `buf` to the log files, rotating as necessary. Returns how much was written.
+    fn write_limited(&mut self, mut buf: &[u8]) -> Result<usize, Error> {
+        let len = buf.len();
+        match self.content_limit {
+            ContentLimit::Bytes(bytes) if self.split_at == SplitPolicy::WholeWrites => {
+                // An empty file takes any write, so that no file stays empty
+                if self.count > 0 && self.count + len > bytes && self.rotation_due() {
+                    self.rotate_or_continue()?;
+                }
+                if let Err(err) = self.write_file(buf) {
+                    return partially_written(0, err);
+                }
+                self.count += len;
+            }
+            ContentLimit::Bytes(bytes) => {
+                while self.count + buf.len() > bytes && self.rotation_due() {
+                    let bytes_left = bytes.saturating_sub(self.count);
+                    let end = match self.split_at {
+                        SplitPolicy::Exact | SplitPolicy::WholeWrites => bytes_left,
+                        SplitPolicy::LineBoundary => {
+                            match last_newline(&buf[..bytes_left]) {
+                                Some(idx) => idx + 1,
+                                // The line doesn't fit, so finish it in this file
+                                None => match nth_newline(buf, 0) {
+                                    Some(idx) => idx + 1,
+                                    None => break,
+                                },
+                            }
+                        }
+                    };
+                    if let Err(err) = self.write_file(&buf[..end]) {
+                        return partially_written(len - buf.len(), err);
+                    }
+                    self.count += end;
+                    buf = &buf[end..];
+                    match self.rotate_or_continue() {
+                        Ok(true) => {}
+                        // The rest goes into the same file, the rotation is retried at the next write
+                        Ok(false) => break,
+                        Err(err) => return partially_written(len - buf.len(), err),
+                    }
+                }
+                if let Err(err) = self.write_file(buf) {
+                    return partially_written(len - buf.len(), err);
+                }
+                self.count += buf.len();
+            }
+            ContentLimit::Lines(lines) => {
+                // An existing file may already be over the limit, it gets one more line then
+                let lines_left = |count: usize| lines.saturating_sub(count).max(1);
+                while let Some(idx) = nth_newline(buf, lines_left(self.count) - 1) {
+                    if !self.rotation_due() {
+                        break;
+                    }
+                    if let Err(err) = self.write_file(&buf[..idx + 1]) {
+                        return partially_written(len - buf.len(), err);
+                    }
+                    self.count += count_newlines(&buf[..idx + 1]);
+                    buf = &buf[idx + 1..];
+                    match self.rotate_or_continue() {
+                        Ok(true) => {}
+                        // The rest goes into the same file, the rotation is retried at the next write
+                        Ok(false) => break,
+                        Err(err) => return partially_written(len - buf.len(), err),
+                    }
+                }
+                if let Err(err) = self.write_file(buf) {
+                    return partially_written(len - buf.len(), err);
+                }
+                self.count += count_newlines(buf);
+            }
+            ContentLimit::BytesSurpassed(bytes) => {
+                if self.count > bytes && self.rotation_due() {
+                    self.rotate_or_continue()?;
+                }
+                if let Err(err) = self.write_file(buf) {
+                    return partially_written(len - buf.len(), err);
+                }
+                self.count += buf.len();
+            }
+            ContentLimit::Writes(writes) => {
+                if let Err(err) = self.write_file(buf) {
+                    return partially_written(0, err);
+                }
+                self.count += 1;
+                if self.count >= writes && self.rotation_due() {
+                    if let Err(err) = self.rotate_or_continue() {
+                        return partially_written(len, err);
+                    }
+                }
+            }
+        }
+        Ok(len)
+    }
+}
+
+impl<S, W: Write, Fs: FsOps> Drop for FileRotate<S, W, Fs> {
+    fn drop(&mut self) {
+        let mut errors = Vec::new();
+        for pending in self.pending_deletion.drain(..) {
+            match delete_file(&*self.fs, &pending.path) {
+                Ok(()) => {
+                    event!(
+                        Info,
+                        "deleted {} ({})",
+                        pending.path.display(),
+                        pending.reason
+                    );
+                    self.subscribers.send(|| RotationEvent::Deleted {
+                        path: pending.path,
+                        reason: pending.reason,
+                    });
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+        if let Some(ref mut file) = self.file {
+            if let Err(source) = file.flush() {
+                errors.push(Error::Flush {
+                    path: self.active.clone(),
+                    source,
+                });
+            }
+        }
+        if let Some((ref mut tee, _)) = self.tee {
+            if let Err(source) = tee.flush() {
+                errors.push(Error::Tee { source });
+            }
+        }
+        for err in &errors {
+            event!(Warn, "{}", err);
+            if let Some(ref callback) = self.on_error {
+                callback(err);
+            }
+        }
+    }
+}
+
+impl<S: suffix::SuffixScheme, W: Write, Fs: FsOps> Write for FileRotate<S, W, Fs> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.prepare_write(buf.len())?;
+        let written = self.write_limited(buf)?;
+        self.tee(&[&buf[..written]])?;
+        self.written();
+        Ok(written)
+    }
+
+    /// Writes all of `bufs` as one record: They are passed on to the writer together unless a
+    /// rotation falls within them, in which case they are split like a single buffer would be.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        self.prepare_write(len)?;
+        // Only `write_file` keeps track of records and markers
+        if !self.fits(bufs, len) || self.record_tail.is_some() || self.rotation_markers {
+            let joined = bufs
+                .iter()
+                .flat_map(|buf| buf.iter().copied())
+                .collect::<Vec<_>>();
+            let written = self.write_limited(&joined)?;
+            self.tee(&[&joined[..written]])?;
+            self.written();
+            return Ok(written);
+        }
+        let written = self.write_vectored_limited(bufs, len)?;
+        self.tee(&bufs.iter().map(|buf| &buf[..]).collect::<Vec<_>>())?;
+        self.written();
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(self.flush_all()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{suffix::*, *};
+    use std::fs;
+    use tempdir::TempDir;
+
+    // Just useful to debug why test doesn't succeed
+    #[allow(dead_code)]
+    fn list(dir: &Path) {
+        let filenames = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>();
+        println!("Files on disk: {:?}", filenames);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_max_files_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let mut log = FileRotate::new(
+            &log_path,
+            TimestampSuffix::default(FileLimit::MaxFiles(4)),
+            ContentLimit::Lines(2),
+        );
+
+        // Write 9 lines
+        // This should result in 5 files in total (4 rotated files). The main file will have one line.
+        write!(log, "a\nb\nc\nd\ne\nf\ng\nh\ni\n").unwrap();
+        let log_paths = log.log_paths();
+        assert_eq!(log_paths.len(), 4);
+
+        // Log names should be sorted. Low (old timestamp) to high (more recent timestamp)
+        let mut log_paths_sorted = log_paths.clone();
+        log_paths_sorted.sort();
+        assert_eq!(log_paths, log_paths_sorted);
+
+        assert_eq!("a\nb\n", fs::read_to_string(&log_paths[0]).unwrap());
+        assert_eq!("c\nd\n", fs::read_to_string(&log_paths[1]).unwrap());
+        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[2]).unwrap());
+        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[3]).unwrap());
+        assert_eq!("i\n", fs::read_to_string(&log_path).unwrap());
+
+        // Write 4 more lines
+        write!(log, "j\nk\nl\nm\n").unwrap();
+        let log_paths = log.log_paths();
+        assert_eq!(log_paths.len(), 4);
+        let mut log_paths_sorted = log_paths.clone();
+        log_paths_sorted.sort();
+        assert_eq!(log_paths, log_paths_sorted);
+
+        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[0]).unwrap());
+        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[1]).unwrap());
+        assert_eq!("i\nj\n", fs::read_to_string(&log_paths[2]).unwrap());
+        assert_eq!("k\nl\n", fs::read_to_string(&log_paths[3]).unwrap());
+        assert_eq!("m\n", fs::read_to_string(&log_path).unwrap());
+    }
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_age_rotation() {
+        // In order not to have to sleep, and keep it deterministic, let's already create the log files and see how FileRotate
+        // cleans up the old ones.
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+
+        // One recent file:
+        let recent_file = chrono::offset::Local::now()
+            .format("log.%Y%m%dT%H%M%S")
+            .to_string();
+        File::create(dir.join(&recent_file)).unwrap();
+        // Two very old files:
+        File::create(dir.join("log.20200825T151133")).unwrap();
+        File::create(dir.join("log.20200825T151133.1")).unwrap();
+
+        let mut log = FileRotate::new(
+            &*log_path.to_string_lossy(),
+            TimestampSuffix::default(FileLimit::Age(std::time::Duration::from_secs(
+                7 * 24 * 3600,
+            ))),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "trigger\nat\nleast\none\nrotation").unwrap();
+
+        let mut filenames = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        filenames.sort();
+        assert!(filenames.contains(&"log".to_string()));
+        assert!(filenames.contains(&recent_file));
+        assert!(!filenames.contains(&"log.20200825T151133".to_string()));
+        assert!(!filenames.contains(&"log.20200825T151133.1".to_string()));
+    }
+    #[test]
+    fn count_max_files_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let parent = tmp_dir.path();
+        let log_path = parent.join("log");
+        let mut log = FileRotate::new(
+            &*log_path.to_string_lossy(),
+            CountSuffix::new(4),
+            ContentLimit::Lines(2),
+        );
+
+        // Write 9 lines
+        // This should result in 5 files in total (4 rotated files). The main file will have one line.
+        write!(log, "a\nb\nc\nd\ne\nf\ng\nh\ni\n").unwrap(); // 9 lines
+        let log_paths = vec![
+            parent.join("log.4"),
+            parent.join("log.3"),
+            parent.join("log.2"),
+            parent.join("log.1"),
+        ];
+        assert_eq!(log_paths, log.log_paths());
+        assert_eq!("a\nb\n", fs::read_to_string(&log_paths[0]).unwrap());
+        assert_eq!("c\nd\n", fs::read_to_string(&log_paths[1]).unwrap());
+        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[2]).unwrap());
+        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[3]).unwrap());
+        assert_eq!("i\n", fs::read_to_string(&log_path).unwrap());
+
+        // Write 4 more lines
+        write!(log, "j\nk\nl\nm\n").unwrap();
+        list(parent);
+        assert_eq!(log_paths, log.log_paths());
+
+        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[0]).unwrap());
+        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[1]).unwrap());
+        assert_eq!("i\nj\n", fs::read_to_string(&log_paths[2]).unwrap());
+        assert_eq!("k\nl\n", fs::read_to_string(&log_paths[3]).unwrap());
+        assert_eq!("m\n", fs::read_to_string(&log_path).unwrap());
+    }
+
+    #[test]
+    fn count_reduced_max_files() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        // Files from a previous run with a larger `max_files`, with a gap in the numbering
+        for n in (1..=20).filter(|n| *n != 10) {
+            File::create(dir.join(format!("log.{}", n))).unwrap();
+        }
+
+        let mut log = FileRotate::new(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1));
+        writeln!(log, "a").unwrap();
+
+        let mut filenames = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        filenames.sort();
+        assert_eq!(vec!["log", "log.1", "log.2", "log.3"], filenames);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_reduced_max_files() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        for n in 1..=20 {
+            File::create(dir.join(format!("log.20200825T151133.{}", n))).unwrap();
+        }
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::MaxFiles(3)),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+
+        let filenames = fs::read_dir(dir).unwrap().count();
+        assert_eq!(4, filenames);
+        assert_eq!(3, log.log_paths().len());
+        for path in log.log_paths() {
+            assert!(path.exists());
+        }
+    }
+
+    #[test]
+    fn sequence_max_files_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        File::create(dir.join("log.7")).unwrap();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            SequenceSuffix::new(3),
+            ContentLimit::Lines(2),
+        );
+        write!(log, "a\nb\nc\nd\ne\nf\ng\n").unwrap();
+
+        let log_paths = vec![dir.join("log.8"), dir.join("log.9"), dir.join("log.10")];
+        assert_eq!(log_paths, log.log_paths());
+        assert_eq!("a\nb\n", fs::read_to_string(&log_paths[0]).unwrap());
+        assert_eq!("c\nd\n", fs::read_to_string(&log_paths[1]).unwrap());
+        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[2]).unwrap());
+        assert_eq!("g\n", fs::read_to_string(dir.join("log")).unwrap());
+        assert!(!dir.join("log.7").exists());
+    }
+
+    #[test]
+    fn writer_factory() {
+        struct Uppercase(File);
+        impl Write for Uppercase {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(&buf.to_ascii_uppercase())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                self.0.flush()
+            }
+        }
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let opened = Arc::new(Mutex::new(Vec::new()));
+
+        let opened2 = opened.clone();
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1))
+                .writer_factory(move |path| {
+                    opened2.lock().unwrap().push(path.to_path_buf());
+                    File::create(path).map(Uppercase)
+                })
+                .build();
+        write!(log, "a\nb\nc").unwrap();
+        log.flush().unwrap();
+
+        assert_eq!(vec![dir.join("log"); 3], *opened.lock().unwrap());
+        assert_eq!("A\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("B\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("C", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn split_at_line_boundary() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            CountSuffix::new(5),
+            ContentLimit::Bytes(10),
+        )
+        .split_at(SplitPolicy::LineBoundary)
+        .build();
+
+        write!(log, "aaa\nbbb\nccc\n").unwrap();
+        assert_eq!("aaa\nbbb\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("ccc\n", fs::read_to_string(dir.join("log")).unwrap());
+
+        // A line longer than the limit is not split, even across writes
+        write!(log, "dddddddddd").unwrap();
+        write!(log, "dddddddddd\ne").unwrap();
+        assert_eq!(
+            "ccc\ndddddddddddddddddddd\n",
+            fs::read_to_string(dir.join("log.1")).unwrap()
+        );
+        assert_eq!("e", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn open_behavior() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let open = |name: &str, behavior| {
+            fs::write(dir.join(name), "old 1\nold 2\n").unwrap();
+            FileRotate::builder(dir.join(name), CountSuffix::new(3), ContentLimit::Lines(3))
+                .open_behavior(behavior)
+                .build()
+        };
+
+        let mut log = open("append", OpenBehavior::Append);
+        write!(log, "new 1\nnew 2\n").unwrap();
+        assert_eq!(
+            "old 1\nold 2\nnew 1\n",
+            fs::read_to_string(dir.join("append.1")).unwrap()
+        );
+        assert_eq!("new 2\n", fs::read_to_string(dir.join("append")).unwrap());
+
+        let mut log = open("truncate", OpenBehavior::Truncate);
+        writeln!(log, "new 1").unwrap();
+        assert_eq!("new 1\n", fs::read_to_string(dir.join("truncate")).unwrap());
+        assert!(log.log_paths().is_empty());
+
+        let mut log = open("rotate", OpenBehavior::RotateExisting);
+        writeln!(log, "new 1").unwrap();
+        assert_eq!(
+            "old 1\nold 2\n",
+            fs::read_to_string(dir.join("rotate.1")).unwrap()
+        );
+        assert_eq!("new 1\n", fs::read_to_string(dir.join("rotate")).unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn shared_writers() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let open = || {
+            FileRotate::builder(
+                dir.join("log"),
+                CountSuffix::new(3),
+                ContentLimit::Bytes(10),
+            )
+            .open_behavior(OpenBehavior::Append)
+            .shared(true)
+            .build()
+        };
+        let mut first = open();
+        let mut second = open();
+
+        write!(first, "aaaa").unwrap();
+        write!(second, "bbbb").unwrap();
+        write!(first, "cccc").unwrap();
+        assert_eq!("aaaabbbbcc", fs::read_to_string(dir.join("log.1")).unwrap());
+        // `second` notices that `first` rotated the file
+        write!(second, "dd").unwrap();
+        assert_eq!("ccdd", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn non_blocking() {
+        use non_blocking::{NonBlockingFileRotate, OverflowPolicy};
+        use std::sync::{mpsc, Arc, Mutex};
+
+        /// Lets the test know when the worker is writing, and blocks it while `gate` is locked
+        struct Gate {
+            file: File,
+            entered: mpsc::Sender<()>,
+            gate: Arc<Mutex<()>>,
+        }
+        impl Write for Gate {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let _ = self.entered.send(());
+                let _lock = self.gate.lock().unwrap();
+                self.file.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                self.file.flush()
+            }
+        }
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let log = FileRotate::new(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1));
+        let (mut writer, guard) = NonBlockingFileRotate::new(log, 10, OverflowPolicy::Block);
+        write!(writer, "a\nb\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        write!(writer, "c").unwrap();
+        drop(guard);
+        assert_eq!("c", fs::read_to_string(dir.join("log")).unwrap());
+        assert!(write!(writer, "d").is_err());
+
+        let (entered, entered_receiver) = mpsc::channel();
+        let gate = Arc::new(Mutex::new(()));
+        let log_with_gate = |path: PathBuf| {
+            let entered = entered.clone();
+            let gate = gate.clone();
+            FileRotate::builder(path, CountSuffix::new(3), ContentLimit::Lines(10))
+                .writer_factory(move |path| {
+                    Ok(Gate {
+                        file: File::create(path)?,
+                        entered: entered.clone(),
+                        gate: gate.clone(),
+                    })
+                })
+                .build()
+        };
+        let (mut writer, _guard) = NonBlockingFileRotate::new(
+            log_with_gate(dir.join("drop")),
+            1,
+            OverflowPolicy::DropNewest,
+        );
+        let lock = gate.lock().unwrap();
+        writer.write_all(b"a").unwrap();
+        // The worker is blocked writing "a", so "b" fills the queue and "c" is dropped
+        entered_receiver.recv().unwrap();
+        writer.write_all(b"b").unwrap();
+        writer.write_all(b"c").unwrap();
+        assert_eq!(1, writer.dropped());
+        drop(lock);
+        writer.flush().unwrap();
+        assert_eq!("ab", fs::read_to_string(dir.join("drop")).unwrap());
+
+        let (mut writer, _guard) = NonBlockingFileRotate::with_drop_marker(
+            log_with_gate(dir.join("oldest")),
+            2,
+            OverflowPolicy::DropOldest,
+            Box::new(non_blocking::drop_marker),
+        );
+        // Forget the writes so far
+        entered_receiver.try_iter().for_each(drop);
+        let lock = gate.lock().unwrap();
+        writer.write_all(b"a\n").unwrap();
+        entered_receiver.recv().unwrap();
+        for line in &["b\n", "c\n", "d\n", "e\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        assert_eq!(2, writer.dropped());
+        drop(lock);
+        writer.flush().unwrap();
+        assert_eq!(
+            "a\nd\ne\n--- 2 records dropped ---\n",
+            fs::read_to_string(dir.join("oldest")).unwrap()
+        );
+
+        let (mut writer, guard) = NonBlockingFileRotate::new(
+            log_with_gate(dir.join("timeout")),
+            1,
+            OverflowPolicy::BlockTimeout(std::time::Duration::from_millis(10)),
+        );
+        entered_receiver.try_iter().for_each(drop);
+        let lock = gate.lock().unwrap();
+        writer.write_all(b"a").unwrap();
+        entered_receiver.recv().unwrap();
+        writer.write_all(b"b").unwrap();
+        let err = writer.write_all(b"c").unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+        assert_eq!(0, writer.dropped());
+        drop(lock);
+        // Shutting down writes what is queued
+        drop(guard);
+        assert_eq!("ab", fs::read_to_string(dir.join("timeout")).unwrap());
+    }
+
+    #[test]
+    fn flush_interval() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let buffered = |path: &Path, interval: std::time::Duration| {
+            FileRotate::builder(path, CountSuffix::new(3), ContentLimit::Lines(10))
+                .writer_factory(|path| File::create(path).map(io::BufWriter::new))
+                .flush_interval(interval)
+                .build()
+        };
+
+        let mut log = buffered(&dir.join("log"), std::time::Duration::ZERO);
+        write!(log, "a").unwrap();
+        assert_eq!("a", fs::read_to_string(dir.join("log")).unwrap());
+
+        // Far from due, however slowly the test runs
+        let mut log = buffered(&dir.join("log"), std::time::Duration::from_secs(3600));
+        write!(log, "b").unwrap();
+        log.flush_if_due().unwrap();
+        assert_eq!("", fs::read_to_string(dir.join("log")).unwrap());
+
+        let mut log = buffered(&dir.join("log"), std::time::Duration::from_millis(50));
+        write!(log, "c").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        log.flush_if_due().unwrap();
+        assert_eq!("c", fs::read_to_string(dir.join("log")).unwrap());
+
+        // The thread flushes without being asked to
+        use non_blocking::{NonBlockingFileRotate, OverflowPolicy};
+        let log = buffered(&dir.join("log"), std::time::Duration::from_millis(10));
+        let (mut writer, _guard) = NonBlockingFileRotate::new(log, 10, OverflowPolicy::Block);
+        write!(writer, "d").unwrap();
+        let start = Instant::now();
+        while fs::read_to_string(dir.join("log")).unwrap() != "d" {
+            assert!(start.elapsed() < std::time::Duration::from_secs(10));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn async_write() {
+        use asynchronous::AsyncFileRotate;
+        use futures::io::AsyncWriteExt;
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let log = FileRotate::new(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1));
+        let mut log = AsyncFileRotate::new(log, 1);
+        futures::executor::block_on(async {
+            log.write_all(b"a\nb\n").await.unwrap();
+            log.write_all(b"c").await.unwrap();
+            log.close().await.unwrap();
+        });
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("c", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "slog")]
+    fn slog_drain() {
+        use ::slog::Drain;
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let log = FileRotate::builder(
+            dir.join("log"),
+            CountSuffix::new(10),
+            ContentLimit::Bytes(100),
+        )
+        .split_at(SplitPolicy::LineBoundary)
+        .build();
+        let logger = ::slog::Logger::root(
+            slog::FileRotateDrain::new(log).fuse(),
+            ::slog::o!("app" => "test"),
+        );
+        for idx in 0..10 {
+            ::slog::info!(logger, "record {}", idx; "key" => "value");
+        }
+        drop(logger);
+
+        let mut paths = vec![dir.join("log")];
+        paths.extend((1..=10).map(|n| dir.join(format!("log.{}", n))));
+        let mut records = 0;
+        for path in paths.iter().filter(|path| path.exists()) {
+            for line in fs::read_to_string(path).unwrap().lines() {
+                assert!(line.contains(" INFO record "), "torn record {:?}", line);
+                assert!(
+                    line.ends_with("key: value, app: test"),
+                    "torn record {:?}",
+                    line
+                );
+                records += 1;
+            }
+        }
+        assert_eq!(10, records);
+    }
+
+    #[test]
+    fn control_handle() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            CountSuffix::new(3),
+            ContentLimit::Lines(10),
+        );
+        let handle = log.control_handle();
+        writeln!(log, "a").unwrap();
+        handle.request_rotate();
+        writeln!(log, "b").unwrap();
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("log")).unwrap());
+
+        // Moved away by an external tool
+        fs::rename(dir.join("log"), dir.join("external")).unwrap();
+        handle.reopen_flag().store(true, Ordering::SeqCst);
+        writeln!(log, "c").unwrap();
+        assert_eq!("b\n", fs::read_to_string(dir.join("external")).unwrap());
+        assert_eq!("c\n", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn lazy_create() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path().join("logs");
+
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1))
+                .lazy_create(true)
+                .build();
+        assert_eq!(dir.join("log"), log.current_path());
+        assert!(!dir.exists());
+        write!(log, "a").unwrap();
+        assert_eq!("a", fs::read_to_string(dir.join("log")).unwrap());
+
+        // An existing file is rotated right away, but the new one is only created when writing
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1))
+                .open_behavior(OpenBehavior::RotateExisting)
+                .lazy_create(true)
+                .build();
+        assert_eq!("a", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert!(!dir.join("log").exists());
+        write!(log, "b").unwrap();
+        assert_eq!("b", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn write_limit() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(3), ContentLimit::Writes(2));
+        log.write_all(b"\n\na").unwrap();
+        log.write_all(b"b\n").unwrap();
+        log.write_all(b"\x00\n\x01").unwrap();
+        assert_eq!(
+            b"\n\nab\n",
+            &fs::read(tmp_dir.path().join("log.1")).unwrap()[..]
+        );
+        assert_eq!(b"\x00\n\x01", &fs::read(&log_path).unwrap()[..]);
+        log.write_all(b"c").unwrap();
+        assert_eq!(
+            b"\x00\n\x01c",
+            &fs::read(tmp_dir.path().join("log.1")).unwrap()[..]
+        );
+        assert_eq!(b"", &fs::read(&log_path).unwrap()[..]);
+    }
+
+    #[test]
+    fn on_rotated() {
+        use std::sync::Mutex;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let decision = Arc::new(Mutex::new(ArchiveDecision::Pending));
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut log = {
+            let (decision, calls) = (decision.clone(), calls.clone());
+            FileRotate::builder(
+                dir.join("log"),
+                SequenceSuffix::new(10),
+                ContentLimit::Lines(1),
+            )
+            .on_rotated(move |path| {
+                calls.lock().unwrap().push(path.to_path_buf());
+                *decision.lock().unwrap()
+            })
+            .max_pending(2)
+            .build()
+        };
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
+        assert_eq!(
+            vec![dir.join("log.1"), dir.join("log.1"), dir.join("log.2")],
+            *calls.lock().unwrap()
+        );
+        assert!(log.mark_archived(&dir.join("log.1")));
+        assert!(!log.mark_archived(&dir.join("log.1")));
+        assert!(!dir.join("log.1").exists());
+
+        // Beyond `max_pending`, the oldest pending file is deleted
+        writeln!(log, "c").unwrap();
+        writeln!(log, "d").unwrap();
+        assert!(!dir.join("log.2").exists());
+        assert!(dir.join("log.3").exists());
+        assert!(dir.join("log.4").exists());
+
+        *decision.lock().unwrap() = ArchiveDecision::DeleteNow;
+        writeln!(log, "e").unwrap();
+        for n in 3..=5 {
+            assert!(!dir.join(format!("log.{}", n)).exists());
+        }
+
+        *decision.lock().unwrap() = ArchiveDecision::KeepLocal;
+        writeln!(log, "f").unwrap();
+        assert_eq!("f\n", fs::read_to_string(dir.join("log.6")).unwrap());
+    }
+
+    #[test]
+    fn pending_files_are_kept() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        // Only the first file stays pending, as if its upload hung
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(2), ContentLimit::Lines(1))
+                .on_rotated(|path| match fs::read_to_string(path).unwrap().as_str() {
+                    "a\n" => ArchiveDecision::Pending,
+                    _ => ArchiveDecision::KeepLocal,
+                })
+                .build();
+        for line in ["a", "b", "c", "d", "e"] {
+            writeln!(log, "{}", line).unwrap();
+        }
+        // Older than the 2 files kept, but followed through the renames and not deleted
+        assert_eq!(
+            vec![dir.join("log.4"), dir.join("log.2"), dir.join("log.1")],
+            log.log_paths()
+        );
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.4")).unwrap());
+        assert_eq!(vec![dir.join("log.4")], log.snapshot().pending_archive);
+
+        assert!(log.mark_archived(&dir.join("log.4")));
+        assert!(!dir.join("log.4").exists());
+        writeln!(log, "f").unwrap();
+        assert_eq!(vec![dir.join("log.2"), dir.join("log.1")], log.log_paths());
+    }
+
+    #[test]
+    fn count_suffix_numbers() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        for name in &["log.01", "log.2", "log.+5", "log.99999999999999999999"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let mut log = FileRotate::new(dir.join("log"), CountSuffix::new(5), ContentLimit::Lines(1));
+        assert_eq!(
+            vec![dir.join("log.+5"), dir.join("log.99999999999999999999")],
+            log.scan_report().unwrap().unrecognized
+        );
+
+        // `log.01` is renamed to `log.1` before the cascade
+        writeln!(log, "a").unwrap();
+        assert_eq!("log.01", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("log.2", fs::read_to_string(dir.join("log.3")).unwrap());
+        assert_eq!(
+            vec![dir.join("log.3"), dir.join("log.2"), dir.join("log.1")],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    fn unrecognized_files() {
+        use std::sync::Mutex;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        for name in &[
+            "log.3",
+            "log.4.bak",
+            "log.bak",
+            "log.corrupted-20240601",
+            "log2.1",
+        ] {
+            File::create(dir.join(name)).unwrap();
+        }
+        let unrecognized = vec![
+            dir.join("log.4.bak"),
+            dir.join("log.bak"),
+            dir.join("log.corrupted-20240601"),
+        ];
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let mut log = {
+            let reported = reported.clone();
+            FileRotate::builder(dir.join("log"), CountSuffix::new(5), ContentLimit::Lines(1))
+                .on_unrecognized(move |path| reported.lock().unwrap().push(path.to_path_buf()))
+                .build()
+        };
+        let report = log.scan_report().unwrap();
+        assert_eq!(
+            vec![dir.join("log.3")],
+            report
+                .recognized
+                .iter()
+                .map(|file| file.path.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(unrecognized, report.unrecognized);
+
+        // Each file is reported once
+        writeln!(log, "a").unwrap();
+        let mut reported = reported.lock().unwrap().clone();
+        reported.sort();
+        assert_eq!(unrecognized, reported);
+
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(5), ContentLimit::Lines(1))
+                .delete_unrecognized(true)
+                .build();
+        writeln!(log, "b").unwrap();
+        // Only the leftover of a rotated file
+        assert_eq!(&unrecognized[1..], log.scan_report().unwrap().unrecognized);
+        for name in &["log", "log.1", "log.2", "log.3", "log2.1"] {
+            assert!(dir.join(name).exists(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn delete_unrecognized_keeps_other_logs() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        // Other logs next to `app`, and leftovers of its rotated files
+        for name in &["app.log", "app.err", "app.json", "app.2.gz", "app.3~"] {
+            File::create(dir.join(name)).unwrap();
+        }
+        let mut log =
+            FileRotate::builder(dir.join("app"), CountSuffix::new(5), ContentLimit::Lines(1))
+                .delete_unrecognized(true)
+                .build();
+        writeln!(log, "a").unwrap();
+        for name in &["app.log", "app.err", "app.json", "app.1"] {
+            assert!(dir.join(name).exists(), "{}", name);
+        }
+        assert!(!dir.join("app.2.gz").exists());
+        assert!(!dir.join("app.3~").exists());
+    }
+
+    #[test]
+    fn archive_dir() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+        let archive = tmp_dir.path().join("archive");
+
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(2), ContentLimit::Lines(1))
+            .archive_dir(&archive)
+            .build();
+        for line in ["a", "b", "c"].iter() {
+            writeln!(log, "{}", line).unwrap();
+        }
+        write!(log, "d").unwrap();
+        assert_eq!(
+            vec![archive.join("log.2"), archive.join("log.1")],
+            log.log_paths()
+        );
+        assert_eq!("b\n", fs::read_to_string(archive.join("log.2")).unwrap());
+        assert_eq!("c\n", fs::read_to_string(archive.join("log.1")).unwrap());
+        assert_eq!("d", fs::read_to_string(&log_path).unwrap());
+        assert!(!archive.join("log.3").exists());
+        assert!(!tmp_dir.path().join("log.1").exists());
+    }
+
+    #[test]
+    fn lazy_recreate() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(3), ContentLimit::Lines(1))
+            .lazy_recreate(true)
+            .build();
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            "a\n",
+            fs::read_to_string(tmp_dir.path().join("log.1")).unwrap()
+        );
+        assert!(!log_path.exists());
+        write!(log, "b").unwrap();
+        assert_eq!("b", fs::read_to_string(&log_path).unwrap());
+    }
+
+    #[test]
+    fn tee() {
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let secondary = Shared::default();
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1))
+                .tee(secondary.clone(), TeeErrorPolicy::Propagate)
+                .build();
+        write!(log, "a\nb\nc").unwrap();
+        log.flush().unwrap();
+        assert_eq!(b"a\nb\nc", &secondary.0.lock().unwrap()[..]);
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.2")).unwrap());
+
+        let mut log = FileRotate::builder(
+            dir.join("ignore"),
+            CountSuffix::new(3),
+            ContentLimit::Lines(1),
+        )
+        .tee(Broken, TeeErrorPolicy::Ignore)
+        .build();
+        writeln!(log, "a").unwrap();
+        log.flush().unwrap();
+        assert_eq!("a\n", fs::read_to_string(dir.join("ignore.1")).unwrap());
+
+        let mut log = FileRotate::builder(
+            dir.join("propagate"),
+            CountSuffix::new(3),
+            ContentLimit::Lines(1),
+        )
+        .tee(Broken, TeeErrorPolicy::Propagate)
+        .build();
+        assert!(writeln!(log, "a").is_err());
+        assert!(log.flush().is_err());
+        assert_eq!("a\n", fs::read_to_string(dir.join("propagate.1")).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        use config::*;
+        use serde::{de::DeserializeOwned, Serialize};
+
+        fn round_trip<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(value: T) {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(value, serde_json::from_str::<T>(&json).unwrap(), "{}", json);
+        }
+
+        round_trip(ContentLimit::Bytes(100_000_000));
+        round_trip(ContentLimit::Lines(1000));
+        round_trip(ContentLimit::BytesSurpassed(1));
+        round_trip(FileLimit::MaxFiles(5));
+        round_trip(FileLimit::Age(std::time::Duration::from_secs(
+            7 * 24 * 3600,
+        )));
+        round_trip(FileLimit::Age(std::time::Duration::from_millis(1500)));
+        round_trip(FileLimit::AgeWithMinFiles {
+            age: std::time::Duration::from_secs(24 * 3600),
+            min_files: 3,
+        });
+        round_trip(FileLimit::KeepPerPeriod {
+            recent: chrono::Duration::hours(24),
+            per_period: 1,
+            period: Period::Day,
+        });
+        round_trip(FileLimit::Unlimited);
+        round_trip(DateFrom::Now);
+        round_trip(DateFrom::Custom(chrono::Duration::minutes(-15)));
+        for suffix in [
+            SuffixConfig::Count(CountConfig {
+                max_files: 3,
+                separator: "-".to_string(),
+            }),
+            SuffixConfig::Sequence(CountConfig {
+                max_files: 3,
+                separator: ".".to_string(),
+            }),
+            SuffixConfig::Timestamp(TimestampConfig {
+                format: "%Y%m%d".to_string(),
+                file_limit: FileLimit::Age(std::time::Duration::from_secs(7 * 24 * 3600)),
+                date_from: DateFrom::Now,
+                separator: ".".to_string(),
+            }),
+            SuffixConfig::Epoch(EpochConfig {
+                file_limit: FileLimit::MaxFiles(3),
+                separator: ".".to_string(),
+            }),
+        ] {
+            round_trip(RotationConfig {
+                path: "logs/log".into(),
+                suffix,
+                content_limit: ContentLimit::Lines(10),
+            });
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_human_friendly_values() {
+        use config::*;
+
+        let config: RotationConfig = serde_json::from_str(
+            r#"{
+                "path": "logs/log",
+                "suffix": { "scheme": "timestamp", "file_limit": { "age": "7d" } },
+                "content_limit": { "bytes": "100MB" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(ContentLimit::Bytes(100_000_000), config.content_limit);
+        assert_eq!(
+            SuffixConfig::Timestamp(TimestampConfig {
+                format: "%Y%m%dT%H%M%S".to_string(),
+                file_limit: FileLimit::Age(std::time::Duration::from_secs(7 * 24 * 3600)),
+                date_from: DateFrom::Now,
+                separator: ".".to_string(),
+            }),
+            config.suffix
+        );
+
+        let parse_limit = |json: &str| serde_json::from_str::<ContentLimit>(json);
+        assert_eq!(
+            ContentLimit::Bytes(1 << 20),
+            parse_limit(r#"{"bytes": "1 MiB"}"#).unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Bytes(1500),
+            parse_limit(r#"{"bytes": "1.5kb"}"#).unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Bytes(512),
+            parse_limit(r#"{"bytes": 512}"#).unwrap()
+        );
+        assert!(parse_limit(r#"{"bytes": "12 parsecs"}"#).is_err());
+        assert!(parse_limit(r#"{"bytes": -1}"#).is_err());
+
+        let parse_file_limit = |json: &str| serde_json::from_str::<FileLimit>(json);
+        assert_eq!(
+            FileLimit::Age(std::time::Duration::from_secs(12 * 3600)),
+            parse_file_limit(r#"{"age": "12h"}"#).unwrap()
+        );
+        assert_eq!(
+            FileLimit::Age(std::time::Duration::from_secs(90)),
+            parse_file_limit(r#"{"age": 90}"#).unwrap()
+        );
+        assert!(parse_file_limit(r#"{"age": "7 fortnights"}"#).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn config_build_errors() {
+        use config::*;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let config = |suffix, content_limit| RotationConfig {
+            path: tmp_dir.path().join("log"),
+            suffix,
+            content_limit,
+        };
+        let count = |separator: &str| {
+            SuffixConfig::Count(CountConfig {
+                max_files: 3,
+                separator: separator.to_string(),
+            })
+        };
+        let timestamp = |format: &str, separator: &str| {
+            SuffixConfig::Timestamp(TimestampConfig {
+                format: format.to_string(),
+                file_limit: FileLimit::MaxFiles(3),
+                date_from: DateFrom::Now,
+                separator: separator.to_string(),
+            })
+        };
+        let error = |config: RotationConfig| config.build().err().map(|err| err.to_string());
+
+        assert_eq!(None, error(config(count("."), ContentLimit::Lines(1))));
+        for limit in [
+            ContentLimit::Bytes(0),
+            ContentLimit::Lines(0),
+            ContentLimit::BytesSurpassed(0),
+            ContentLimit::Writes(0),
+        ] {
+            assert_eq!(
+                Some("content_limit: the limit can't be zero".to_string()),
+                error(config(count("."), limit))
+            );
+        }
+        assert_eq!(
+            Some("suffix.separator: can't be empty".to_string()),
+            error(config(count(""), ContentLimit::Lines(1)))
+        );
+        assert_eq!(
+            Some(
+                "suffix.format: timestamp format \"%d-%m-%Y\" doesn't sort chronologically"
+                    .to_string()
+            ),
+            error(config(timestamp("%d-%m-%Y", "."), ContentLimit::Lines(1)))
+        );
+        assert_eq!(
+            Some("suffix.separator: the timestamp format renders it".to_string()),
+            error(config(timestamp("%Y-%m-%d", "-"), ContentLimit::Lines(1)))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn config_from_env() {
+        use config::*;
+        use std::collections::BTreeMap;
+
+        // The variables as `RotationConfig::from_env` passes them on, without the prefix
+        let from_env = |vars: &[(&str, &str)]| {
+            let vars = vars
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>();
+            RotationConfig::from_vars("FROM_ENV_TEST", vars)
+        };
+
+        assert_eq!(
+            RotationConfig {
+                path: "from_env_test.log".into(),
+                suffix: SuffixConfig::Count(CountConfig {
+                    max_files: 10,
+                    separator: ".".to_string(),
+                }),
+                content_limit: ContentLimit::Bytes(10_000_000),
+            },
+            from_env(&[]).unwrap()
+        );
+        let config = from_env(&[
+            ("PATH", "logs/app.log"),
+            ("ROTATE", "1000 lines"),
+            ("KEEP", "3"),
+            ("SCHEME", "sequence"),
+        ])
+        .unwrap();
+        assert_eq!(PathBuf::from("logs/app.log"), config.path);
+        assert_eq!(ContentLimit::Lines(1000), config.content_limit);
+        assert_eq!(
+            SuffixConfig::Sequence(CountConfig {
+                max_files: 3,
+                separator: ".".to_string(),
+            }),
+            config.suffix
+        );
+        let config = from_env(&[("MAX_BYTES", "1 MiB"), ("KEEP", "7d")]).unwrap();
+        assert_eq!(ContentLimit::Bytes(1 << 20), config.content_limit);
+        assert!(matches!(
+            config.suffix,
+            SuffixConfig::Timestamp(TimestampConfig {
+                file_limit: FileLimit::Age(age),
+                ..
+            }) if age == std::time::Duration::from_secs(7 * 24 * 3600)
+        ));
+
+        let error = |vars: &[(&str, &str)]| from_env(vars).unwrap_err().setting().to_string();
+        assert_eq!("FROM_ENV_TEST_LOG_LEVEL", error(&[("LEVEL", "debug")]));
+        assert_eq!("FROM_ENV_TEST_LOG_ROTATE", error(&[("ROTATE", "often")]));
+        assert_eq!("FROM_ENV_TEST_LOG_ROTATE", error(&[("ROTATE", "0 lines")]));
+        assert_eq!(
+            "FROM_ENV_TEST_LOG_MAX_BYTES",
+            error(&[("ROTATE", "1MB"), ("MAX_BYTES", "1MB")])
+        );
+        assert_eq!(
+            "FROM_ENV_TEST_LOG_KEEP",
+            error(&[("KEEP", "7d"), ("SCHEME", "count")])
+        );
+        assert_eq!("FROM_ENV_TEST_LOG_SCHEME", error(&[("SCHEME", "random")]));
+        assert_eq!("FROM_ENV_TEST_LOG_COMPRESS", error(&[("COMPRESS", "gzip")]));
+
+        // Without variables of the prefix, the defaults are used
+        assert_eq!(
+            from_env(&[]).unwrap(),
+            RotationConfig::from_env("FROM_ENV_TEST").unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn parse_limits() {
+        fn kind<T: std::fmt::Debug>(result: Result<T, ParseError>) -> ParseErrorKind {
+            result.unwrap_err().kind().clone()
+        }
+
+        assert_eq!(
+            ContentLimit::Bytes(500_000_000),
+            ContentLimit::bytes_from_str("500 MB").unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Bytes(100 << 20),
+            "100MiB".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Bytes(2_000),
+            "2kb".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Bytes(1536),
+            "1.5 KiB".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Bytes(4096),
+            "4096".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Lines(10_000),
+            "10000 lines".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Lines(1),
+            "1 Line".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ContentLimit::Writes(100_000),
+            "100000 writes".parse::<ContentLimit>().unwrap()
+        );
+        assert_eq!(
+            ParseErrorKind::UnknownUnit("parsecs".to_string()),
+            kind("12 parsecs".parse::<ContentLimit>())
+        );
+        assert_eq!(
+            ParseErrorKind::InvalidNumber,
+            kind("MB".parse::<ContentLimit>())
+        );
+        assert_eq!(
+            ParseErrorKind::InvalidNumber,
+            kind("1.5 lines".parse::<ContentLimit>())
+        );
+        assert_eq!(
+            ParseErrorKind::Unsupported,
+            kind("daily".parse::<ContentLimit>())
+        );
+        assert_eq!(
+            "unknown unit \"parsecs\" in \"12 parsecs\"",
+            "12 parsecs"
+                .parse::<ContentLimit>()
+                .unwrap_err()
+                .to_string()
+        );
+
+        assert_eq!(
+            FileLimit::Age(std::time::Duration::from_secs(30 * 24 * 3600)),
+            FileLimit::age_from_str("30d").unwrap()
+        );
+        assert_eq!(
+            FileLimit::Age(std::time::Duration::from_secs(12 * 3600)),
+            FileLimit::age_from_str("12 H").unwrap()
+        );
+        assert_eq!(
+            ParseErrorKind::UnknownUnit("y".to_string()),
+            kind(FileLimit::age_from_str("1y"))
+        );
+        assert_eq!(
+            ParseErrorKind::InvalidNumber,
+            kind(FileLimit::age_from_str("d"))
+        );
+    }
+
+    #[test]
+    fn close() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(2))
+                .writer_factory(|path| File::create(path).map(io::BufWriter::new))
+                .build();
+        write!(log, "a\nb\nc").unwrap();
+        let report = log.close().unwrap();
+        assert_eq!(dir.join("log"), report.path);
+        assert_eq!(1, report.bytes);
+        assert_eq!("c", fs::read_to_string(dir.join("log")).unwrap());
+
+        // Dropping flushes too
+        let mut log = FileRotate::builder(
+            dir.join("drop"),
+            CountSuffix::new(3),
+            ContentLimit::Lines(2),
+        )
+        .writer_factory(|path| File::create(path).map(io::BufWriter::new))
+        .build();
+        write!(log, "a").unwrap();
+        drop(log);
+        assert_eq!("a", fs::read_to_string(dir.join("drop")).unwrap());
+
+        // The log file could not be reopened after a rotation
+        let opened = std::sync::atomic::AtomicUsize::new(0);
+        let mut log = FileRotate::builder(
+            dir.join("reopen"),
+            CountSuffix::new(3),
+            ContentLimit::Lines(1),
+        )
+        .writer_factory(move |path| {
+            if opened.fetch_add(1, Ordering::SeqCst) == 0 {
+                File::create(path)
+            } else {
+                Err(io::ErrorKind::PermissionDenied.into())
+            }
+        })
+        .build();
+        // The line made it to disk before reopening failed, so only the next write fails
+        writeln!(log, "a").unwrap();
+        let err = writeln!(log, "b").unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, err.kind());
+        assert!(err.to_string().contains("failed to open"));
+        assert!(err.to_string().contains("reopen"));
+        let report = log.close().unwrap();
+        assert_eq!(0, report.bytes);
+        assert_eq!("a\n", fs::read_to_string(dir.join("reopen.1")).unwrap());
+
+        // The final flush fails
+        struct FailingFlush;
+        impl Write for FailingFlush {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::ErrorKind::Other.into())
+            }
+        }
+        let mut log = FileRotate::builder(
+            dir.join("fail"),
+            CountSuffix::new(3),
+            ContentLimit::Lines(2),
+        )
+        .writer_factory(|_| Ok(FailingFlush))
+        .build();
+        write!(log, "a").unwrap();
+        match log.close() {
+            Err(Error::Flush { path, source }) => {
+                assert_eq!(dir.join("fail"), path);
+                assert_eq!(io::ErrorKind::Other, source.kind());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn subscribe() {
+        use events::RotationEvent;
+        use fs_ops::{FaultyFs, FsOp};
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let faulty = FaultyFs::new();
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(1), ContentLimit::Lines(1))
+                .fs_ops(faulty.clone())
+                .build();
+        let events = log.subscribe();
+        // Dropping a receiver doesn't bother the writer
+        drop(log.subscribe());
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
+        faulty.fail(FsOp::Rename, 1, io::ErrorKind::PermissionDenied);
+        writeln!(log, "c").unwrap();
+        let rotated = RotationEvent::Rotated {
+            path: dir.join("log.1"),
+            size: 2,
+            suffix: Some("1".to_string()),
+        };
+        assert_eq!(Ok(rotated.clone()), events.try_recv());
+        assert_eq!(
+            Ok(RotationEvent::Deleted {
+                path: dir.join("log.1"),
+                reason: "numbered 2 or higher".to_string(),
+            }),
+            events.try_recv()
+        );
+        assert_eq!(Ok(rotated), events.try_recv());
+        match events.try_recv() {
+            Ok(RotationEvent::Deleted { .. }) => {}
+            event => panic!("{:?}", event),
+        }
+        match events.try_recv() {
+            Ok(RotationEvent::Error { kind, .. }) => {
+                assert_eq!(io::ErrorKind::PermissionDenied, kind)
+            }
+            event => panic!("{:?}", event),
+        }
+        assert_eq!(Err(std::sync::mpsc::TryRecvError::Empty), events.try_recv());
+        drop(log);
+        assert_eq!(
+            Err(std::sync::mpsc::TryRecvError::Disconnected),
+            events.try_recv()
+        );
+        assert_eq!(0, events.dropped());
+    }
+
+    #[test]
+    fn snapshot() {
+        use fs_ops::{FaultyFs, FsOp};
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let faulty = FaultyFs::new();
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(2), ContentLimit::Lines(1))
+                .fs_ops(faulty.clone())
+                .build();
+        writeln!(log, "a").unwrap();
+        // The first rename is the suffix scheme's, of `log.1` to `log.2`
+        faulty.fail(FsOp::Rename, 2, io::ErrorKind::PermissionDenied);
+        writeln!(log, "b").unwrap();
+        assert!(log.take_last_error().is_some());
+
+        let snapshot = log.snapshot();
+        assert_eq!("Lines(1)", snapshot.content_limit);
+        assert_eq!(dir.join("log"), snapshot.current_path);
+        assert_eq!((2, 1), (snapshot.current_bytes, snapshot.count));
+        assert!(snapshot.last_rotation.is_some());
+        assert_eq!(
+            // Moved aside before the rename failed
+            vec![SnapshotFile {
+                path: dir.join("log.2"),
+                suffix: Some("2".to_string()),
+                size: Some(2),
+            }],
+            snapshot.rotated
+        );
+        assert_eq!(Some(dir.join("log.1")), snapshot.pending_rotation);
+        assert_eq!(1, snapshot.recent_errors.len());
+        assert!(snapshot.recent_errors[0]
+            .message
+            .contains("Rename number 3 failed"));
+        let text = snapshot.to_string();
+        assert!(text.contains("pending rotation: to "), "{}", text);
+        assert!(text.contains("recent errors: 1\n"), "{}", text);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn boxed_suffix_schemes() {
+        use chrono::TimeZone;
+        /// Rotate once a second, and list the files left and what the scheme knows of them
+        fn run<S: SuffixScheme>(dir: &Path, scheme: S, clock: &clock::ManualClock) -> Vec<String> {
+            let mut log = FileRotate::new(dir.join("log"), scheme, ContentLimit::Lines(1));
+            for line in 0..6 {
+                writeln!(log, "{}", line).unwrap();
+                clock.advance(chrono::Duration::seconds(1));
+            }
+            let mut files = fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            files.sort();
+            files.extend(log.log_paths().into_iter().map(|path| {
+                let file_name = path.file_name().unwrap().to_str().unwrap();
+                format!("{}: {}", file_name, fs::read_to_string(&path).unwrap())
+            }));
+            files
+        }
+        let start = chrono::Local.timestamp_opt(1_000_000_000, 0).unwrap();
+        for idx in 0..4 {
+            let boxed_dir = TempDir::new("file-rotate-test").unwrap();
+            let boxed_clock = clock::ManualClock::new(start);
+            let scheme: Box<dyn SuffixScheme + Send> = match idx {
+                0 => Box::new(CountSuffix::new(3)),
+                1 => Box::new(SequenceSuffix::new(3)),
+                2 => Box::new(
+                    TimestampSuffix::default(FileLimit::MaxFiles(3))
+                        .with_clock(boxed_clock.clone()),
+                ),
+                _ => Box::new(
+                    EpochSuffix::new(FileLimit::Age(std::time::Duration::from_secs(3)))
+                        .with_clock(boxed_clock.clone()),
+                ),
+            };
+            let boxed = run(boxed_dir.path(), scheme, &boxed_clock);
+
+            let dir = TempDir::new("file-rotate-test").unwrap();
+            let clock = clock::ManualClock::new(start);
+            let files = match idx {
+                0 => run(dir.path(), CountSuffix::new(3), &clock),
+                1 => run(dir.path(), SequenceSuffix::new(3), &clock),
+                2 => run(
+                    dir.path(),
+                    TimestampSuffix::default(FileLimit::MaxFiles(3)).with_clock(clock.clone()),
+                    &clock,
+                ),
+                _ => run(
+                    dir.path(),
+                    EpochSuffix::new(FileLimit::Age(std::time::Duration::from_secs(3)))
+                        .with_clock(clock.clone()),
+                    &clock,
+                ),
+            };
+            assert_eq!(files, boxed);
+            assert!(files.len() > 4, "{:?}", files);
+        }
+    }
+
+    #[test]
+    fn into_parts() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        use fs_ops::{FaultyFs, FsOp};
+        let faulty = FaultyFs::new();
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(1))
+                .fs_ops(faulty.clone())
+                .build();
+        faulty.fail(FsOp::Rename, 1, io::ErrorKind::PermissionDenied);
+        writeln!(log, "a").unwrap();
+        assert!(log.take_last_error().is_some());
+        // The failed rotation is retried before handing over
+        let (mut file, report) = log.into_parts().unwrap();
+        assert_eq!(dir.join("log"), report.path);
+        assert_eq!(0, report.bytes);
+        writeln!(file, "bye").unwrap();
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("bye\n", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_clock_going_backwards() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        // A file from "the future", as if the clock was set back after it was rotated
+        let future = (chrono::Local::now() + chrono::Duration::hours(1))
+            .format("%Y%m%dT%H%M%S")
+            .to_string();
+        File::create(dir.join(format!("log.{}", future))).unwrap();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::MaxFiles(2)),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            vec![
+                dir.join(format!("log.{}", future)),
+                dir.join(format!("log.{}.1", future)),
+            ],
+            log.log_paths()
+        );
+
+        // The newest file survives, the oldest is deleted
+        write!(log, "b\nc\nd\ne\nf\ng\nh\ni\nj\nk\n").unwrap();
+        assert_eq!(
+            vec![
+                dir.join(format!("log.{}.10", future)),
+                dir.join(format!("log.{}.11", future)),
+            ],
+            log.log_paths()
+        );
+        assert_eq!(
+            "k\n",
+            fs::read_to_string(dir.join(format!("log.{}.11", future))).unwrap()
+        );
+        assert!(!dir.join(format!("log.{}", future)).exists());
+
+        // The order also holds when scanning the directory anew
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::MaxFiles(2)),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(
+            vec![
+                dir.join(format!("log.{}.10", future)),
+                dir.join(format!("log.{}.11", future)),
+            ],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn epoch_clock_going_backwards() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let future = chrono::Local::now().timestamp() + 3600;
+        File::create(dir.join(format!("log.{}", future))).unwrap();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            EpochSuffix::new(FileLimit::MaxFiles(2)),
+            ContentLimit::Lines(1),
+        );
+        write!(log, "a\nb\n").unwrap();
+        assert_eq!(
+            vec![
+                dir.join(format!("log.{}.1", future)),
+                dir.join(format!("log.{}.2", future)),
+            ],
+            log.log_paths()
+        );
+        assert_eq!(
+            "b\n",
+            fs::read_to_string(dir.join(format!("log.{}.2", future))).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn rotation_scans_only_what_it_needs() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        // With unlimited files, only the newest timestamp matters for the next suffix
+        let future = chrono::Local::now().timestamp() + 3600;
+        for name in [future - 10, future - 5] {
+            File::create(dir.join(format!("log.{}", name))).unwrap();
+        }
+        File::create(dir.join(format!("log.{}", future))).unwrap();
+        File::create(dir.join(format!("log.{}.1", future))).unwrap();
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            EpochSuffix::new(FileLimit::Unlimited),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            "a\n",
+            fs::read_to_string(dir.join(format!("log.{}.2", future))).unwrap()
+        );
+        // Listing the files still finds all of them
+        assert_eq!(5, log.log_paths().len());
+
+        // With a maximum, the older files are deleted while scanning
+        let mut log = FileRotate::new(
+            dir.join("seq"),
+            SequenceSuffix::new(3),
+            ContentLimit::Lines(1),
+        );
+        for n in 1..=10 {
+            File::create(dir.join(format!("seq.{}", n))).unwrap();
+        }
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            vec![dir.join("seq.9"), dir.join("seq.10"), dir.join("seq.11")],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    fn defer_deletions() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        for n in 1..=5 {
+            File::create(dir.join(format!("log.{}", n))).unwrap();
+        }
+
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            SequenceSuffix::new(2),
+            ContentLimit::Lines(1),
+        )
+        .defer_deletions(true)
+        .build();
+        let events = log.subscribe();
+        // The rotation leaves four files, and the write deletes the oldest
+        writeln!(log, "a").unwrap();
+        assert_eq!(vec![dir.join("log.5"), dir.join("log.6")], log.log_paths());
+        let old = (1..=4)
+            .map(|n| dir.join(format!("log.{}", n)))
+            .collect::<Vec<_>>();
+        let left = || old.iter().filter(|path| path.exists()).count();
+        assert_eq!(3, left());
+        let pending = log.snapshot().pending_deletion;
+        assert_eq!(3, pending.len());
+        assert!(pending.iter().all(|path| path.exists()));
+
+        // One per write
+        log.write_all(b"b").unwrap();
+        assert_eq!(2, left());
+
+        log.sync().unwrap();
+        assert_eq!(0, left());
+        assert!(log.snapshot().pending_deletion.is_empty());
+        let mut deleted = events
+            .try_iter()
+            .filter_map(|event| match event {
+                RotationEvent::Deleted { path, reason } => Some((path, reason)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        deleted.sort();
+        let reason = "more than 2 files".to_string();
+        assert_eq!(
+            old.into_iter()
+                .map(|path| (path, reason.clone()))
+                .collect::<Vec<_>>(),
+            deleted
+        );
+    }
+
+    #[test]
+    fn min_rotation_interval() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            CountSuffix::new(10),
+            ContentLimit::Bytes(1),
+        )
+        .min_rotation_interval(std::time::Duration::from_secs(3600))
+        .build();
+        for _ in 0..100 {
+            log.write_all(b"x").unwrap();
+        }
+        // The second write rotates, and the log file takes the rest
+        assert_eq!(vec![dir.join("log.1")], log.log_paths());
+        assert_eq!(99, fs::metadata(dir.join("log")).unwrap().len());
+        assert_eq!(98, log.deferred_rotations());
+
+        // Requested rotations happen regardless
+        log.control_handle().request_rotate();
+        log.write_all(b"y").unwrap();
+        assert_eq!(2, log.log_paths().len());
+        assert_eq!("y", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn carry_over_records() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let starts_record = |line: &[u8]| !line.starts_with(b" ");
+
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(3))
+                .carry_over_records(starts_record)
+                .build();
+        write!(log, "rec1\n  a\nrec2\n").unwrap();
+        write!(log, "  b\n  c\nrec3\n").unwrap();
+        writeln!(log, "rec4").unwrap();
+        assert_eq!(
+            "rec1\n  a\nrec2\n",
+            fs::read_to_string(dir.join("log.2")).unwrap()
+        );
+        // The carried line doesn't count toward the limit
+        assert_eq!(
+            "rec2\n  b\n  c\nrec3\n",
+            fs::read_to_string(dir.join("log.1")).unwrap()
+        );
+        assert_eq!("rec4\n", fs::read_to_string(dir.join("log")).unwrap());
+
+        // A rotation within a line carries the start of the line
+        let mut log = FileRotate::builder(
+            dir.join("split"),
+            CountSuffix::new(3),
+            ContentLimit::Bytes(8),
+        )
+        .carry_over_records(starts_record)
+        .build();
+        writeln!(log, "r1 0123456789").unwrap();
+        assert_eq!("r1 01234", fs::read_to_string(dir.join("split.1")).unwrap());
+        assert_eq!(
+            "r1 0123456789\n",
+            fs::read_to_string(dir.join("split")).unwrap()
+        );
+    }
+
+    #[test]
+    fn rotation_markers() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(2))
+                .rotation_markers(true)
+                .build();
+        write!(log, "a\nb\n").unwrap();
+        let end = format!("---- log continues in {} ----\n", dir.join("log").display());
+        assert_eq!(
+            format!("a\nb\n{}", end),
+            fs::read_to_string(dir.join("log.1")).unwrap()
+        );
+        // The markers don't count toward the limit
+        write!(log, "c\nd\n").unwrap();
+        let rotated = fs::read_to_string(dir.join("log.1")).unwrap();
+        let start = format!(
+            "---- continued from {} (rotated ",
+            dir.join("log.1").display()
+        );
+        assert!(rotated.starts_with(&start), "{}", rotated);
+        assert!(
+            rotated.ends_with(&format!(" ----\nc\nd\n{}", end)),
+            "{}",
+            rotated
+        );
+        assert_eq!(0, fs::metadata(dir.join("log")).unwrap().len());
+
+        // Nothing is marked when nothing is kept
+        let mut log = FileRotate::builder(
+            dir.join("discard"),
+            CountSuffix::new(0),
+            ContentLimit::Lines(1),
+        )
+        .rotation_markers(true)
+        .build();
+        write!(log, "a\nb").unwrap();
+        assert_eq!("b", fs::read_to_string(dir.join("discard")).unwrap());
+    }
+
+    #[test]
+    fn file_rotate_cell() {
+        use cell::FileRotateCell;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let log = FileRotateCell::new(FileRotate::new(
+            &log_path,
+            CountSuffix::new(3),
+            ContentLimit::Lines(2),
+        ));
+        let (mut a, mut b) = (&log, &log);
+        writeln!(a, "a").unwrap();
+        writeln!(b, "b").unwrap();
+        writeln!(a, "c").unwrap();
+        assert_eq!(
+            "a\nb\n",
+            fs::read_to_string(tmp_dir.path().join("log.1")).unwrap()
+        );
+
+        // A re-entrant write fails instead of panicking
+        let guard = log.borrow_mut().unwrap();
+        let err = writeln!(b, "d").unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+        drop(guard);
+
+        let mut log = log.into_inner();
+        log.flush().unwrap();
+        assert_eq!("c\n", fs::read_to_string(&log_path).unwrap());
+    }
+
+    #[test]
+    fn rotation_status() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(3), ContentLimit::Bytes(10));
+        assert_eq!(None, log.last_rotation());
+        assert_eq!(Some(10), log.bytes_until_rotation());
+        assert_eq!(None, log.lines_until_rotation());
+        write!(log, "abc").unwrap();
+        assert_eq!(Some(7), log.bytes_until_rotation());
+
+        let before = SystemTime::now();
+        log.control_handle().request_rotate();
+        write!(log, "d").unwrap();
+        assert!(log.last_rotation().unwrap() >= before);
+        assert_eq!(Some(9), log.bytes_until_rotation());
+
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(3), ContentLimit::Lines(3));
+        writeln!(log, "a").unwrap();
+        assert_eq!(Some(2), log.lines_until_rotation());
+        assert_eq!(None, log.bytes_until_rotation());
+    }
+
+    #[test]
+    fn rotate_mechanics() {
+        let mechanics = vec![
+            RotateMechanics::CloseRenameReopen,
+            RotateMechanics::RenameKeepFdThenReopen,
+            RotateMechanics::CopyTruncate,
+        ];
+        for mechanics in mechanics {
+            let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+            let log_path = tmp_dir.path().join("log");
+
+            let mut log =
+                FileRotate::builder(&log_path, SequenceSuffix::new(100), ContentLimit::Bytes(7))
+                    .rotate_mechanics(mechanics)
+                    .build();
+            let mut expected = String::new();
+            for idx in 0..20 {
+                let line = format!("line {}\n", idx);
+                log.write_all(line.as_bytes()).unwrap();
+                expected += &line;
+            }
+
+            let mut written = String::new();
+            for path in log.log_paths() {
+                let content = fs::read_to_string(path).unwrap();
+                assert_eq!(7, content.len(), "{:?}: {:?}", mechanics, content);
+                written += &content;
+            }
+            written += &fs::read_to_string(&log_path).unwrap();
+            assert_eq!(expected, written, "{:?}", mechanics);
+        }
+    }
+
+    #[test]
+    fn deferred_errors() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            SequenceSuffix::new(10),
+            ContentLimit::Lines(1),
+        )
+        .on_collision(|_| CollisionAction::Overwrite)
+        .build();
+
+        // Renaming a file onto a non-empty directory fails
+        let blocker = dir.join("log.1");
+        fs::create_dir(&blocker).unwrap();
+        fs::write(blocker.join("x"), "").unwrap();
+
+        writeln!(log, "a").unwrap();
+        assert!(matches!(log.take_last_error(), Some(Error::Rotate { .. })));
+        assert!(log.take_last_error().is_none());
+        writeln!(log, "b").unwrap();
+        assert!(matches!(log.sync(), Err(Error::Rotate { .. })));
+
+        fs::remove_dir_all(&blocker).unwrap();
+        // The retry succeeds, but the failure of the last write is still reported
+        assert!(matches!(log.sync(), Err(Error::Rotate { .. })));
+        assert!(log.sync().is_ok());
+        assert_eq!("a\nb\n", fs::read_to_string(dir.join("log.1")).unwrap());
+    }
+
+    #[test]
+    fn error_hook() {
+        use std::sync::Mutex;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        let mut log =
+            FileRotate::builder(dir.join("log"), CountSuffix::new(1), ContentLimit::Lines(1))
+                .on_error(move |err| reported.lock().unwrap().push(err.to_string()))
+                .build();
+
+        // The oldest file can't be deleted, and so the log file can't take its place
+        fs::create_dir(dir.join("log.1")).unwrap();
+        fs::write(dir.join("log.1").join("x"), "").unwrap();
+        writeln!(log, "a").unwrap();
+        {
+            let errors = errors.lock().unwrap();
+            // Once more when the rotation cascades
+            assert_eq!(3, errors.len(), "{:?}", errors);
+            assert!(errors[0].starts_with("failed to delete"));
+            assert!(errors[1].starts_with("failed to delete"));
+            assert!(errors[2].starts_with("failed to move"));
+        }
+        assert!(matches!(log.take_last_error(), Some(Error::Rotate { .. })));
+
+        // Flushing when dropped
+        struct FailingFlush;
+        impl Write for FailingFlush {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::ErrorKind::Other.into())
+            }
+        }
+        errors.lock().unwrap().clear();
+        let reported = errors.clone();
+        let mut log = FileRotate::builder(
+            dir.join("fail"),
+            CountSuffix::new(1),
+            ContentLimit::Lines(2),
+        )
+        .writer_factory(|_| Ok(FailingFlush))
+        .on_error(move |err| reported.lock().unwrap().push(err.to_string()))
+        .build();
+        write!(log, "a").unwrap();
+        drop(log);
+        assert_eq!(1, errors.lock().unwrap().len());
+        assert!(errors.lock().unwrap()[0].starts_with("failed to flush"));
+    }
+
+    #[test]
+    #[cfg(feature = "log-internals")]
+    fn internal_events() {
+        use std::cell::RefCell;
+        thread_local! {
+            static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+            static ECHO: RefCell<Option<FileRotate<CountSuffix>>> = const { RefCell::new(None) };
+        }
+        struct Logger;
+        impl log::Log for Logger {
+            fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+                metadata.target() == "file_rotate"
+            }
+            fn log(&self, record: &log::Record<'_>) {
+                EVENTS.with(|events| events.borrow_mut().push(record.args().to_string()));
+                // Rotates with every line, which would log again
+                ECHO.with(|echo| {
+                    if let Some(ref mut echo) = *echo.borrow_mut() {
+                        writeln!(echo, "{}", record.args()).unwrap();
+                    }
+                });
+            }
+            fn flush(&self) {}
+        }
+        log::set_logger(&Logger).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let echo = FileRotate::new(
+            dir.join("echo"),
+            CountSuffix::new(1),
+            ContentLimit::Lines(1),
+        );
+        ECHO.with(|cell| *cell.borrow_mut() = Some(echo));
+        let mut log = FileRotate::new(dir.join("log"), CountSuffix::new(1), ContentLimit::Lines(1));
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
+
+        let events = EVENTS.with(|events| events.borrow_mut().split_off(0));
+        let log_1 = dir.join("log.1");
+        let rotated = format!(
+            "rotated {} to {}",
+            dir.join("log").display(),
+            log_1.display()
+        );
+        let deleted = format!("deleted {} (numbered 2 or higher)", log_1.display());
+        assert!(events.contains(&rotated), "{:?}", events);
+        assert!(events.contains(&deleted), "{:?}", events);
+        // Events of writing them to `echo` were dropped
+        assert!(
+            !events.iter().any(|event| event.contains("echo")),
+            "{:?}",
+            events
+        );
+        assert!(dir.join("echo.1").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn date_in_name() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let new_log = |open_behavior| {
+            FileRotate::builder(
+                dir.join("log"),
+                SequenceSuffix::new(3),
+                ContentLimit::Lines(2),
+            )
+            .rotate_mechanics(RotateMechanics::DateInName { symlink: true })
+            .open_behavior(open_behavior)
+            .build()
+        };
+
+        let mut log = new_log(OpenBehavior::Truncate);
+        assert_eq!(dir.join("log.1"), log.current_path());
+        write!(log, "a\nb\nc\nd\ne\nf\ng\n").unwrap();
+        assert!(!dir.join("log.1").exists());
+        assert_eq!("c\nd\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("e\nf\n", fs::read_to_string(dir.join("log.3")).unwrap());
+        assert_eq!(vec![dir.join("log.2"), dir.join("log.3")], log.log_paths());
+        assert_eq!(Path::new("log.4"), fs::read_link(dir.join("log")).unwrap());
+        assert_eq!("g\n", fs::read_to_string(dir.join("log")).unwrap());
+        drop(log);
+
+        // Continue in the newest file
+        let mut log = new_log(OpenBehavior::Append);
+        write!(log, "h\ni\n").unwrap();
+        assert_eq!("g\nh\n", fs::read_to_string(dir.join("log.4")).unwrap());
+        assert_eq!("i\n", fs::read_to_string(dir.join("log")).unwrap());
+        drop(log);
+
+        // Start a new file
+        let log = new_log(OpenBehavior::RotateExisting);
+        assert_eq!(dir.join("log.6"), log.current_path());
+        assert_eq!(Path::new("log.6"), fs::read_link(dir.join("log")).unwrap());
+        assert_eq!("i\n", fs::read_to_string(dir.join("log.5")).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn interval_suffix() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let start = chrono::Local
+            .with_ymd_and_hms(2024, 6, 1, 12, 0, 0)
+            .earliest()
+            .unwrap();
+        let clock = clock::ManualClock::new(start);
+        let new_log = |open_behavior| {
+            FileRotate::builder(
+                dir.join("log"),
+                IntervalSuffix::new(FileLimit::MaxFiles(2)).with_clock(clock.clone()),
+                ContentLimit::Lines(2),
+            )
+            .rotate_mechanics(RotateMechanics::DateInName { symlink: false })
+            .open_behavior(open_behavior)
+            .build()
+        };
+
+        let mut log = new_log(OpenBehavior::Truncate);
+        assert_eq!(dir.join("log.20240601T120000.part"), log.current_path());
+        writeln!(log, "a").unwrap();
+        clock.advance(chrono::Duration::hours(1));
+        writeln!(log, "b").unwrap();
+        let first = dir.join("log.20240601T120000-20240601T130000");
+        assert_eq!("a\nb\n", fs::read_to_string(&first).unwrap());
+        assert_eq!(dir.join("log.20240601T130000.part"), log.current_path());
+        writeln!(log, "c").unwrap();
+        drop(log);
+
+        // The `.part` file of the previous run ends when it was last modified
+        let modified = start + chrono::Duration::minutes(90);
+        File::options()
+            .append(true)
+            .open(dir.join("log.20240601T130000.part"))
+            .unwrap()
+            .set_modified(modified.into())
+            .unwrap();
+        clock.advance(chrono::Duration::hours(1));
+        let log = new_log(OpenBehavior::Truncate);
+        let second = dir.join("log.20240601T130000-20240601T133000");
+        assert_eq!("c\n", fs::read_to_string(&second).unwrap());
+        assert_eq!(dir.join("log.20240601T140000.part"), log.current_path());
+        drop(log);
+
+        // Only the `.part` file is continued, and it doesn't count against the limit
+        let mut log = new_log(OpenBehavior::Append);
+        assert_eq!(dir.join("log.20240601T140000.part"), log.current_path());
+        writeln!(log, "d").unwrap();
+        clock.advance(chrono::Duration::hours(1));
+        writeln!(log, "e").unwrap();
+        let third = dir.join("log.20240601T140000-20240601T150000");
+        assert!(!first.exists());
+        assert_eq!("d\ne\n", fs::read_to_string(&third).unwrap());
+        assert_eq!(vec![second, third], log.log_paths());
+        assert_eq!(dir.join("log.20240601T150000.part"), log.current_path());
+    }
+
+    #[test]
+    fn split_at_whole_writes() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            CountSuffix::new(5),
+            ContentLimit::Bytes(10),
+        )
+        .split_at(SplitPolicy::WholeWrites)
+        .build();
+
+        // Exactly up to the limit
+        log.write_all(b"aaaa").unwrap();
+        log.write_all(b"bbbbbb").unwrap();
+        assert_eq!("aaaabbbbbb", fs::read_to_string(dir.join("log")).unwrap());
+        assert!(!dir.join("log.1").exists());
+        log.write_all(b"cc").unwrap();
+        assert_eq!("aaaabbbbbb", fs::read_to_string(dir.join("log.1")).unwrap());
+        log.write_all(b"ddddddddd").unwrap();
+        assert_eq!("cc", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("ddddddddd", fs::read_to_string(dir.join("log")).unwrap());
+
+        // Larger than the limit, in a file of its own
+        log.write_all(b"eeeeeeeeeeeeeee").unwrap();
+        log.write_all(b"f").unwrap();
+        assert_eq!("ddddddddd", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!(
+            "eeeeeeeeeeeeeee",
+            fs::read_to_string(dir.join("log.1")).unwrap()
+        );
+        assert_eq!("f", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn tail() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let mut log = FileRotate::new(dir.join("log"), CountSuffix::new(3), ContentLimit::Lines(2));
+        assert!(log.tail(5, 100).unwrap().is_empty());
+        write!(log, "a\nb\nc\nd\ne\nf").unwrap();
+        assert_eq!(vec!["f"], log.tail(1, 100).unwrap());
+        assert_eq!(vec!["c", "d", "e", "f"], log.tail(4, 100).unwrap());
+        assert_eq!(
+            vec!["a", "b", "c", "d", "e", "f"],
+            log.tail(10, 100).unwrap()
+        );
+        assert!(log.tail(0, 100).unwrap().is_empty());
+
+        // Deleted files are skipped
+        fs::remove_file(dir.join("log.1")).unwrap();
+        assert_eq!(vec!["a", "b", "e", "f"], log.tail(10, 100).unwrap());
+
+        // Only the complete lines of what is read, and nothing older
+        fs::write(dir.join("log.1"), "long line\n").unwrap();
+        assert_eq!(vec!["e", "f"], log.tail(10, 5).unwrap());
+        fs::write(dir.join("log.1"), "long\nline\n").unwrap();
+        assert_eq!(vec!["line", "e", "f"], log.tail(10, 5).unwrap());
+    }
+
+    #[test]
+    fn sizes() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let mut log = FileRotate::new(dir.join("log"), CountSuffix::new(3), ContentLimit::Bytes(4));
+        assert_eq!(vec![(dir.join("log"), 0)], log.sizes().unwrap());
+
+        log.write_all(b"aaaabbbbcc").unwrap();
+        assert_eq!(
+            vec![
+                (dir.join("log"), 2),
+                (dir.join("log.1"), 4),
+                (dir.join("log.2"), 4)
+            ],
+            log.sizes().unwrap()
+        );
+        assert_eq!(10, log.total_size().unwrap());
+
+        // Deleted files are skipped
+        fs::remove_file(dir.join("log.1")).unwrap();
+        assert_eq!(6, log.total_size().unwrap());
+    }
+
+    #[test]
+    fn interrupted_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+
+        // Killed after moving the log file, before creating the new one
+        fs::write(dir.join("log.1"), "b\n").unwrap();
+        fs::write(dir.join("log.2"), "a\n").unwrap();
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(3), ContentLimit::Lines(1));
+        assert!(log_path.exists());
+        writeln!(log, "c").unwrap();
+        let contents = log
+            .log_paths()
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a\n", "b\n", "c\n"], contents);
+        drop(log);
+
+        // Killed in the middle of moving the rotated files up, leaving a gap at `log.2`
+        fs::remove_file(dir.join("log.3")).unwrap();
+        fs::rename(dir.join("log.2"), dir.join("log.3")).unwrap();
+        fs::write(&log_path, "d\n").unwrap();
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(3), ContentLimit::Lines(2))
+            .open_behavior(OpenBehavior::Append)
+            .build();
+        writeln!(log, "e").unwrap();
+        assert_eq!(
+            vec![dir.join("log.3"), dir.join("log.2"), dir.join("log.1")],
+            log.log_paths()
+        );
+        let contents = log
+            .log_paths()
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["b\n", "c\n", "d\ne\n"], contents);
+    }
+
+    #[test]
+    fn rotate_with_name() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(3), ContentLimit::Lines(2));
+        writeln!(log, "a").unwrap();
+        let snapshot = log.rotate_with_name("before-upgrade", false).unwrap();
+        assert_eq!(dir.join("log.before-upgrade"), snapshot);
+        assert_eq!("a\n", fs::read_to_string(&snapshot).unwrap());
+        writeln!(log, "b").unwrap();
+        writeln!(log, "c").unwrap();
+        assert_eq!("b\nc\n", fs::read_to_string(dir.join("log.1")).unwrap());
+
+        // Not one of the rotated files
+        assert_eq!(vec![dir.join("log.1")], log.log_paths());
+        let report = scan_report(&log_path, &mut CountSuffix::new(3)).unwrap();
+        assert_eq!(vec![snapshot.clone()], report.unrecognized);
+
+        let kind = |result: io::Result<PathBuf>| result.unwrap_err().kind();
+        assert_eq!(
+            io::ErrorKind::AlreadyExists,
+            kind(log.rotate_with_name("before-upgrade", false))
+        );
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            kind(log.rotate_with_name("../elsewhere", false))
+        );
+        assert_eq!("a\n", fs::read_to_string(&snapshot).unwrap());
+        writeln!(log, "d").unwrap();
+        log.rotate_with_name("before-upgrade", true).unwrap();
+        assert_eq!("d\n", fs::read_to_string(&snapshot).unwrap());
+        assert_eq!("", fs::read_to_string(&log_path).unwrap());
+    }
+
+    #[test]
+    fn open_options() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+        let mut options = LogOpenOptions::new();
+        options.read(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(3), ContentLimit::Lines(1))
+            .open_options(options)
+            .build();
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("log.1")).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&log_path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        // Still appended to
+        let log_path = dir.join("existing");
+        fs::write(&log_path, "a\n").unwrap();
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(3), ContentLimit::Lines(3))
+            .open_behavior(OpenBehavior::Append)
+            .open_options(LogOpenOptions::new())
+            .build();
+        writeln!(log, "b").unwrap();
+        assert_eq!("a\nb\n", fs::read_to_string(&log_path).unwrap());
+    }
+
+    #[test]
+    fn few_max_files() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        for max_files in 0..=2 {
+            let log_path = dir.join(format!("count{}", max_files));
+            let mut log = FileRotate::new(
+                &log_path,
+                CountSuffix::new(max_files),
+                ContentLimit::Lines(1),
+            );
+            let sequence_path = dir.join(format!("sequence{}", max_files));
+            let mut sequence = FileRotate::new(
+                &sequence_path,
+                SequenceSuffix::new(max_files),
+                ContentLimit::Lines(1),
+            );
+            for line in ["a", "b", "c", "d"] {
+                writeln!(log, "{}", line).unwrap();
+                writeln!(sequence, "{}", line).unwrap();
+            }
+            write!(log, "e").unwrap();
+            write!(sequence, "e").unwrap();
+            let expected = &["c\n", "d\n"][2 - max_files..];
+            for (log_paths, current) in [
+                (log.log_paths(), &log_path),
+                (sequence.log_paths(), &sequence_path),
+            ] {
+                let contents = log_paths
+                    .iter()
+                    .map(|path| fs::read_to_string(path).unwrap())
+                    .collect::<Vec<_>>();
+                assert_eq!(expected, &contents[..], "{}", max_files);
+                assert_eq!("e", fs::read_to_string(current).unwrap());
+            }
+        }
+        assert_eq!(
+            vec![dir.join("count1.1")],
+            FileRotate::new(
+                dir.join("count1"),
+                CountSuffix::new(1),
+                ContentLimit::Lines(1)
+            )
+            .log_paths()
+        );
+        assert!(!dir.join("count0.1").exists());
+    }
+
+    #[test]
+    fn faulty_fs() {
+        use fs_ops::{FaultyFs, FsOp};
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+        let fs = FaultyFs::new();
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(3), ContentLimit::Lines(1))
+            .fs_ops(fs.clone())
+            .build();
+        assert_eq!(1, fs.calls(FsOp::Open));
+
+        // The rotation is retried at the next write
+        fs.fail(FsOp::Rename, 1, io::ErrorKind::PermissionDenied);
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            io::ErrorKind::PermissionDenied,
+            log.take_last_error().unwrap().kind()
+        );
+        assert_eq!("a\n", fs::read_to_string(&log_path).unwrap());
+        // The counters still describe the file that wasn't moved
+        let snapshot = log.snapshot();
+        assert_eq!(1, snapshot.count);
+        assert_eq!(2, snapshot.current_bytes);
+        assert_eq!(Some(dir.join("log.1")), snapshot.pending_rotation);
+        writeln!(log, "b").unwrap();
+        // Including the suffix scheme's of `log.1` to `log.2`
+        assert_eq!(4, fs.calls(FsOp::Rename));
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("b\n", fs::read_to_string(dir.join("log.1")).unwrap());
+
+        // Opening the new log file fails after the rotation, so it's opened at the next write
+        fs.fail(FsOp::Open, 1, io::ErrorKind::Other);
+        writeln!(log, "c").unwrap();
+        assert!(!log_path.exists());
+        writeln!(log, "d").unwrap();
+        assert_eq!("c\n", fs::read_to_string(dir.join("log.2")).unwrap());
+        assert_eq!("d\n", fs::read_to_string(dir.join("log.1")).unwrap());
+    }
+
+    #[test]
+    fn faulty_fs_directories() {
+        use fs_ops::{FaultyFs, FsOp};
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let archive = dir.join("archive");
+        let fs = FaultyFs::new();
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            SequenceSuffix::new(10),
+            ContentLimit::Lines(1),
+        )
+        .archive_dir(&archive)
+        .fs_ops(fs.clone())
+        .build();
+
+        fs.fail(FsOp::CreateDirAll, 1, io::ErrorKind::PermissionDenied);
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            io::ErrorKind::PermissionDenied,
+            log.take_last_error().unwrap().kind()
+        );
+        assert!(!archive.exists());
+        writeln!(log, "b").unwrap();
+        assert_eq!("a\n", fs::read_to_string(archive.join("log.1")).unwrap());
+
+        fs.fail(FsOp::ReadDir, 1, io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            io::ErrorKind::PermissionDenied,
+            log.scan_report().unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn faulty_fs_in_suffix_scheme() {
+        use fs_ops::{FaultyFs, FsOp};
+        use std::sync::Mutex;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let fs = FaultyFs::new();
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let mut log = {
+            let reported = reported.clone();
+            FileRotate::builder(dir.join("log"), CountSuffix::new(1), ContentLimit::Lines(1))
+                .fs_ops(fs.clone())
+                .on_error(move |err| reported.lock().unwrap().push(err.to_string()))
+                .build()
+        };
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
+        assert!(fs.calls(FsOp::ReadDir) > 0);
+
+        // The suffix scheme deletes `log.1` to make room
+        fs.fail(FsOp::Remove, 1, io::ErrorKind::PermissionDenied);
+        writeln!(log, "c").unwrap();
+        let reported = reported.lock().unwrap();
+        assert_eq!(1, reported.len(), "{:?}", reported);
+        let expected = format!("failed to delete {}", dir.join("log.1").display());
+        assert!(reported[0].starts_with(&expected), "{:?}", reported);
+    }
+
+    #[test]
+    fn on_collision() {
+        use std::sync::Mutex;
+        /// Always rotates to the same name
+        struct Fixed;
+        impl SuffixScheme for Fixed {
+            fn rotate(&mut self, _basepath: &Path) -> String {
+                "old".to_string()
+            }
+            fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
+                vec![self.rotated_path(basepath, "old")]
+            }
+        }
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let renamed = dir.join("log.renamed");
+        let actions = Arc::new(Mutex::new(VecDeque::from(vec![
+            CollisionAction::Abort,
+            CollisionAction::RenameTo(renamed.clone()),
+            CollisionAction::Overwrite,
+            CollisionAction::Cascade,
+        ])));
+        let collisions = Arc::new(Mutex::new(Vec::new()));
+        let mut log = FileRotate::builder(dir.join("log"), Fixed, ContentLimit::Lines(1))
+            .on_collision({
+                let collisions = collisions.clone();
+                move |path| {
+                    collisions.lock().unwrap().push(path.to_path_buf());
+                    actions.lock().unwrap().pop_front().unwrap()
+                }
+            })
+            .build();
+        writeln!(log, "a").unwrap();
+        assert!(collisions.lock().unwrap().is_empty());
+
+        // Aborted, and retried at the next write
+        writeln!(log, "b").unwrap();
+        assert_eq!(
+            io::ErrorKind::AlreadyExists,
+            log.take_last_error().unwrap().kind()
+        );
+        assert_eq!("b\n", fs::read_to_string(dir.join("log")).unwrap());
+        assert_eq!("a\n", fs::read_to_string(dir.join("log.old")).unwrap());
+
+        writeln!(log, "c").unwrap();
+        assert_eq!("b\n", fs::read_to_string(&renamed).unwrap());
+        assert_eq!("c\n", fs::read_to_string(dir.join("log.old")).unwrap());
+        assert_eq!(vec![dir.join("log.old"); 3], *collisions.lock().unwrap());
+
+        // The scheme can't make room
+        writeln!(log, "d").unwrap();
+        assert_eq!(
+            io::ErrorKind::AlreadyExists,
+            log.take_last_error().unwrap().kind()
+        );
+        assert_eq!("c\n", fs::read_to_string(dir.join("log.old")).unwrap());
+
+        // Without a callback the rotation cascades, and fails rather than replace the file
+        drop(log);
+        let mut log = FileRotate::new(dir.join("log"), Fixed, ContentLimit::Lines(1));
+        writeln!(log, "e").unwrap();
+        assert_eq!(
+            io::ErrorKind::AlreadyExists,
+            log.take_last_error().unwrap().kind()
+        );
+        assert_eq!("c\n", fs::read_to_string(dir.join("log.old")).unwrap());
+    }
+
+    #[test]
+    fn max_total_bytes() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            CountSuffix::new(10),
+            ContentLimit::Bytes(10),
+        )
+        .max_total_bytes(25, QuotaPolicy::Fail)
+        .build();
+        log.write_all(b"aaaaaaaaaa").unwrap();
+        log.write_all(b"bbbbbbbbbb").unwrap();
+        // The oldest file is deleted first
+        log.write_all(b"cccccccccc").unwrap();
+        assert_eq!(vec![dir.join("log.1")], log.log_paths());
+        assert_eq!("bbbbbbbbbb", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!(20, log.total_size().unwrap());
+
+        // Even without any rotated files, it doesn't fit
+        let err = log.write_all(&[b'd'; 20]).unwrap_err();
+        let quota_exceeded = |err: &io::Error| {
+            matches!(
+                err.get_ref().and_then(|err| err.downcast_ref()),
+                Some(Error::QuotaExceeded { .. })
+            )
+        };
+        assert!(quota_exceeded(&err), "{:?}", err);
+        assert!(!dir.join("log.1").exists());
+        assert_eq!("cccccccccc", fs::read_to_string(dir.join("log")).unwrap());
+
+        let mut log = FileRotate::builder(
+            dir.join("other"),
+            CountSuffix::new(10),
+            ContentLimit::Bytes(100),
+        )
+        .max_total_bytes(25, QuotaPolicy::RotateEarly)
+        .build();
+        log.write_all(&[b'a'; 20]).unwrap();
+        log.write_all(&[b'b'; 10]).unwrap();
+        assert!(log.log_paths().is_empty());
+        assert_eq!("bbbbbbbbbb", fs::read_to_string(dir.join("other")).unwrap());
+        assert!(quota_exceeded(&log.write_all(&[b'c'; 30]).unwrap_err()));
+
+        // A file that can't be deleted is skipped for the next-oldest one
+        let faulty = fs_ops::FaultyFs::new();
+        let mut log = FileRotate::builder(
+            dir.join("undeletable"),
+            CountSuffix::new(10),
+            ContentLimit::Bytes(10),
+        )
+        .max_total_bytes(35, QuotaPolicy::Fail)
+        .fs_ops(faulty.clone())
+        .build();
+        log.write_all(b"aaaaaaaaaa").unwrap();
+        log.write_all(b"bbbbbbbbbb").unwrap();
+        log.write_all(b"cccccccccc").unwrap();
+        faulty.fail(fs_ops::FsOp::Remove, 1, io::ErrorKind::PermissionDenied);
+        log.write_all(b"dddddddddd").unwrap();
+        assert_eq!(
+            io::ErrorKind::PermissionDenied,
+            log.take_last_error().unwrap().kind()
+        );
+        assert_eq!(
+            "aaaaaaaaaa",
+            fs::read_to_string(dir.join("undeletable.2")).unwrap()
+        );
+        assert_eq!(
+            "cccccccccc",
+            fs::read_to_string(dir.join("undeletable.1")).unwrap()
+        );
+
+        // A failed write isn't counted
+        struct Failing;
+        impl Write for Failing {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::Other.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut log = FileRotate::builder(
+            dir.join("failing"),
+            CountSuffix::new(10),
+            ContentLimit::Bytes(10),
+        )
+        .split_at(SplitPolicy::Exact)
+        .writer_factory(|_| Ok(Failing))
+        .build();
+        log.write_all(b"aaaaaaaa").unwrap_err();
+        assert_eq!(0, log.snapshot().count);
+    }
+
+    #[test]
+    fn check_size() {
+        use std::sync::Mutex;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        let mut log = FileRotate::builder(&log_path, CountSuffix::new(3), ContentLimit::Bytes(10))
+            .check_size_every(2)
+            .on_error(move |err| reported.lock().unwrap().push(err.to_string()))
+            .build();
+
+        // Truncated, noticed before the rotation
+        log.write_all(b"aaaaaaaa").unwrap();
+        File::create(&log_path).unwrap();
+        log.write_all(b"bbbbb").unwrap();
+        assert_eq!("bbbbb", fs::read_to_string(&log_path).unwrap());
+        assert!(!dir.join("log.1").exists());
+        assert_eq!(1, errors.lock().unwrap().len());
+        assert!(errors.lock().unwrap()[0].contains("0 bytes instead of 8"));
+
+        // Appended to, noticed at the second write
+        OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .unwrap()
+            .write_all(b"ccc")
+            .unwrap();
+        log.write_all(b"d").unwrap();
+        log.write_all(b"e").unwrap();
+        assert_eq!(2, errors.lock().unwrap().len());
+        log.write_all(b"f").unwrap();
+        assert_eq!("bbbbbcccde", fs::read_to_string(dir.join("log.1")).unwrap());
+        assert_eq!("f", fs::read_to_string(&log_path).unwrap());
+        assert_eq!(2, errors.lock().unwrap().len());
+    }
+
+    #[test]
+    fn write_vectored() {
+        let record = |idx: usize| format!("[{}] record {}\n", idx, idx);
+        for content_limit in &[
+            ContentLimit::Bytes(30),
+            ContentLimit::Lines(2),
+            ContentLimit::Writes(2),
+        ] {
+            let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+            let log_path = tmp_dir.path().join("log");
+            let mut log =
+                FileRotate::new(&log_path, SequenceSuffix::new(100), content_limit.clone());
+            let mut expected = String::new();
+            for idx in 0..10 {
+                let (prefix, message) = (format!("[{}] ", idx), format!("record {}", idx));
+                let bufs = [
+                    IoSlice::new(prefix.as_bytes()),
+                    IoSlice::new(message.as_bytes()),
+                    IoSlice::new(b"\n"),
+                ];
+                let len = prefix.len() + message.len() + 1;
+                assert_eq!(len, log.write_vectored(&bufs).unwrap());
+                expected += &record(idx);
+            }
+
+            let mut written = String::new();
+            for path in log.log_paths() {
+                let content = fs::read_to_string(path).unwrap();
+                match content_limit {
+                    ContentLimit::Bytes(bytes) => assert_eq!(*bytes, content.len()),
+                    // Each record is one write
+                    _ => assert_eq!(2, content.lines().count()),
+                }
+                written += &content;
+            }
+            written += &fs::read_to_string(&log_path).unwrap();
+            assert_eq!(expected, written, "{:?}", content_limit);
+        }
+    }
+
+    #[test]
+    fn move_across_devices() {
+        use fs_ops::{FaultyFs, FsOp};
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let archive = dir.join("archive");
+        let fs = FaultyFs::new();
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            SequenceSuffix::new(10),
+            ContentLimit::Lines(1),
+        )
+        .archive_dir(&archive)
+        .fs_ops(fs.clone())
+        .build();
+        // Copied when the archive directory is on another file system
+        fs.fail_with(FsOp::Rename, 1, io::Error::from_raw_os_error(EXDEV));
+        writeln!(log, "a").unwrap();
+        assert!(log.take_last_error().is_none());
+        assert_eq!(1, fs.calls(FsOp::Copy));
+        assert_eq!("a\n", fs::read_to_string(archive.join("log.1")).unwrap());
+        assert_eq!("", fs::read_to_string(dir.join("log")).unwrap());
+
+        // Not copied for other failures
+        fs.fail(FsOp::Rename, 1, io::ErrorKind::PermissionDenied);
+        writeln!(log, "b").unwrap();
+        assert_eq!(
+            io::ErrorKind::PermissionDenied,
+            log.take_last_error().unwrap().kind()
+        );
+        assert_eq!(1, fs.calls(FsOp::Copy));
+        assert!(!archive.join("log.2").exists());
+    }
+
+    #[test]
+    fn uncreatable_directory() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let blocker = dir.join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let archive = blocker.join("archive");
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            SequenceSuffix::new(10),
+            ContentLimit::Lines(1),
+        )
+        .archive_dir(&archive)
+        .build();
+
+        // The rotation is kept pending, as when renaming fails
+        writeln!(log, "a").unwrap();
+        let err = log.take_last_error().unwrap();
+        assert!(matches!(err, Error::Rotate { .. }), "{:?}", err);
+        assert!(err.to_string().contains(&archive.display().to_string()));
+        assert_eq!(Some(archive.join("log.1")), log.snapshot().pending_rotation);
+        assert_eq!("a\n", fs::read_to_string(dir.join("log")).unwrap());
+
+        fs::remove_file(&blocker).unwrap();
+        writeln!(log, "b").unwrap();
+        assert!(log.take_last_error().is_none());
+        assert_eq!("a\n", fs::read_to_string(archive.join("log.1")).unwrap());
+
+        // Nor does writing panic if the log file's directory can't be created
+        let blocker = dir.join("file");
+        fs::write(&blocker, "").unwrap();
+        let mut log = FileRotate::new(
+            blocker.join("dir").join("log"),
+            SequenceSuffix::new(10),
+            ContentLimit::Lines(1),
+        );
+        let err = writeln!(log, "c").unwrap_err();
+        assert!(err.to_string().contains("failed to create"), "{}", err);
+    }
+
+    #[test]
+    fn copies_keep_modification_time() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        for (mechanics, archive_dir) in &[
+            (RotateMechanics::CopyTruncate, dir.to_path_buf()),
+            (RotateMechanics::CloseRenameReopen, dir.join("archive")),
+        ] {
+            let log_path = dir.join("log");
+            fs::write(&log_path, "a\n").unwrap();
+            File::options()
+                .write(true)
+                .open(&log_path)
+                .and_then(|file| file.set_modified(modified))
+                .unwrap();
+            let mut log =
+                FileRotate::builder(&log_path, SequenceSuffix::new(10), ContentLimit::Lines(1))
+                    .open_behavior(OpenBehavior::RotateExisting)
+                    .rotate_mechanics(*mechanics)
+                    .archive_dir(archive_dir)
+                    .build();
+            let rotated = log.log_paths().pop().unwrap();
+            assert_eq!(modified, fs::metadata(rotated).unwrap().modified().unwrap());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_daylight_saving_fall_back() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let clock = clock::ManualClock::new(
+            chrono::Local
+                .with_ymd_and_hms(2021, 10, 31, 2, 55, 0)
+                .earliest()
+                .unwrap(),
+        );
+        let new_log = || {
+            FileRotate::new(
+                dir.join("log"),
+                TimestampSuffix::default(FileLimit::MaxFiles(3)).with_clock(clock.clone()),
+                ContentLimit::Lines(1),
+            )
+        };
+        let mut log = new_log();
+        writeln!(log, "a").unwrap();
+        // The local time jumps back an hour at 03:00, like when daylight saving time ends
+        clock.advance(chrono::Duration::minutes(-45));
+        writeln!(log, "b").unwrap();
+        clock.advance(chrono::Duration::minutes(30));
+        writeln!(log, "c").unwrap();
+        clock.advance(chrono::Duration::minutes(30));
+        writeln!(log, "d").unwrap();
+
+        // The oldest file is deleted, not the newest ones
+        let expected = vec![
+            dir.join("log.20211031T025500.1"),
+            dir.join("log.20211031T025500.2"),
+            dir.join("log.20211031T031000"),
+        ];
+        assert_eq!(expected, log.log_paths());
+        assert_eq!(expected, new_log().log_paths());
+        let contents = expected
+            .iter()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["b\n", "c\n", "d\n"], contents);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn failed_rotation_keeps_data() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let clock = clock::ManualClock::new(
+            chrono::Local
+                .with_ymd_and_hms(2021, 1, 1, 12, 0, 0)
+                .unwrap(),
+        );
+        let mut log = FileRotate::builder(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::Unlimited).with_clock(clock),
+            ContentLimit::Lines(1),
+        )
+        .on_collision(|_| CollisionAction::Overwrite)
+        .build();
+        assert!(log.log_paths().is_empty());
+
+        // Renaming a file onto a non-empty directory fails
+        let blocker = dir.join("log.20210101T120000");
+        fs::create_dir(&blocker).unwrap();
+        fs::write(blocker.join("x"), "").unwrap();
+
+        write!(log, "a\nb\n").unwrap();
+        assert_eq!("a\nb\n", fs::read_to_string(dir.join("log")).unwrap());
+
+        fs::remove_dir_all(&blocker).unwrap();
+        write!(log, "c\nd").unwrap();
+        log.flush().unwrap();
+        assert_eq!("a\nb\n", fs::read_to_string(&blocker).unwrap());
+        assert_eq!(
+            "c\n",
+            fs::read_to_string(dir.join("log.20210101T120000.1")).unwrap()
+        );
+        assert_eq!("d", fs::read_to_string(dir.join("log")).unwrap());
+    }
+
+    #[test]
+    fn rotate_to_deleted_directory() {
+        // NOTE: Only supported with count, not with timestamp suffix.
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let parent = tmp_dir.path();
+        let log_path = parent.join("log");
+        let mut log = FileRotate::new(
             &*log_path.to_string_lossy(),
-            TimestampSuffix::default(FileLimit::Age(chrono::Duration::weeks(1))),
+            CountSuffix::new(4),
+            ContentLimit::Lines(1),
+        );
+
+        write!(log, "a\nb\n").unwrap();
+        assert_eq!("", fs::read_to_string(&log_path).unwrap());
+        assert_eq!("a\n", fs::read_to_string(&log.log_paths()[0]).unwrap());
+
+        let _ = fs::remove_dir_all(parent);
+
+        assert!(writeln!(log, "c").is_ok());
+
+        log.flush().unwrap();
+
+        writeln!(log, "d").unwrap();
+        assert_eq!("", fs::read_to_string(&log_path).unwrap());
+        assert_eq!("d\n", fs::read_to_string(&log.log_paths()[0]).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn write_complete_record_until_bytes_surpassed() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+
+        let mut log = FileRotate::new(
+            &log_path,
+            TimestampSuffix::default(FileLimit::MaxFiles(100)),
+            ContentLimit::BytesSurpassed(1),
+        );
+
+        write!(log, "0123456789").unwrap();
+        log.flush().unwrap();
+        assert!(log_path.exists());
+        // shouldn't exist yet - because entire record was written in one shot
+        assert!(log.log_paths().is_empty());
+
+        // This should create the second file
+        write!(log, "0123456789").unwrap();
+        log.flush().unwrap();
+        assert!(&log.log_paths()[0].exists());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    #[cfg(unix)]
+    fn non_utf8_filenames() {
+        use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("log");
+
+        File::create(dir.join("log.1")).unwrap();
+        File::create(dir.join("log.20200825T151133")).unwrap();
+        File::create(dir.join(OsString::from_vec(b"log.\xff\xfe".to_vec()))).unwrap();
+        File::create(dir.join(OsString::from_vec(b"log.20200825T151133.\xff".to_vec()))).unwrap();
+        File::create(dir.join(OsString::from_vec(b"\xfflog.2".to_vec()))).unwrap();
+
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(4), ContentLimit::Lines(1));
+        assert_eq!(vec![dir.join("log.1")], log.log_paths());
+
+        let mut log = FileRotate::new(
+            &log_path,
+            TimestampSuffix::default(FileLimit::MaxFiles(4)),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(vec![dir.join("log.20200825T151133")], log.log_paths());
+
+        // A base name which is not valid UTF-8 is kept intact in the rotated file names
+        let log_path = dir.join(OsString::from_vec(b"\xfflog".to_vec()));
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(4), ContentLimit::Lines(1));
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            vec![
+                dir.join(OsString::from_vec(b"\xfflog.2".to_vec())),
+                dir.join(OsString::from_vec(b"\xfflog.1".to_vec())),
+            ],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn custom_separator() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let log_path = dir.join("app_log");
+
+        // Files from before the separator was changed are left alone
+        File::create(dir.join("app_log.1")).unwrap();
+        File::create(dir.join("app_log.2024-06-01")).unwrap();
+
+        let mut log = FileRotate::new(
+            &log_path,
+            CountSuffix::new(2).with_separator("-"),
+            ContentLimit::Lines(1),
+        );
+        write!(log, "a\nb\nc\n").unwrap();
+        assert_eq!(
+            vec![dir.join("app_log-2"), dir.join("app_log-1")],
+            log.log_paths()
+        );
+        assert_eq!("b\n", fs::read_to_string(dir.join("app_log-2")).unwrap());
+        assert_eq!("c\n", fs::read_to_string(dir.join("app_log-1")).unwrap());
+        assert!(dir.join("app_log.1").exists());
+
+        let log_path = dir.join("other_log");
+        let mut log = FileRotate::new(
+            &log_path,
+            TimestampSuffix::default(FileLimit::MaxFiles(10)).with_separator("-"),
+            ContentLimit::Lines(1),
+        );
+        write!(log, "a\nb\n").unwrap();
+        let log_paths = log.log_paths();
+        assert_eq!(2, log_paths.len());
+        for path in &log_paths {
+            let filename = path.file_name().unwrap().to_string_lossy();
+            assert!(filename.starts_with("other_log-2"));
+        }
+        assert_eq!("a\n", fs::read_to_string(&log_paths[0]).unwrap());
+        assert_eq!("b\n", fs::read_to_string(&log_paths[1]).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn strict_timestamp_parsing() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let near_misses = [
+            "2024-06-01x",
+            "2024-06",
+            "2024-06-01T",
+            "2024-6-1",
+            "2024-06-01 ",
+            "20240601",
+            "20240601T",
+            "20240601T1200",
+            "20240601T120000x",
+            "20240601T120000Z",
+            "202406",
+            "2024-06-01T12:00:00",
+        ];
+        for name in near_misses.iter() {
+            File::create(dir.join(format!("log.{}", name))).unwrap();
+        }
+        File::create(dir.join("log.2024-06-01")).unwrap();
+        File::create(dir.join("log.2024-06-01.1")).unwrap();
+        File::create(dir.join("log.20240601T120000")).unwrap();
+        File::create(dir.join("log.20240601T120000.2")).unwrap();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::with_format("%Y-%m-%d", FileLimit::MaxFiles(100)),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(
+            vec![dir.join("log.2024-06-01"), dir.join("log.2024-06-01.1")],
+            log.log_paths()
+        );
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::with_format("%Y%m%dT%H%M%S", FileLimit::MaxFiles(100)),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(
+            vec![
+                dir.join("log.20240601T120000"),
+                dir.join("log.20240601T120000.2")
+            ],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_date_from_custom() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::with_format("%Y%m%d", FileLimit::MaxFiles(10))
+                .with_date_from(DateFrom::Custom(chrono::Duration::days(1))),
+            ContentLimit::Lines(1),
+        );
+        write!(log, "a\nb\n").unwrap();
+        let yesterday = (chrono::Local::now() - chrono::Duration::days(1)).format("%Y%m%d");
+        assert_eq!(
+            vec![
+                dir.join(format!("log.{}", yesterday)),
+                dir.join(format!("log.{}.1", yesterday)),
+            ],
+            log.log_paths()
+        );
+
+        let mut log = FileRotate::new(
+            dir.join("other"),
+            TimestampSuffix::with_format("%Y%m%d", FileLimit::MaxFiles(10))
+                .with_date_from(DateFrom::Custom(chrono::Duration::days(-1))),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        let tomorrow = (chrono::Local::now() + chrono::Duration::days(1)).format("%Y%m%d");
+        assert_eq!(
+            vec![dir.join(format!("other.{}", tomorrow))],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn epoch_max_files_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        // Old epoch files, and a `CountSuffix` file which must not be mistaken for one
+        File::create(dir.join("log.1000000000")).unwrap();
+        File::create(dir.join("log.1000000000.1")).unwrap();
+        File::create(dir.join("log.1")).unwrap();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            EpochSuffix::new(FileLimit::MaxFiles(3)),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(
+            vec![dir.join("log.1000000000"), dir.join("log.1000000000.1")],
+            log.log_paths()
+        );
+
+        write!(log, "a\nb\n").unwrap();
+        let log_paths = log.log_paths();
+        assert_eq!(3, log_paths.len());
+        assert_eq!(dir.join("log.1000000000.1"), log_paths[0]);
+        assert_eq!("a\n", fs::read_to_string(&log_paths[1]).unwrap());
+        assert_eq!("b\n", fs::read_to_string(&log_paths[2]).unwrap());
+        assert!(!dir.join("log.1000000000").exists());
+        assert!(dir.join("log.1").exists());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn epoch_age_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        File::create(dir.join("log.1000000000")).unwrap();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            EpochSuffix::new(FileLimit::Age(std::time::Duration::from_secs(
+                7 * 24 * 3600,
+            ))),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        assert!(!dir.join("log.1000000000").exists());
+        assert_eq!(1, log.log_paths().len());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn age_with_min_files() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        for day in 1..=10 {
+            File::create(dir.join(format!("log.202008{:02}T120000", day))).unwrap();
+            File::create(dir.join(format!("log.{}", 1_000_000_000 + day))).unwrap();
+        }
+        let file_limit = FileLimit::AgeWithMinFiles {
+            age: std::time::Duration::from_secs(7 * 24 * 3600),
+            min_files: 3,
+        };
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(file_limit.clone()),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        let paths = log.log_paths();
+        assert_eq!(3, paths.len());
+        assert_eq!(dir.join("log.20200809T120000"), paths[0]);
+        assert_eq!(dir.join("log.20200810T120000"), paths[1]);
+        assert_eq!("a\n", fs::read_to_string(&paths[2]).unwrap());
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            EpochSuffix::new(file_limit),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        let paths = log.log_paths();
+        assert_eq!(3, paths.len());
+        assert_eq!(dir.join("log.1000000009"), paths[0]);
+        assert_eq!(dir.join("log.1000000010"), paths[1]);
+        assert_eq!("a\n", fs::read_to_string(&paths[2]).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn sub_second_age() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let clock = clock::ManualClock::new(chrono::Local.timestamp_opt(1_000_000_000, 0).unwrap());
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            EpochSuffix::new(FileLimit::Age(std::time::Duration::from_millis(1500)))
+                .with_clock(clock.clone()),
             ContentLimit::Lines(1),
         );
-        writeln!(log, "trigger\nat\nleast\none\nrotation").unwrap();
+        writeln!(log, "a").unwrap();
+        // 1.5 seconds are rounded up to 2, so the first file is still kept after 2 seconds
+        clock.advance(chrono::Duration::seconds(2));
+        writeln!(log, "b").unwrap();
+        assert!(dir.join("log.1000000000").exists());
+        clock.advance(chrono::Duration::seconds(1));
+        writeln!(log, "c").unwrap();
+        assert!(!dir.join("log.1000000000").exists());
 
+        assert_eq!(
+            FileLimit::Age(std::time::Duration::from_secs(7 * 24 * 3600)),
+            FileLimit::age(chrono::Duration::weeks(1))
+        );
+        assert_eq!(
+            FileLimit::Age(std::time::Duration::ZERO),
+            FileLimit::age(chrono::Duration::seconds(-5))
+        );
+        assert_eq!(
+            ParseErrorKind::OutOfRange,
+            *FileLimit::age_from_str("-5m").unwrap_err().kind()
+        );
+    }
 
-        let mut filenames = std::fs::read_dir(dir)
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_offset_order() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        // A file from before the offset was added to the format, and files from three time zones
+        let timestamps = vec![
+            "20240501T120000",
+            "20240601T120000+0200",
+            "20240601T101500+0000",
+            "20240601T113000+0100",
+        ];
+        for timestamp in &timestamps {
+            File::create(dir.join(format!("log.{}", timestamp))).unwrap();
+        }
+        let now = chrono::Utc
+            .with_ymd_and_hms(2024, 6, 1, 11, 0, 0)
             .unwrap()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.path().is_file())
-            .map(|entry| entry.file_name().to_string_lossy().into_owned())
-            .collect::<Vec<_>>();
-        filenames.sort();
-        assert!(filenames.contains(&"log".to_string()));
-        assert!(filenames.contains(&recent_file));
-        assert!(!filenames.contains(&"log.20200825T151133".to_string()));
-        assert!(!filenames.contains(&"log.20200825T151133.1".to_string()));
+            .with_timezone(&chrono::Local);
+        let format = "%Y%m%dT%H%M%S%z";
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::try_with_format(
+                format,
+                FileLimit::Age(std::time::Duration::from_secs(30 * 60)),
+            )
+            .unwrap()
+            .with_clock(clock::ManualClock::new(now)),
+            ContentLimit::Lines(1),
+        );
+        let suffixes = |log: &mut FileRotate<TimestampSuffix>| {
+            log.log_paths()
+                .iter()
+                .map(|path| path.extension().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(timestamps, suffixes(&mut log));
+
+        // Everything before 10:30 UTC is deleted
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            vec![
+                "20240601T113000+0100".to_string(),
+                now.format(format).to_string()
+            ],
+            suffixes(&mut log)
+        );
     }
+
     #[test]
-    fn count_max_files_rotation() {
+    #[cfg(feature = "chrono04")]
+    fn timestamp_previous_formats() {
+        use chrono::TimeZone;
         let tmp_dir = TempDir::new("file-rotate-test").unwrap();
-        let parent = tmp_dir.path();
-        let log_path = parent.join("log");
+        let dir = tmp_dir.path();
+        // Lexically, "2024-06-01" would sort before "20240531T120000"
+        for timestamp in &["2024-06-01", "20240531T120000", "20240601T120000"] {
+            File::create(dir.join(format!("log.{}", timestamp))).unwrap();
+        }
+        let clock = clock::ManualClock::new(
+            chrono::Local
+                .with_ymd_and_hms(2024, 6, 2, 12, 0, 0)
+                .unwrap(),
+        );
         let mut log = FileRotate::new(
-            &*log_path.to_string_lossy(),
-            CountSuffix::new(4),
-            ContentLimit::Lines(2),
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::MaxFiles(3))
+                .with_previous_formats(vec!["%Y-%m-%d".to_string()])
+                .with_clock(clock),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(
+            vec![
+                dir.join("log.20240531T120000"),
+                dir.join("log.2024-06-01"),
+                dir.join("log.20240601T120000"),
+            ],
+            log.log_paths()
         );
 
-        // Write 9 lines
-        // This should result in 5 files in total (4 rotated files). The main file will have one line.
-        write!(log, "a\nb\nc\nd\ne\nf\ng\nh\ni\n").unwrap(); // 9 lines
-        let log_paths = vec![
-            parent.join("log.4"),
-            parent.join("log.3"),
-            parent.join("log.2"),
-            parent.join("log.1"),
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            vec![
+                dir.join("log.2024-06-01"),
+                dir.join("log.20240601T120000"),
+                dir.join("log.20240602T120000"),
+            ],
+            log.log_paths()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_origin() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let other_host = dir.join("log.db-01.7.20000101T000000");
+
+        for only_own_origin in [true, false].iter() {
+            File::create(&other_host).unwrap();
+            let mut log = FileRotate::new(
+                dir.join("log"),
+                TimestampSuffix::default(FileLimit::MaxFiles(2))
+                    .with_origin(vec!["web.03".to_string(), "42".to_string()])
+                    .only_own_origin(*only_own_origin),
+                ContentLimit::Lines(1),
+            );
+            writeln!(log, "a").unwrap();
+            writeln!(log, "b").unwrap();
+            let paths = log.log_paths();
+            assert_eq!(2, paths.len());
+            for path in &paths {
+                let name = path.file_name().unwrap().to_string_lossy();
+                assert!(name.starts_with("log.web_03.42.20"), "{}", name);
+            }
+            assert_eq!(*only_own_origin, other_host.exists());
+            for path in paths {
+                fs::remove_file(path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn age_of_coarse_timestamps() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        // Both files are from the day that is 36 hours ago, only their modification time tells
+        // which one is older than that
+        let old = chrono::Local::now() - chrono::Duration::hours(36);
+        let day = old.format("%Y-%m-%d");
+        let files = vec![
+            (
+                dir.join(format!("log.{}", day)),
+                old - chrono::Duration::hours(1),
+            ),
+            (
+                dir.join(format!("log.{}.1", day)),
+                old + chrono::Duration::hours(1),
+            ),
         ];
-        assert_eq!(log_paths, log.log_paths());
-        assert_eq!("a\nb\n", fs::read_to_string(&log_paths[0]).unwrap());
-        assert_eq!("c\nd\n", fs::read_to_string(&log_paths[1]).unwrap());
-        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[2]).unwrap());
-        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[3]).unwrap());
-        assert_eq!("i\n", fs::read_to_string(&log_path).unwrap());
+        for (path, modified) in &files {
+            File::create(path)
+                .unwrap()
+                .set_modified((*modified).into())
+                .unwrap();
+        }
 
-        // Write 4 more lines
-        write!(log, "j\nk\nl\nm\n").unwrap();
-        list(parent);
-        assert_eq!(log_paths, log.log_paths());
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::with_format(
+                "%Y-%m-%d",
+                FileLimit::Age(std::time::Duration::from_secs(36 * 3600)),
+            ),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        assert!(!files[0].0.exists());
+        assert!(files[1].0.exists());
+    }
 
-        assert_eq!("e\nf\n", fs::read_to_string(&log_paths[0]).unwrap());
-        assert_eq!("g\nh\n", fs::read_to_string(&log_paths[1]).unwrap());
-        assert_eq!("i\nj\n", fs::read_to_string(&log_paths[2]).unwrap());
-        assert_eq!("k\nl\n", fs::read_to_string(&log_paths[3]).unwrap());
-        assert_eq!("m\n", fs::read_to_string(&log_path).unwrap());
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn manual_clock_age_rotation() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let clock = clock::ManualClock::new(
+            chrono::Local
+                .with_ymd_and_hms(2021, 1, 1, 12, 0, 0)
+                .unwrap(),
+        );
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::Age(std::time::Duration::from_secs(24 * 3600)))
+                .with_clock(clock.clone()),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+        assert_eq!(vec![dir.join("log.20210101T120000")], log.log_paths());
+
+        clock.advance(chrono::Duration::hours(25));
+        writeln!(log, "b").unwrap();
+        assert_eq!(vec![dir.join("log.20210102T130000")], log.log_paths());
+        assert!(!dir.join("log.20210101T120000").exists());
     }
 
     #[test]
-    fn rotate_to_deleted_directory() {
-        // NOTE: Only supported with count, not with timestamp suffix.
+    #[cfg(feature = "chrono04")]
+    fn timestamp_subsecond_format() {
+        use chrono::TimeZone;
         let tmp_dir = TempDir::new("file-rotate-test").unwrap();
-        let parent = tmp_dir.path();
-        let log_path = parent.join("log");
+        let dir = tmp_dir.path();
+        let clock = clock::ManualClock::new(
+            chrono::Local
+                .with_ymd_and_hms(2021, 1, 1, 12, 0, 0)
+                .unwrap(),
+        );
+        for format in ["%Y%m%dT%H%M%S%3f", "%Y%m%dT%H%M%S%.3f"].iter() {
+            let name = format!("log{}", format.len());
+            let new_log = || {
+                FileRotate::new(
+                    dir.join(&name),
+                    TimestampSuffix::with_format(*format, FileLimit::MaxFiles(3))
+                        .with_clock(clock.clone()),
+                    ContentLimit::Lines(1),
+                )
+            };
+            let mut log = new_log();
+            for _ in 0..3 {
+                writeln!(log, "a").unwrap();
+                clock.advance(chrono::Duration::milliseconds(1));
+            }
+            // Same millisecond as the previous rotation
+            clock.advance(chrono::Duration::milliseconds(-1));
+            writeln!(log, "a").unwrap();
+
+            let suffixes = |log: &mut FileRotate<TimestampSuffix>| {
+                log.log_paths()
+                    .iter()
+                    .map(|path| {
+                        path.to_string_lossy()[dir.to_string_lossy().len() + 1..].to_string()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let expected = if format.contains('.') {
+                vec![
+                    "log17.20210101T120000.001",
+                    "log17.20210101T120000.002",
+                    "log17.20210101T120000.002.1",
+                ]
+            } else {
+                vec![
+                    "log16.20210101T120000001",
+                    "log16.20210101T120000002",
+                    "log16.20210101T120000002.1",
+                ]
+            };
+            assert_eq!(expected, suffixes(&mut log));
+            // The same, after scanning the directory
+            assert_eq!(expected, suffixes(&mut new_log()));
+            clock.advance(chrono::Duration::milliseconds(-2));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_format_validation() {
+        let kind = |format: &'static str| {
+            TimestampSuffix::try_with_format(format, FileLimit::MaxFiles(1))
+                .err()
+                .map(|err| err.kind().clone())
+        };
+        assert_eq!(None, kind("%Y%m%dT%H%M%S"));
+        assert_eq!(None, kind("%Y-%m-%d"));
+        assert_eq!(None, kind("%Y%m%dT%H%M%S%.3f"));
+        assert_eq!(Some(FormatErrorKind::Invalid), kind("%Y%Q"));
+        assert_eq!(Some(FormatErrorKind::NotSortable), kind("%d-%m-%Y"));
+        assert_eq!(Some(FormatErrorKind::NotSortable), kind("%Y%-m%d"));
+        assert_eq!(Some(FormatErrorKind::NotSortable), kind("static"));
+        assert_eq!(Some(FormatErrorKind::NotParsable), kind("%Y%m%dT%H"));
+        assert_eq!(Some(FormatErrorKind::PathSeparator), kind("%Y/%m/%d"));
+        assert_eq!(None, kind("%G-W%V"));
+        // The week of 2008-12-29 is the first of 2009
+        assert_eq!(Some(FormatErrorKind::NotSortable), kind("%Y-W%V"));
+
+        let interval = |format: &'static str| {
+            IntervalSuffix::try_with_format(format, FileLimit::MaxFiles(1))
+                .err()
+                .map(|err| err.kind().clone())
+        };
+        assert_eq!(None, interval("%Y-%m-%d"));
+        assert_eq!(Some(FormatErrorKind::NotSortable), interval("%d-%m-%Y"));
+        assert_eq!(Some(FormatErrorKind::PathSeparator), interval("%Y/%m/%d"));
+    }
+
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_iso_weeks() {
+        use chrono::TimeZone;
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        // 2025-W01 starts on 2024-12-30
+        for week in ["2024-W01", "2024-W22", "2024-W52", "2025-W01", "2025-W22"] {
+            File::create(dir.join(format!("log.{}", week))).unwrap();
+        }
+        let now = chrono::Local
+            .with_ymd_and_hms(2025, 6, 2, 12, 0, 0)
+            .unwrap();
         let mut log = FileRotate::new(
-            &*log_path.to_string_lossy(),
-            CountSuffix::new(4),
+            dir.join("log"),
+            TimestampSuffix::try_with_format("%G-W%V", FileLimit::age_from_str("52w").unwrap())
+                .unwrap()
+                .with_clock(clock::ManualClock::new(now)),
             ContentLimit::Lines(1),
         );
+        writeln!(log, "a").unwrap();
+        assert_eq!(
+            vec![
+                dir.join("log.2024-W52"),
+                dir.join("log.2025-W01"),
+                dir.join("log.2025-W22"),
+                dir.join("log.2025-W23"),
+            ],
+            log.log_paths()
+        );
+    }
 
-        write!(log, "a\nb\n").unwrap();
-        assert_eq!("", fs::read_to_string(&log_path).unwrap());
-        assert_eq!("a\n", fs::read_to_string(&log.log_paths()[0]).unwrap());
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn ignore_files_sharing_the_prefix() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        let others = ["log2.1", "log.old", "logfoo.20240101T000000", "logging.1"];
+        for name in others.iter() {
+            File::create(dir.join(name)).unwrap();
+        }
+        File::create(dir.join("log.1")).unwrap();
+        File::create(dir.join("log.20240101T000000")).unwrap();
 
-        let _ = fs::remove_dir_all(parent);
+        let mut log = FileRotate::new(dir.join("log"), CountSuffix::new(1), ContentLimit::Lines(1));
+        assert_eq!(vec![dir.join("log.1")], log.log_paths());
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
 
-        assert!(writeln!(log, "c").is_ok());
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::MaxFiles(1)),
+            ContentLimit::Lines(1),
+        );
+        assert_eq!(vec![dir.join("log.20240101T000000")], log.log_paths());
+        writeln!(log, "a").unwrap();
+        assert!(!dir.join("log.20240101T000000").exists());
 
-        log.flush().unwrap();
+        for name in others.iter() {
+            assert!(dir.join(name).exists(), "{} was deleted", name);
+        }
+    }
 
-        writeln!(log, "d").unwrap();
-        assert_eq!("", fs::read_to_string(&log_path).unwrap());
-        assert_eq!("d\n", fs::read_to_string(&log.log_paths()[0]).unwrap());
+    #[test]
+    fn count_suffix_base() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            CountSuffix::with_base(3, 0),
+            ContentLimit::Lines(1),
+        );
+        for line in ["a", "b", "c", "d", "e"].iter() {
+            writeln!(log, "{}", line).unwrap();
+        }
+        let mut files = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(vec!["log", "log.0", "log.1", "log.2"], files);
+        assert_eq!("e\n", fs::read_to_string(dir.join("log.0")).unwrap());
+        assert_eq!("c\n", fs::read_to_string(dir.join("log.2")).unwrap());
     }
 
     #[test]
-    fn write_complete_record_until_bytes_surpassed() {
+    fn name_template() {
+        use template::{NameTemplate, TemplateErrorKind};
+        let kind = |template: &str| {
+            template
+                .parse::<NameTemplate>()
+                .map_err(|err| err.kind().clone())
+        };
+        assert_eq!(Err(TemplateErrorKind::MissingSuffix), kind("{base}.log"));
+        assert_eq!(
+            Err(TemplateErrorKind::RepeatedSuffix),
+            kind("{base}.{suffix}.{suffix}")
+        );
+        assert_eq!(Err(TemplateErrorKind::MissingBase), kind("log-{suffix}"));
+        assert_eq!(
+            Err(TemplateErrorKind::UnknownPlaceholder("date".to_string())),
+            kind("{base}.{date}.{suffix}")
+        );
+        assert_eq!(Err(TemplateErrorKind::Unclosed), kind("{base}.{suffix"));
+        assert_eq!(
+            Err(TemplateErrorKind::AdjacentPlaceholders),
+            kind("{stem}{suffix}.log")
+        );
+        assert_eq!(
+            Err(TemplateErrorKind::PathSeparator),
+            kind("old/{base}.{suffix}")
+        );
+        assert!(kind("{{{base}}}.{suffix}").is_ok());
+
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+        File::create(dir.join("app-x.log")).unwrap();
+        File::create(dir.join("other-1.log")).unwrap();
+        let template = "{stem}-{suffix}.{ext}".parse::<NameTemplate>().unwrap();
+        let mut log = FileRotate::new(
+            dir.join("app.log"),
+            CountSuffix::new(2).with_template(template.clone()),
+            ContentLimit::Lines(1),
+        );
+        for line in ["a", "b", "c"].iter() {
+            writeln!(log, "{}", line).unwrap();
+        }
+        assert_eq!(
+            vec![dir.join("app-2.log"), dir.join("app-1.log")],
+            log.log_paths()
+        );
+        assert_eq!("c\n", fs::read_to_string(dir.join("app-1.log")).unwrap());
+        assert!(!dir.join("app-3.log").exists());
+        assert!(dir.join("app-x.log").exists());
+
+        let files = scan(
+            &dir.join("app.log"),
+            &mut CountSuffix::new(2).with_template(template),
+        )
+        .unwrap();
+        let suffixes = files
+            .iter()
+            .map(|file| file.suffix.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["1", "2"], suffixes);
+    }
+
+    #[test]
+    fn scan_log_files() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let mut log = FileRotate::new(&log_path, CountSuffix::new(3), ContentLimit::Lines(1));
+        for line in ["a", "bb", "ccc"].iter() {
+            writeln!(log, "{}", line).unwrap();
+        }
+        let files = scan(&log_path, &mut CountSuffix::new(3)).unwrap();
+        let files = files
+            .iter()
+            .map(|file| (file.path.clone(), file.suffix.as_str(), file.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (tmp_dir.path().join("log.1"), "1", 4),
+                (tmp_dir.path().join("log.2"), "2", 3),
+                (tmp_dir.path().join("log.3"), "3", 2),
+            ],
+            files
+        );
+
+        assert!(scan(
+            &tmp_dir.path().join("missing/log"),
+            &mut CountSuffix::new(3)
+        )
+        .is_err());
+        assert!(!tmp_dir.path().join("missing").exists());
+
+        /// Lists a file that isn't named after the log file
+        struct Foreign;
+        impl SuffixScheme for Foreign {
+            fn rotate(&mut self, _basepath: &Path) -> String {
+                "1".to_string()
+            }
+            fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
+                vec![
+                    basepath.with_file_name("other"),
+                    self.rotated_path(basepath, "1"),
+                ]
+            }
+        }
+        fs::write(tmp_dir.path().join("other"), "x\n").unwrap();
+        let files = scan(&log_path, &mut Foreign).unwrap();
+        assert_eq!(
+            vec![tmp_dir.path().join("log.1")],
+            files.into_iter().map(|file| file.path).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "chrono04", not(target_os = "wasi")))]
+    fn broken_time_zone() {
+        use clock::{Clock, UtcClock};
+        /// Like [clock::SystemClock] where the local time zone can't be determined
+        struct BrokenTimeZone;
+        impl Clock for BrokenTimeZone {
+            fn now(&self) -> chrono::DateTime<chrono::Local> {
+                clock::now_or_utc(|| panic!("no time zone database"))
+            }
+        }
         let tmp_dir = TempDir::new("file-rotate-test").unwrap();
         let dir = tmp_dir.path();
-        let log_path = dir.join("log");
 
         let mut log = FileRotate::new(
-            &log_path,
-            TimestampSuffix::default(FileLimit::MaxFiles(100)),
-            ContentLimit::BytesSurpassed(1),
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::MaxFiles(2)).with_clock(BrokenTimeZone),
+            ContentLimit::Lines(1),
         );
+        writeln!(log, "a").unwrap();
+        assert_eq!(1, log.log_paths().len());
+        assert_eq!(0, BrokenTimeZone.now().offset().local_minus_utc());
 
-        write!(log, "0123456789").unwrap();
-        log.flush().unwrap();
-        assert!(log_path.exists());
-        // shouldn't exist yet - because entire record was written in one shot
-        assert!(log.log_paths().is_empty());
+        assert_eq!(0, UtcClock.now().offset().local_minus_utc());
+        let mut log = FileRotate::new(
+            dir.join("utc"),
+            EpochSuffix::new(FileLimit::KeepPerPeriod {
+                recent: chrono::Duration::zero(),
+                per_period: 1,
+                period: Period::Day,
+            })
+            .with_clock(UtcClock),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a\nb").unwrap();
+        assert_eq!(1, log.log_paths().len());
+    }
 
-        // This should create the second file
-        write!(log, "0123456789").unwrap();
-        log.flush().unwrap();
-        assert!(&log.log_paths()[0].exists());
+    #[test]
+    #[cfg(feature = "chrono04")]
+    fn timestamp_keep_per_period_rotation() {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let dir = tmp_dir.path();
+
+        let old_files = [
+            "log.20200824T080000",
+            "log.20200824T120000",
+            "log.20200824T120000.1",
+            "log.20200825T151133",
+            "log.20200826T000000",
+            "log.20200826T235959",
+        ];
+        for name in old_files.iter() {
+            File::create(dir.join(name)).unwrap();
+        }
+        let now = chrono::Local::now();
+        let recent_files = [
+            (now - chrono::Duration::hours(3))
+                .format("log.%Y%m%dT%H%M%S")
+                .to_string(),
+            (now - chrono::Duration::hours(2))
+                .format("log.%Y%m%dT%H%M%S")
+                .to_string(),
+        ];
+        for name in recent_files.iter() {
+            File::create(dir.join(name)).unwrap();
+        }
+
+        let mut log = FileRotate::new(
+            dir.join("log"),
+            TimestampSuffix::default(FileLimit::KeepPerPeriod {
+                recent: chrono::Duration::hours(24),
+                per_period: 1,
+                period: Period::Day,
+            }),
+            ContentLimit::Lines(1),
+        );
+        writeln!(log, "a").unwrap();
+
+        let log_paths = log.log_paths();
+        assert_eq!(6, log_paths.len());
+        assert_eq!(dir.join("log.20200824T120000.1"), log_paths[0]);
+        assert_eq!(dir.join("log.20200825T151133"), log_paths[1]);
+        assert_eq!(dir.join("log.20200826T235959"), log_paths[2]);
+        assert_eq!(dir.join(&recent_files[0]), log_paths[3]);
+        assert_eq!(dir.join(&recent_files[1]), log_paths[4]);
+        for name in [
+            "log.20200824T080000",
+            "log.20200824T120000",
+            "log.20200826T000000",
+        ]
+        .iter()
+        {
+            assert!(!dir.join(name).exists());
+        }
     }
 
     #[quickcheck_macros::quickcheck]
+    #[cfg(feature = "chrono04")]
     fn arbitrary_lines(count: usize) {
         let tmp_dir = TempDir::new("file-rotate-test").unwrap();
         let dir = tmp_dir.path();
@@ -537,6 +6924,7 @@ mod tests {
     }
 
     #[quickcheck_macros::quickcheck]
+    #[cfg(feature = "chrono04")]
     fn arbitrary_bytes(count: usize) {
         let tmp_dir = TempDir::new("file-rotate-test").unwrap();
         let dir = tmp_dir.path();
@@ -558,4 +6946,82 @@ mod tests {
         write!(log, "1").unwrap();
         assert!(&log.log_paths()[0].exists());
     }
+
+    #[quickcheck_macros::quickcheck]
+    fn arbitrary_line_breaks(lines: u8, writes: Vec<Vec<bool>>) {
+        let tmp_dir = TempDir::new("file-rotate-test").unwrap();
+        let log_path = tmp_dir.path().join("log");
+
+        let lines = lines as usize % 4 + 1;
+        let mut log = FileRotate::new(
+            &log_path,
+            CountSuffix::new(1000),
+            ContentLimit::Lines(lines),
+        );
+        let mut expected = Vec::new();
+        // Every line break may mean a rotation, so keep the input small
+        for write in writes.iter().take(8) {
+            let buf = write
+                .iter()
+                .take(32)
+                .map(|newline| if *newline { b'\n' } else { b'x' })
+                .collect::<Vec<_>>();
+            log.write_all(&buf).unwrap();
+            expected.extend(buf);
+        }
+
+        let mut written = Vec::new();
+        for path in log.log_paths() {
+            let content = fs::read(path).unwrap();
+            assert_eq!(lines, count_newlines(&content));
+            assert_eq!(Some(&b'\n'), content.last());
+            written.extend(content);
+        }
+        let current = fs::read(&log_path).unwrap();
+        assert!(count_newlines(&current) < lines);
+        written.extend(current);
+        assert_eq!(expected, written);
+    }
+
+    #[test]
+    fn bare_file_name() {
+        // Like a preopened directory of WASI, where paths are relative and never made absolute
+        assert_eq!(Path::new("."), directory(Path::new("app.log")));
+        assert_eq!(Path::new("logs"), directory(Path::new("logs/app.log")));
+        assert_eq!(
+            PathBuf::from("app.log.1"),
+            CountSuffix::new(3).rotated_path(Path::new("app.log"), "1")
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "wasi")]
+    fn wasi_preopened_directory() {
+        // WASI has no temporary directory; run with an empty directory preopened as `.`, e.g.
+        // `wasmtime --dir <directory>::. <test binary> wasi_preopened_directory`
+        let names = ["app.log", "app.log.1", "app.log.2", "app.log.3"];
+        let mut log = FileRotate::new("app.log", CountSuffix::new(3), ContentLimit::Lines(1));
+        writeln!(log, "a").unwrap();
+        writeln!(log, "b").unwrap();
+        drop(log);
+        // The rotated files are found again in the directory
+        let mut log = FileRotate::new("app.log", CountSuffix::new(3), ContentLimit::Lines(1));
+        writeln!(log, "c").unwrap();
+        let log_paths = log.log_paths();
+        drop(log);
+        let first = fs::read_to_string("app.log.3");
+        for name in &names {
+            let _ = fs::remove_file(name);
+        }
+
+        assert_eq!(
+            vec![
+                PathBuf::from("app.log.3"),
+                PathBuf::from("app.log.2"),
+                PathBuf::from("app.log.1"),
+            ],
+            log_paths
+        );
+        assert_eq!("a\n", first.unwrap());
+    }
 }
@@ -0,0 +1,386 @@
+//! Write to a [FileRotate] from a dedicated thread, so that rotations and slow disks don't block
+//! the writer.
+//!
+//! ```
+//! use file_rotate::{
+//!     non_blocking::{NonBlockingFileRotate, OverflowPolicy},
+//!     suffix::CountSuffix,
+//!     ContentLimit, FileRotate,
+//! };
+//! use std::io::Write;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let log = FileRotate::new(directory.join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(100));
+//! let (mut writer, guard) = NonBlockingFileRotate::new(log, 1000, OverflowPolicy::Block);
+//! writeln!(writer, "Hello World!").unwrap();
+//! // Dropping the guard writes everything that is queued and stops the thread
+//! drop(guard);
+//! ```
+
+use crate::{fs_ops::FsOps, suffix::SuffixScheme, FileRotate};
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::{mpsc, Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// What to do when the queue of a [NonBlockingFileRotate] is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait until there is room in the queue.
+    Block,
+    /// Wait until there is room in the queue, for at most the given time. Then the write fails
+    /// with [io::ErrorKind::TimedOut], and isn't counted in [NonBlockingFileRotate::dropped].
+    BlockTimeout(Duration),
+    /// Discard the write, and count it in [NonBlockingFileRotate::dropped].
+    DropNewest,
+    /// Discard the oldest write in the queue to make room, and count it in
+    /// [NonBlockingFileRotate::dropped].
+    DropOldest,
+}
+
+/// Makes the bytes written in place of the given number of dropped writes, see
+/// [NonBlockingFileRotate::with_drop_marker].
+pub type DropMarkerFn = Box<dyn Fn(usize) -> Vec<u8> + Send + Sync>;
+
+/// The line `--- N records dropped ---`, for [NonBlockingFileRotate::with_drop_marker].
+pub fn drop_marker(dropped: usize) -> Vec<u8> {
+    format!("--- {} records dropped ---\n", dropped).into_bytes()
+}
+
+enum Message {
+    Write(Vec<u8>),
+    Flush(mpsc::SyncSender<io::Result<()>>),
+}
+
+/// What the writers and the thread share.
+struct Queue {
+    state: Mutex<State>,
+    /// Notified when a message is queued or the thread is to stop
+    not_empty: Condvar,
+    /// Notified when a message is taken from the queue or the thread has stopped
+    not_full: Condvar,
+    capacity: usize,
+}
+
+struct State {
+    messages: VecDeque<Message>,
+    /// All dropped writes
+    dropped: usize,
+    /// Dropped writes that no marker has been written for yet
+    unmarked: usize,
+    /// The [WorkerGuard] was dropped
+    shutdown: bool,
+    /// The thread has ended
+    stopped: bool,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A [Write] that queues the written bytes for a thread that owns the [FileRotate].
+///
+/// Clones share the queue. Errors of the [FileRotate] are returned by the next
+/// [flush](Write::flush).
+#[derive(Clone)]
+pub struct NonBlockingFileRotate {
+    queue: Arc<Queue>,
+    policy: OverflowPolicy,
+}
+
+/// Stops the thread of a [NonBlockingFileRotate] when dropped.
+///
+/// Everything that is queued by then is written, whatever the [OverflowPolicy], followed by the
+/// drop marker if there is one and writes were dropped since the last. Writes after that, and
+/// writes waiting for room under [OverflowPolicy::Block], fail with
+/// [io::ErrorKind::BrokenPipe].
+pub struct WorkerGuard {
+    queue: Arc<Queue>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Marks the queue as stopped when the thread ends, even by a panic.
+struct Stopped(Arc<Queue>);
+
+impl Drop for Stopped {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.stopped = true;
+        // Fails waiting flushes
+        state.messages.clear();
+        self.0.not_full.notify_all();
+    }
+}
+
+/// What the thread does next.
+enum Job {
+    Message(Message, usize),
+    Mark(usize),
+    FlushIfDue,
+    Stop,
+}
+
+impl NonBlockingFileRotate {
+    /// Move `file_rotate` to a new thread, with room for `capacity` writes in the queue (at least
+    /// one).
+    pub fn new<S, W, Fs>(
+        file_rotate: FileRotate<S, W, Fs>,
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> (Self, WorkerGuard)
+    where
+        S: SuffixScheme + Send + 'static,
+        W: Write + Send + 'static,
+        Fs: FsOps + 'static,
+    {
+        Self::spawn(file_rotate, capacity, policy, None)
+    }
+
+    /// Like [NonBlockingFileRotate::new], and when writes were dropped, write `marker(count)` to
+    /// the file once the queue is drained, or before a [flush](Write::flush) is done. E.g.
+    /// `Box::new(drop_marker)` for the line `--- N records dropped ---`.
+    pub fn with_drop_marker<S, W, Fs>(
+        file_rotate: FileRotate<S, W, Fs>,
+        capacity: usize,
+        policy: OverflowPolicy,
+        marker: DropMarkerFn,
+    ) -> (Self, WorkerGuard)
+    where
+        S: SuffixScheme + Send + 'static,
+        W: Write + Send + 'static,
+        Fs: FsOps + 'static,
+    {
+        Self::spawn(file_rotate, capacity, policy, Some(marker))
+    }
+
+    fn spawn<S, W, Fs>(
+        mut file_rotate: FileRotate<S, W, Fs>,
+        capacity: usize,
+        policy: OverflowPolicy,
+        marker: Option<DropMarkerFn>,
+    ) -> (Self, WorkerGuard)
+    where
+        S: SuffixScheme + Send + 'static,
+        W: Write + Send + 'static,
+        Fs: FsOps + 'static,
+    {
+        let queue = Arc::new(Queue {
+            state: Mutex::new(State {
+                messages: VecDeque::new(),
+                dropped: 0,
+                unmarked: 0,
+                shutdown: false,
+                stopped: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity: capacity.max(1),
+        });
+        let stopped = Stopped(queue.clone());
+        let handle = thread::spawn(move || {
+            let queue = stopped.0.clone();
+            // The first error since the last flush
+            let mut error = None;
+            loop {
+                // Wake up when written data is due to be flushed
+                let due_in = file_rotate.flush_due_in();
+                let job = {
+                    let mut state = queue.lock();
+                    loop {
+                        if let Some(message) = state.messages.pop_front() {
+                            queue.not_full.notify_one();
+                            let unmarked = match message {
+                                Message::Flush(_) => std::mem::take(&mut state.unmarked),
+                                Message::Write(_) => 0,
+                            };
+                            break Job::Message(message, unmarked);
+                        }
+                        if state.unmarked > 0 {
+                            break Job::Mark(std::mem::take(&mut state.unmarked));
+                        }
+                        if state.shutdown {
+                            break Job::Stop;
+                        }
+                        match due_in {
+                            Some(due_in) => {
+                                let (guard, timeout) = queue
+                                    .not_empty
+                                    .wait_timeout(state, due_in)
+                                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                                state = guard;
+                                if timeout.timed_out() && state.messages.is_empty() {
+                                    break Job::FlushIfDue;
+                                }
+                            }
+                            None => {
+                                state = queue
+                                    .not_empty
+                                    .wait(state)
+                                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                            }
+                        }
+                    }
+                };
+                match job {
+                    Job::Message(Message::Write(buf), _) => {
+                        if let Err(err) = file_rotate.write_all(&buf) {
+                            error.get_or_insert(err);
+                        }
+                    }
+                    Job::Message(Message::Flush(reply), unmarked) => {
+                        if let Err(err) = mark(&mut file_rotate, &marker, unmarked) {
+                            error.get_or_insert(err);
+                        }
+                        let result = match error.take() {
+                            Some(err) => Err(err),
+                            None => file_rotate.sync().map_err(io::Error::from),
+                        };
+                        let _ = reply.send(result);
+                    }
+                    Job::Mark(unmarked) => {
+                        if let Err(err) = mark(&mut file_rotate, &marker, unmarked) {
+                            error.get_or_insert(err);
+                        }
+                    }
+                    Job::FlushIfDue => {
+                        if let Err(err) = file_rotate.flush_if_due() {
+                            error.get_or_insert(err.into());
+                        }
+                    }
+                    Job::Stop => break,
+                }
+            }
+            drop(stopped);
+        });
+        (
+            Self {
+                queue: queue.clone(),
+                policy,
+            },
+            WorkerGuard {
+                queue,
+                handle: Some(handle),
+            },
+        )
+    }
+
+    /// How many writes were discarded because the queue was full, in total.
+    pub fn dropped(&self) -> usize {
+        self.queue.lock().dropped
+    }
+}
+
+/// Write the drop marker, if there is one and writes were dropped.
+fn mark<S, W, Fs>(
+    file_rotate: &mut FileRotate<S, W, Fs>,
+    marker: &Option<DropMarkerFn>,
+    unmarked: usize,
+) -> io::Result<()>
+where
+    S: SuffixScheme,
+    W: Write,
+    Fs: FsOps,
+{
+    match marker {
+        Some(marker) if unmarked > 0 => file_rotate.write_all(&marker(unmarked)),
+        _ => Ok(()),
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the writer thread has stopped")
+}
+
+impl Write for NonBlockingFileRotate {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let queue = &*self.queue;
+        let mut state = queue.lock();
+        let mut deadline = None;
+        loop {
+            if state.stopped || state.shutdown {
+                return Err(stopped());
+            }
+            if state.messages.len() < queue.capacity {
+                break;
+            }
+            match self.policy {
+                OverflowPolicy::Block => {
+                    state = queue
+                        .not_full
+                        .wait(state)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+                OverflowPolicy::BlockTimeout(timeout) => {
+                    let now = Instant::now();
+                    let deadline = *deadline.get_or_insert(now + timeout);
+                    if now >= deadline {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!("the queue stayed full for {:?}", timeout),
+                        ));
+                    }
+                    state = queue
+                        .not_full
+                        .wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0;
+                }
+                OverflowPolicy::DropNewest => {
+                    state.dropped += 1;
+                    state.unmarked += 1;
+                    return Ok(buf.len());
+                }
+                OverflowPolicy::DropOldest => {
+                    // Flushes are never dropped, they're waited for
+                    if let Some(oldest) = state
+                        .messages
+                        .iter()
+                        .position(|message| matches!(message, Message::Write(_)))
+                    {
+                        state.messages.remove(oldest);
+                        state.dropped += 1;
+                        state.unmarked += 1;
+                    }
+                    break;
+                }
+            }
+        }
+        state.messages.push_back(Message::Write(buf.to_vec()));
+        queue.not_empty.notify_one();
+        Ok(buf.len())
+    }
+
+    /// Wait until everything queued before has been written, and [sync](FileRotate::sync) the
+    /// [FileRotate]. Flushes are queued even when the queue is full.
+    fn flush(&mut self) -> io::Result<()> {
+        let (reply, result) = mpsc::sync_channel(1);
+        {
+            let mut state = self.queue.lock();
+            if state.stopped || state.shutdown {
+                return Err(stopped());
+            }
+            state.messages.push_back(Message::Flush(reply));
+            self.queue.not_empty.notify_one();
+        }
+        result.recv().map_err(|_| stopped())?
+    }
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        self.queue.lock().shutdown = true;
+        self.queue.not_empty.notify_all();
+        self.queue.not_full.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
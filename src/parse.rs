@@ -0,0 +1,148 @@
+//! Parsing of human-readable byte sizes, durations and limits.
+
+use std::{error::Error, fmt};
+
+/// Error from parsing a human-readable byte size, duration or limit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    input: String,
+    kind: ParseErrorKind,
+}
+
+/// What was wrong with the input of a [ParseError].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The number could not be parsed.
+    InvalidNumber,
+    /// The unit after the number is not known.
+    UnknownUnit(String),
+    /// The value doesn't fit, or is negative where it can't be.
+    OutOfRange,
+    /// The input is understood, but this kind of limit isn't supported.
+    Unsupported,
+}
+
+impl ParseError {
+    pub(crate) fn new(input: &str, kind: ParseErrorKind) -> Self {
+        Self {
+            input: input.to_string(),
+            kind,
+        }
+    }
+    /// The input that failed to parse.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+    /// What was wrong with the input.
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number in {:?}", self.input),
+            ParseErrorKind::UnknownUnit(unit) => {
+                write!(f, "unknown unit {:?} in {:?}", unit, self.input)
+            }
+            ParseErrorKind::OutOfRange => write!(f, "{:?} is out of range", self.input),
+            ParseErrorKind::Unsupported => write!(f, "{:?} is not supported", self.input),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a byte size like `"100MB"`, `"1.5 GiB"` or `"512"` (bytes). Units are case-insensitive;
+/// `k`, `m`, `g` and `t` are powers of 1000, `ki`, `mi`, `gi` and `ti` powers of 1024, optionally
+/// followed by `b`.
+pub(crate) fn parse_bytes(input: &str) -> Result<usize, ParseError> {
+    let (number, unit) = split_number(input.trim());
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "ki" | "kib" => 1 << 10,
+        "m" | "mb" => 1_000_000,
+        "mi" | "mib" => 1 << 20,
+        "g" | "gb" => 1_000_000_000,
+        "gi" | "gib" => 1 << 30,
+        "t" | "tb" => 1_000_000_000_000,
+        "ti" | "tib" => 1 << 40,
+        _ => {
+            return Err(ParseError::new(
+                input,
+                ParseErrorKind::UnknownUnit(unit.to_string()),
+            ))
+        }
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| ParseError::new(input, ParseErrorKind::InvalidNumber))?;
+    let bytes = (number * multiplier as f64).round();
+    if bytes > usize::MAX as f64 {
+        return Err(ParseError::new(input, ParseErrorKind::OutOfRange));
+    }
+    Ok(bytes as usize)
+}
+
+/// Parse a duration like `"7d"`, `"12h"`, `"30 min"`, `"-15m"` or `"90"` (seconds). Units are
+/// `ms`, `s`, `m`/`min`, `h`, `d` and `w`, case-insensitive.
+#[cfg(feature = "chrono04")]
+pub(crate) fn parse_duration(input: &str) -> Result<chrono::Duration, ParseError> {
+    use chrono::Duration;
+
+    let trimmed = input.trim();
+    let (negative, rest) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let (number, unit) = split_number(rest);
+    let number = number
+        .parse::<i64>()
+        .map_err(|_| ParseError::new(input, ParseErrorKind::InvalidNumber))?;
+    let number = if negative { -number } else { number };
+    let duration = match unit.to_ascii_lowercase().as_str() {
+        "ms" => Duration::try_milliseconds(number),
+        "" | "s" => Duration::try_seconds(number),
+        "m" | "min" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        _ => {
+            return Err(ParseError::new(
+                input,
+                ParseErrorKind::UnknownUnit(unit.to_string()),
+            ))
+        }
+    };
+    duration.ok_or_else(|| ParseError::new(input, ParseErrorKind::OutOfRange))
+}
+
+/// Format a duration the way [parse_duration] reads it, in the largest unit that fits exactly.
+#[cfg(all(feature = "chrono04", feature = "serde"))]
+pub(crate) fn format_duration(duration: &chrono::Duration) -> String {
+    let millis = duration.num_milliseconds();
+    let units = [
+        ("w", 7 * 24 * 3600 * 1000),
+        ("d", 24 * 3600 * 1000),
+        ("h", 3600 * 1000),
+        ("m", 60 * 1000),
+        ("s", 1000),
+    ];
+    for (unit, size) in units.iter() {
+        if millis != 0 && millis % size == 0 {
+            return format!("{}{}", millis / size, unit);
+        }
+    }
+    format!("{}ms", millis)
+}
+
+/// Split `input` into the leading number and the (trimmed) unit after it.
+pub(crate) fn split_number(input: &str) -> (&str, &str) {
+    let end = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    (&input[..end], input[end..].trim())
+}
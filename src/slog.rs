@@ -0,0 +1,118 @@
+//! A [Drain] for [slog], writing to a [FileRotate].
+//!
+//! ```
+//! use file_rotate::{slog::FileRotateDrain, suffix::CountSuffix, ContentLimit, FileRotate};
+//! use slog::Drain;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! let log = FileRotate::new(directory.join("my-log-file"), CountSuffix::new(3), ContentLimit::Lines(100));
+//! let logger = slog::Logger::root(FileRotateDrain::new(log).ignore_res(), slog::o!("app" => "demo"));
+//! slog::info!(logger, "Hello World!"; "answer" => 42);
+//! ```
+
+use crate::{
+    fs_ops::{FsOps, RealFs},
+    suffix::SuffixScheme,
+    FileRotate,
+};
+use ::slog::{Drain, FlushError, Key, OwnedKVList, Record, Serializer, KV};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+/// Turns a record into bytes.
+pub trait RecordFormat: Send + Sync {
+    /// Append `record` and `values` (the key-values of the logger) to `buf`.
+    fn format(&self, buf: &mut Vec<u8>, record: &Record, values: &OwnedKVList) -> io::Result<()>;
+}
+
+/// The default [RecordFormat], one line per record like
+/// `2024-06-01T12:00:00.000+02:00 INFO Hello World!, answer: 42, app: demo`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LineFormat;
+
+impl RecordFormat for LineFormat {
+    fn format(&self, buf: &mut Vec<u8>, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        write!(
+            buf,
+            "{} {} {}",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+            record.level().as_str(),
+            record.msg()
+        )?;
+        let mut serializer = LineSerializer(buf);
+        record
+            .kv()
+            .serialize(record, &mut serializer)
+            .map_err(io::Error::from)?;
+        values
+            .serialize(record, &mut serializer)
+            .map_err(io::Error::from)?;
+        writeln!(buf)
+    }
+}
+
+struct LineSerializer<'a>(&'a mut Vec<u8>);
+
+impl Serializer for LineSerializer<'_> {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> ::slog::Result {
+        write!(self.0, ", {}: {}", key, val)?;
+        Ok(())
+    }
+}
+
+/// A [Drain] that formats each record (see [RecordFormat]) and writes it to a [FileRotate] in a
+/// single write, so that a record is never split across files with
+/// [SplitPolicy::LineBoundary](crate::SplitPolicy::LineBoundary),
+/// [SplitPolicy::WholeWrites](crate::SplitPolicy::WholeWrites) or
+/// [ContentLimit::BytesSurpassed](crate::ContentLimit::BytesSurpassed).
+///
+/// I/O errors are returned from [Drain::log]; use e.g. [Drain::ignore_res] or [Drain::fuse] to
+/// decide what happens to them. It can also be used behind `slog_async`.
+pub struct FileRotateDrain<S, W: Write = File, F = LineFormat, Fs: FsOps = RealFs> {
+    file_rotate: Mutex<FileRotate<S, W, Fs>>,
+    format: F,
+}
+
+impl<S: SuffixScheme, W: Write, Fs: FsOps> FileRotateDrain<S, W, LineFormat, Fs> {
+    /// Write records in the [LineFormat] to `file_rotate`.
+    pub fn new(file_rotate: FileRotate<S, W, Fs>) -> Self {
+        Self::with_format(file_rotate, LineFormat)
+    }
+}
+
+impl<S: SuffixScheme, W: Write, F: RecordFormat, Fs: FsOps> FileRotateDrain<S, W, F, Fs> {
+    /// Write records in a custom format to `file_rotate`.
+    pub fn with_format(file_rotate: FileRotate<S, W, Fs>, format: F) -> Self {
+        Self {
+            file_rotate: Mutex::new(file_rotate),
+            format,
+        }
+    }
+}
+
+impl<S: SuffixScheme, W: Write, F: RecordFormat, Fs: FsOps> Drain for FileRotateDrain<S, W, F, Fs> {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.format.format(&mut buf, record, values)?;
+        let mut file_rotate = self
+            .file_rotate
+            .lock()
+            .map_err(|_| io::Error::other("a thread panicked while logging"))?;
+        file_rotate.write_all(&buf)
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        match self.file_rotate.lock() {
+            Ok(mut file_rotate) => Ok(file_rotate.flush()?),
+            Err(_) => Err(io::Error::other("a thread panicked while logging").into()),
+        }
+    }
+}
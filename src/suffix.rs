@@ -1,105 +1,1053 @@
-use chrono::NaiveDateTime;
 #[cfg(feature = "chrono04")]
-use chrono::{offset::Local, Duration};
+use crate::clock::{Clock, SystemClock};
+use crate::template::NameTemplate;
+use crate::{
+    delete_file,
+    fs_ops::{exists, FsOps, RealFs},
+    Error,
+};
+#[cfg(feature = "chrono04")]
+use chrono::{
+    offset::{FixedOffset, Local, TimeZone},
+    DateTime, Duration,
+};
+#[cfg(feature = "chrono04")]
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+#[cfg(feature = "chrono04")]
+use std::{borrow::Cow, cmp::Ordering};
 use std::{
-    collections::VecDeque,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet, VecDeque},
+    ffi::{OsStr, OsString},
+    io,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
 };
 
 /// How to move files: How to rename, when to delete.
+///
+/// The trait is object safe, so a scheme chosen at runtime can be used as
+/// `Box<dyn SuffixScheme + Send>`, like
+/// [SuffixConfig::build](crate::config::SuffixConfig::build) does, and behaves just like the
+/// scheme itself.
 pub trait SuffixScheme {
     /// Returns new suffix to which to move current log file (does not do the move).
     /// Deletes old log files.
     /// Might also do other operations, like moving files in a cascading way.
     fn rotate(&mut self, basepath: &Path) -> String;
 
-    /// Get paths of rotated log files, in order from newest to oldest.
+    /// Get paths of rotated log files, in order from oldest to newest.
     /// Excludes the suffix-less log file.
     fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf>;
+
+    /// The string put between the base name and the suffix of rotated log files.
+    fn separator(&self) -> &str {
+        DEFAULT_SEPARATOR
+    }
+
+    /// Path of the rotated log file with `suffix`.
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        suffixed_path(basepath, self.separator(), suffix)
+    }
+
+    /// The suffix of the rotated log file named `file_name`, or None if it isn't one.
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        separated_suffix(basepath, self.separator(), file_name).map(str::to_string)
+    }
+
+    /// Whether `suffix` (as returned by [SuffixScheme::suffix_of]) is one this scheme names
+    /// rotated files with. Files with other suffixes, like `my-log-file.bak`, are reported by
+    /// [scan_report].
+    ///
+    /// `true` by default, so that no file is reported.
+    fn recognizes(&self, _suffix: &str) -> bool {
+        true
+    }
+    /// Whether the unrecognized `suffix` starts like the suffixes of this scheme, like `3.bak`
+    /// with [CountSuffix], so that it's a leftover of a rotated file rather than, say, the log
+    /// file `app.log` of another log next to `app`. Only these files are deleted by
+    /// [FileRotateBuilder::delete_unrecognized].
+    ///
+    /// `false` by default, so that no file is deleted.
+    ///
+    /// [FileRotateBuilder::delete_unrecognized]: crate::FileRotateBuilder::delete_unrecognized
+    fn resembles(&self, _suffix: &str) -> bool {
+        false
+    }
+
+    /// Failures of [SuffixScheme::rotate] that it carried on after, like an old file that couldn't
+    /// be deleted, since the last call. They are passed to [FileRotateBuilder::on_error].
+    ///
+    /// None by default.
+    ///
+    /// [FileRotateBuilder::on_error]: crate::FileRotateBuilder::on_error
+    fn take_errors(&mut self) -> Vec<Error> {
+        Vec::new()
+    }
+
+    /// Whether no rotated files are kept at all, like with `CountSuffix::new(0)`. Rotation then
+    /// empties the log file instead of moving it, and the suffix returned by
+    /// [SuffixScheme::rotate] is ignored.
+    ///
+    /// `false` by default.
+    fn discards(&self) -> bool {
+        false
+    }
+
+    /// The suffix to rename the current log file to once it's closed, given its `suffix`, for
+    /// schemes that name the current file of [RotateMechanics::DateInName] differently while it's
+    /// written, like `IntervalSuffix`. `None` if the file is finished already, so that
+    /// [OpenBehavior::Append] doesn't continue it.
+    ///
+    /// `suffix` itself, so that the file keeps its name, by default.
+    ///
+    /// [RotateMechanics::DateInName]: crate::RotateMechanics::DateInName
+    /// [OpenBehavior::Append]: crate::OpenBehavior::Append
+    fn closed_suffix(&self, _basepath: &Path, suffix: &str) -> Option<String> {
+        Some(suffix.to_string())
+    }
+
+    /// The files that [SuffixScheme::rotate] deleted since the last call. They are passed to
+    /// [FileRotate::subscribe](crate::FileRotate::subscribe).
+    ///
+    /// None by default.
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        Vec::new()
+    }
+
+    /// From now on, leave the files that [SuffixScheme::rotate] doesn't keep to the caller, who
+    /// takes them from [SuffixScheme::take_deferred] and deletes them later. They are forgotten
+    /// right away, so that they don't count against the limit. Returns whether the scheme
+    /// supports that, see [FileRotateBuilder::defer_deletions].
+    ///
+    /// Schemes that may reuse the name of a deleted file for another file, like [CountSuffix]
+    /// which moves the next file into its place, must delete right away. `false` by default.
+    ///
+    /// [FileRotateBuilder::defer_deletions]: crate::FileRotateBuilder::defer_deletions
+    fn defer_deletions(&mut self) -> bool {
+        false
+    }
+
+    /// The files left to be deleted since the last call, see [SuffixScheme::defer_deletions].
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        Vec::new()
+    }
+    /// Don't delete the rotated files at `paths` when they are over the limit, until this is
+    /// called again without them, e.g. while they are still uploaded. They still count against
+    /// the limit. Returns whether the scheme supports that, see
+    /// [FileRotateBuilder::on_rotated].
+    ///
+    /// Schemes that rename the kept files, like [CountSuffix], report where they moved them in
+    /// [SuffixScheme::take_moved]. `false` by default.
+    ///
+    /// [FileRotateBuilder::on_rotated]: crate::FileRotateBuilder::on_rotated
+    fn protect(&mut self, _paths: &[PathBuf]) -> bool {
+        false
+    }
+    /// The files of [SuffixScheme::protect] that [SuffixScheme::rotate] renamed since the last
+    /// call, from where to where. None by default.
+    fn take_moved(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        Vec::new()
+    }
+    /// Scan, rename and delete the rotated files through `fs`, the operations of
+    /// [FileRotateBuilder::fs_ops], which [FileRotate] passes when it is built. The built-in
+    /// schemes use [RealFs] until then; others may ignore it, which they do by default.
+    ///
+    /// [FileRotate]: crate::FileRotate
+    /// [FileRotateBuilder::fs_ops]: crate::FileRotateBuilder::fs_ops
+    fn use_fs_ops(&mut self, _fs: Arc<dyn FsOps>) {}
+}
+
+impl<S: SuffixScheme + ?Sized> SuffixScheme for Box<S> {
+    fn rotate(&mut self, basepath: &Path) -> String {
+        (**self).rotate(basepath)
+    }
+    fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
+        (**self).log_paths(basepath)
+    }
+    fn separator(&self) -> &str {
+        (**self).separator()
+    }
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        (**self).rotated_path(basepath, suffix)
+    }
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        (**self).suffix_of(basepath, file_name)
+    }
+    fn recognizes(&self, suffix: &str) -> bool {
+        (**self).recognizes(suffix)
+    }
+    fn resembles(&self, suffix: &str) -> bool {
+        (**self).resembles(suffix)
+    }
+    fn take_errors(&mut self) -> Vec<Error> {
+        (**self).take_errors()
+    }
+    fn discards(&self) -> bool {
+        (**self).discards()
+    }
+    fn closed_suffix(&self, basepath: &Path, suffix: &str) -> Option<String> {
+        (**self).closed_suffix(basepath, suffix)
+    }
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        (**self).take_deleted()
+    }
+    fn defer_deletions(&mut self) -> bool {
+        (**self).defer_deletions()
+    }
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        (**self).take_deferred()
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        (**self).protect(paths)
+    }
+    fn take_moved(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        (**self).take_moved()
+    }
+    fn use_fs_ops(&mut self, fs: Arc<dyn FsOps>) {
+        (**self).use_fs_ops(fs)
+    }
+}
+
+/// A file deleted by a suffix scheme, see [SuffixScheme::take_deleted].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deleted {
+    /// The file
+    pub path: PathBuf,
+    /// Why it wasn't kept, like `"numbered 4 or higher"`
+    pub reason: String,
+}
+
+/// The files a suffix scheme deleted, or left to be deleted, see [SuffixScheme::defer_deletions].
+#[derive(Default)]
+struct Deletions {
+    done: Vec<Deleted>,
+    /// Some if deletions are deferred
+    deferred: Option<Vec<Deleted>>,
+    /// The files not to delete, see [SuffixScheme::protect]
+    protected: HashSet<PathBuf>,
+}
+
+impl Deletions {
+    fn defer(&mut self) -> bool {
+        self.deferred.get_or_insert_with(Vec::new);
+        true
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        self.protected = paths.iter().cloned().collect();
+        true
+    }
+    fn take_done(&mut self) -> Vec<Deleted> {
+        std::mem::take(&mut self.done)
+    }
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        self.deferred
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+/// The separator used between the base name and the suffix, unless configured otherwise.
+pub const DEFAULT_SEPARATOR: &str = ".";
+
+/// `basepath` with `{separator}{suffix}` appended to its file name.
+///
+/// The path is built from `OsString`s so that a base path which isn't valid UTF-8 is kept intact.
+pub(crate) fn suffixed_path(basepath: &Path, separator: &str, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(basepath.as_os_str());
+    path.push(separator);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// The suffix of the file named `{basename}{separator}{suffix}`, or None if `file_name` isn't
+/// named like that.
+///
+/// File names are matched against the base name as raw bytes, so files with names that are not
+/// valid UTF-8 never get mangled. Only the suffix, which the caller has to parse, needs to be
+/// valid UTF-8.
+fn separated_suffix<'a>(basepath: &Path, separator: &str, file_name: &'a OsStr) -> Option<&'a str> {
+    let filename_prefix = basepath
+        .file_name()
+        .expect("basepath.file_name()")
+        .as_encoded_bytes();
+    // Other files sharing the prefix, like `log2.1` next to `log`, are not ours. This also skips
+    // the current (suffix-less) log file.
+    let suffix = file_name
+        .as_encoded_bytes()
+        .strip_prefix(filename_prefix)?
+        .strip_prefix(separator.as_bytes())?;
+    std::str::from_utf8(suffix).ok()
+}
+
+/// How rotated log files are named after the log file.
+#[derive(Clone, Debug)]
+enum Naming {
+    /// `{basename}{separator}{suffix}`
+    Separator(String),
+    Template(NameTemplate),
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Naming::Separator(DEFAULT_SEPARATOR.to_string())
+    }
+}
+
+impl Naming {
+    fn path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        match self {
+            Naming::Separator(separator) => suffixed_path(basepath, separator, suffix),
+            Naming::Template(template) => template.path(basepath, suffix),
+        }
+    }
+    fn suffix(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        self.matcher(basepath).suffix(file_name).map(str::to_string)
+    }
+    /// Work out once what the names of rotated files look like, to check many file names.
+    fn matcher<'a>(&self, basepath: &'a Path) -> Matcher<'a> {
+        let basename = basepath.file_name().expect("basepath.file_name()");
+        let (before, after) = match self {
+            Naming::Separator(separator) => {
+                let mut before = basename.to_os_string();
+                before.push(separator);
+                (before, OsString::new())
+            }
+            Naming::Template(template) => template.around_suffix(basepath),
+        };
+        Matcher {
+            basename,
+            before,
+            after,
+        }
+    }
+    /// The separator, which templates don't have, but e.g. [TimestampSuffix::with_origin] needs.
+    fn separator(&self) -> &str {
+        match self {
+            Naming::Separator(separator) => separator,
+            Naming::Template(_) => DEFAULT_SEPARATOR,
+        }
+    }
+}
+
+/// Finds the suffix in the names of rotated files, see [Naming::matcher].
+struct Matcher<'a> {
+    basename: &'a OsStr,
+    /// What the file names of rotated files start with
+    before: OsString,
+    /// What the file names of rotated files end with
+    after: OsString,
+}
+
+impl Matcher<'_> {
+    /// The suffix in `file_name`, if it is the name of a rotated file.
+    ///
+    /// File names are matched as raw bytes, so files with names that are not valid UTF-8 never get
+    /// mangled. Only the suffix, which the caller has to parse, needs to be valid UTF-8.
+    fn suffix<'b>(&self, file_name: &'b OsStr) -> Option<&'b str> {
+        if file_name == self.basename {
+            return None;
+        }
+        let suffix = file_name
+            .as_encoded_bytes()
+            .strip_prefix(self.before.as_encoded_bytes())?
+            .strip_suffix(self.after.as_encoded_bytes())?;
+        match std::str::from_utf8(suffix) {
+            Ok(suffix) if !suffix.is_empty() => Some(suffix),
+            _ => None,
+        }
+    }
+}
+
+/// The directory of `basepath`, `.` for a bare file name like `app.log`. The path isn't made
+/// absolute, since there may be no meaningful current directory, as with the preopened
+/// directories of WASI.
+pub(crate) fn directory(basepath: &Path) -> &Path {
+    match basepath.parent().expect("basepath.parent()") {
+        parent if parent.as_os_str().is_empty() => Path::new("."),
+        parent => parent,
+    }
+}
+
+/// Like [scan_suffixes], but yields the suffixes while reading the directory, so that a directory
+/// with a huge number of files is never held in memory at once.
+fn iter_suffixes<'a>(
+    fs: &dyn FsOps,
+    basepath: &'a Path,
+    naming: &Naming,
+) -> impl Iterator<Item = String> + 'a {
+    let parent = directory(basepath);
+    let entries = fs.read_dir(parent).into_iter().flatten();
+    let matcher = naming.matcher(basepath);
+    entries.filter_map(move |entry| {
+        let entry = entry.ok()?;
+        let file_name = entry.file_name();
+        let suffix = matcher.suffix(&file_name)?;
+        // Only look at the file type of our own files, since that may take a system call
+        if is_file(&entry) {
+            Some(suffix.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Scan the directory of `basepath` and return the suffixes of all files named after it according
+/// to `naming`. Files whose suffix isn't valid UTF-8 are skipped. If the directory can't be read,
+/// nothing is found.
+fn scan_suffixes(fs: &dyn FsOps, basepath: &Path, naming: &Naming) -> Vec<String> {
+    let suffixes = iter_suffixes(fs, basepath, naming).collect::<Vec<_>>();
+    event!(
+        Debug,
+        "found {} rotated files of {}",
+        suffixes.len(),
+        basepath.display()
+    );
+    suffixes
+}
+
+/// The `keep` greatest of `items` in ascending order. The others are passed to `evict` as soon as
+/// `keep` greater ones are seen, so that no more than `keep + 1` items are held at once.
+fn keep_greatest<T: Ord>(
+    items: impl Iterator<Item = T>,
+    keep: usize,
+    mut evict: impl FnMut(T),
+) -> Vec<T> {
+    let mut heap = BinaryHeap::new();
+    for item in items {
+        heap.push(Reverse(item));
+        if heap.len() > keep {
+            evict(heap.pop().unwrap().0);
+        }
+    }
+    // Sorted ascending by `Reverse`, i.e. descending by item
+    let mut items = heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(item)| item)
+        .collect::<Vec<_>>();
+    items.reverse();
+    items
+}
+
+/// All items of `items` with the greatest `key`, in the order they came, found in a single pass.
+#[cfg(feature = "chrono04")]
+fn all_greatest<T, K: Ord>(items: impl Iterator<Item = T>, key: impl Fn(&T) -> K) -> Vec<T> {
+    let mut greatest: Option<K> = None;
+    let mut found = Vec::new();
+    for item in items {
+        let item_key = key(&item);
+        match greatest.as_ref().map(|greatest| item_key.cmp(greatest)) {
+            Some(Ordering::Less) => continue,
+            Some(Ordering::Equal) => {}
+            None | Some(Ordering::Greater) => {
+                greatest = Some(item_key);
+                found.clear();
+            }
+        }
+        found.push(item);
+    }
+    found
+}
+
+/// Whether `entry` is a file, or a symbolic link to one.
+fn is_file(entry: &std::fs::DirEntry) -> bool {
+    // The type of the entry itself doesn't follow symbolic links
+    match entry.file_type() {
+        Ok(file_type) if file_type.is_symlink() => entry.path().is_file(),
+        Ok(file_type) => file_type.is_file(),
+        Err(_) => entry.path().is_file(),
+    }
+}
+
+/// A rotated log file found by [scan].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScannedFile {
+    /// Full path of the file
+    pub path: PathBuf,
+    /// What follows the base name and separator, e.g. `"3"` or `"20210825T151133"`
+    pub suffix: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification time
+    pub modified: SystemTime,
+}
+
+/// List the rotated log files of `basepath` that `scheme` knows about, newest first.
+///
+/// This only reads the directory, so it can be used by other tools to inspect the log files of an
+/// application without creating any file. Use a fresh `scheme`, configured like the one of the
+/// application, since schemes may remember the files they have seen. With
+/// [crate::FileRotateBuilder::archive_dir], `basepath` is the file name in the archive directory.
+///
+/// ```
+/// use file_rotate::suffix::{scan, CountSuffix};
+/// # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+/// # let log_path = directory.path().join("my-log-file");
+/// let total: u64 = scan(&log_path, &mut CountSuffix::new(10))
+///     .unwrap()
+///     .iter()
+///     .map(|file| file.size)
+///     .sum();
+/// ```
+pub fn scan<S: SuffixScheme + ?Sized>(
+    basepath: &Path,
+    scheme: &mut S,
+) -> io::Result<Vec<ScannedFile>> {
+    scan_with(&RealFs, basepath, scheme)
+}
+
+/// [scan] through `fs`.
+pub(crate) fn scan_with<S: SuffixScheme + ?Sized>(
+    fs: &dyn FsOps,
+    basepath: &Path,
+    scheme: &mut S,
+) -> io::Result<Vec<ScannedFile>> {
+    let parent = directory(basepath);
+    // Report an unreadable directory, which `log_paths` treats as empty
+    fs.read_dir(parent)?;
+    let mut files = Vec::new();
+    for path in scheme.log_paths(basepath).into_iter().rev() {
+        // A custom scheme may list paths that don't have a suffix of its own
+        let suffix = match path
+            .file_name()
+            .and_then(|file_name| scheme.suffix_of(basepath, file_name))
+        {
+            Some(suffix) => suffix,
+            None => continue,
+        };
+        let metadata = match fs.metadata(&path) {
+            Ok(metadata) => metadata,
+            // Deleted since, e.g. by a rotation
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        files.push(ScannedFile {
+            suffix,
+            size: metadata.len(),
+            modified: metadata.modified()?,
+            path,
+        });
+    }
+    Ok(files)
+}
+
+/// The files next to the log file, found by [scan_report].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanReport {
+    /// The rotated log files, newest first, like [scan] lists them
+    pub recognized: Vec<ScannedFile>,
+    /// Files named like rotated log files, but with a suffix the scheme doesn't recognize (see
+    /// [SuffixScheme::recognizes]), like `my-log-file.bak` or files of an older naming scheme.
+    /// They don't count against the limit of the scheme and are never deleted by it.
+    pub unrecognized: Vec<PathBuf>,
+}
+
+/// Like [scan], but also list the files that are named like rotated log files, but that `scheme`
+/// doesn't recognize.
+pub fn scan_report<S: SuffixScheme + ?Sized>(
+    basepath: &Path,
+    scheme: &mut S,
+) -> io::Result<ScanReport> {
+    scan_report_with(&RealFs, basepath, scheme)
+}
+
+/// [scan_report] through `fs`.
+pub(crate) fn scan_report_with<S: SuffixScheme + ?Sized>(
+    fs: &dyn FsOps,
+    basepath: &Path,
+    scheme: &mut S,
+) -> io::Result<ScanReport> {
+    Ok(ScanReport {
+        recognized: scan_with(fs, basepath, scheme)?,
+        unrecognized: unrecognized(fs, basepath, scheme),
+    })
+}
+
+/// The files of [ScanReport::unrecognized], sorted by path. If the directory can't be read,
+/// nothing is found.
+pub(crate) fn unrecognized<S: SuffixScheme + ?Sized>(
+    fs: &dyn FsOps,
+    basepath: &Path,
+    scheme: &S,
+) -> Vec<PathBuf> {
+    let parent = directory(basepath);
+    let mut paths = Vec::new();
+    let entries = match fs.read_dir(parent) {
+        Ok(entries) => entries,
+        Err(_) => return paths,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        match scheme.suffix_of(basepath, &entry.file_name()) {
+            Some(suffix) if !scheme.recognizes(&suffix) && is_file(&entry) => {
+                paths.push(entry.path())
+            }
+            _ => {}
+        }
+    }
+    paths.sort();
+    paths
+}
+
+/// Whether `suffix` starts like a number, like `3.bak`, see [SuffixScheme::resembles].
+fn starts_with_digit(suffix: &str) -> bool {
+    suffix.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Whether `suffix` starts with a timestamp that `parses`, like `20240601T120000.bak`, see
+/// [SuffixScheme::resembles].
+#[cfg(feature = "chrono04")]
+fn starts_with_timestamp(suffix: &str, parses: impl Fn(&str) -> bool) -> bool {
+    suffix
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .skip(1)
+        .chain(std::iter::once(suffix.len()))
+        .any(|end| parses(&suffix[..end]))
+}
+
+/// The number of a [CountSuffix] or [SequenceSuffix] file. Only what `usize::to_string` renders
+/// is accepted, so that the file is found again under the same name: No sign, no leading zeros and
+/// nothing too big for `usize`. Other suffixes are reported by [scan_report].
+fn parse_number(suffix: &str) -> Option<usize> {
+    if !suffix.bytes().all(|b| b.is_ascii_digit()) || suffix.len() > 1 && suffix.starts_with('0') {
+        return None;
+    }
+    suffix.parse().ok()
+}
+
+/// `suffix` without leading zeros.
+fn unpadded(suffix: &str) -> &str {
+    match suffix.trim_start_matches('0') {
+        "" if !suffix.is_empty() => "0",
+        unpadded => unpadded,
+    }
+}
+
+/// Delete the rotated file at `path`, which isn't kept because of `reason`, or leave it to the
+/// caller if deletions are deferred. Protected files are left alone.
+fn delete_rotated(
+    fs: &dyn FsOps,
+    path: &Path,
+    reason: std::fmt::Arguments,
+    errors: &mut Vec<Error>,
+    deleted: &mut Deletions,
+) {
+    if deleted.protected.contains(path) {
+        event!(Debug, "keeping protected {} ({})", path.display(), reason);
+        return;
+    }
+    if let Some(ref mut deferred) = deleted.deferred {
+        event!(
+            Debug,
+            "deferring deletion of {} ({})",
+            path.display(),
+            reason
+        );
+        deferred.push(Deleted {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        });
+        return;
+    }
+    match delete_file(fs, path) {
+        Ok(()) => {
+            event!(Info, "deleted {} ({})", path.display(), reason);
+            deleted.done.push(Deleted {
+                path: path.to_path_buf(),
+                reason: reason.to_string(),
+            });
+        }
+        Err(err) => errors.push(err),
+    }
+}
+
+/// Rename zero-padded numbered files like `log.01` to `log.1`, so that they are rotated and
+/// deleted like the others. Files whose unpadded name is taken are left alone.
+fn renumber_padded(fs: &dyn FsOps, basepath: &Path, naming: &Naming, errors: &mut Vec<Error>) {
+    // A renamed file may be seen again under its new name, which is left alone then
+    for suffix in iter_suffixes(fs, basepath, naming) {
+        let unpadded = unpadded(&suffix);
+        if unpadded == suffix || parse_number(unpadded).is_none() {
+            continue;
+        }
+        let to = naming.path(basepath, unpadded);
+        if !exists(fs, &to) {
+            let from = naming.path(basepath, &suffix);
+            if let Err(source) = fs.rename(&from, &to) {
+                errors.push(Error::Rotate { from, to, source });
+            }
+        }
+    }
 }
 
 /// Rotated log files get a number as suffix. The greater the number, the older. The oldest files
 /// are deleted.
+///
+/// Files with zero-padded numbers, like `log.01`, are renamed to the plain number at the next
+/// rotation. Numbers with a sign or too big for `usize` are not recognized.
+///
+/// If a rotation was interrupted, e.g. because the process was killed, the numbering may have a
+/// gap. Rotation only moves the files up to the gap, so none is overwritten, and the gap is filled
+/// by the next rotations.
 pub struct CountSuffix {
     max_files: usize,
+    base: usize,
+    naming: Naming,
+    errors: Vec<Error>,
+    deleted: Deletions,
+    /// The file system operations, see [SuffixScheme::use_fs_ops]
+    fs: Arc<dyn FsOps>,
+    /// Protected files renamed by the cascade, see [SuffixScheme::take_moved]
+    moved: Vec<(PathBuf, PathBuf)>,
 }
 
 impl CountSuffix {
-    /// New CountSuffix
+    /// New CountSuffix, numbering the files from 1. `max_files` rotated files are kept, e.g.
+    /// `log.1` to `log.3` with 3. With 0, rotation empties the log file and keeps nothing.
     pub fn new(max_files: usize) -> Self {
-        Self { max_files }
+        Self::with_base(max_files, 1)
+    }
+    /// New CountSuffix, numbering the files from `base`, e.g. 0 for `log.0`, `log.1`, ... Still
+    /// `max_files` rotated files are kept.
+    pub fn with_base(max_files: usize, base: usize) -> Self {
+        Self {
+            max_files,
+            base,
+            naming: Naming::default(),
+            errors: Vec::new(),
+            deleted: Deletions::default(),
+            fs: Arc::new(RealFs),
+            moved: Vec::new(),
+        }
+    }
+    /// Use `separator` instead of `"."` between the base name and the number.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        assert!(!separator.is_empty());
+        self.naming = Naming::Separator(separator.to_string());
+        self
+    }
+    /// Name the rotated files after `template` instead of appending the separator and suffix.
+    pub fn with_template(mut self, template: NameTemplate) -> Self {
+        self.naming = Naming::Template(template);
+        self
+    }
+    /// Make sure that path(count) does not exist, by moving it to path(count+1).
+    fn cascade(&mut self, basepath: &Path, count: usize, end: usize) {
+        let fs = &*self.fs;
+        let src = self.naming.path(basepath, &count.to_string());
+        if !exists(fs, &src) {
+            return;
+        }
+        let dest = self.naming.path(basepath, &(count + 1).to_string());
+        if exists(fs, &dest) {
+            self.cascade(basepath, count + 1, end);
+        }
+        let fs = &*self.fs;
+        let deleted = &mut self.deleted;
+        let protected = deleted.protected.contains(&src);
+        if count + 1 >= end && !protected {
+            // If the file is too old (too big count), delete it,
+            //   (also if count + 1 == end, because then the file would be moved
+            //   to .end, past the last file that is kept)
+            delete_rotated(
+                fs,
+                &src,
+                format_args!("numbered {} or higher", end),
+                &mut self.errors,
+                deleted,
+            );
+        } else if !exists(fs, &dest) {
+            // otherwise, rename it, protected files also past the last file that is
+            // kept. If `dest` couldn't be moved away, leave both alone rather than
+            // overwrite it.
+            match fs.rename(&src, &dest) {
+                Ok(()) if protected => {
+                    deleted.protected.remove(&src);
+                    deleted.protected.insert(dest.clone());
+                    self.moved.push((src, dest));
+                }
+                Ok(()) => {}
+                Err(source) => self.errors.push(Error::Rotate {
+                    from: src,
+                    to: dest,
+                    source,
+                }),
+            }
+        }
     }
 }
 
 impl SuffixScheme for CountSuffix {
     fn rotate(&mut self, basepath: &Path) -> String {
-        /// Make sure that path(count) does not exist, by moving it to path(count+1).
-        fn cascade(basepath: &Path, count: usize, max_files: usize) {
-            let src = PathBuf::from(format!("{}.{}", basepath.display(), count));
-            if src.exists() {
-                let dest = PathBuf::from(format!("{}.{}", basepath.display(), count + 1));
-                if dest.exists() {
-                    cascade(basepath, count + 1, max_files);
-                }
-                if count >= max_files {
-                    // If the file is too old (too big count), delete it,
-                    //   (also if count == max_files, because then the .(max_files-1) file will be moved
-                    //   to .max_files)
-                    let _ = std::fs::remove_file(&src).unwrap();
-                } else {
-                    // otherwise, rename it.
-                    let _ = std::fs::rename(src, dest);
+        // Files are numbered `base..end`
+        let end = self.base + self.max_files;
+        renumber_padded(&*self.fs, basepath, &self.naming, &mut self.errors);
+        self.cascade(basepath, self.base, end);
+
+        // The cascade stops at the first gap in the numbering, so files beyond `max_files` that it
+        // didn't reach (e.g. because `max_files` was reduced) are deleted here.
+        for suffix in iter_suffixes(&*self.fs, basepath, &self.naming) {
+            if let Some(n) = parse_number(&suffix) {
+                if n >= end {
+                    let path = self.naming.path(basepath, &suffix);
+                    delete_rotated(
+                        &*self.fs,
+                        &path,
+                        format_args!("numbered {} or higher", end),
+                        &mut self.errors,
+                        &mut self.deleted,
+                    );
                 }
             }
         }
-        cascade(basepath, 1, self.max_files);
-        "1".to_string()
+        self.base.to_string()
     }
     fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
-        let filename_prefix = &*basepath
-            .file_name()
-            .expect("basepath.file_name()")
-            .to_string_lossy();
-        let filenames = std::fs::read_dir(basepath.parent().expect("basepath.parent()"))
-            .unwrap()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.path().is_file())
-            .map(|entry| entry.file_name());
-        let mut numbers = Vec::new();
-        for filename in filenames {
-            let filename = filename.to_string_lossy();
-            if !filename.starts_with(&filename_prefix) {
-                continue;
-            }
-            if let Some(dot) = filename.find('.') {
-                let suffix = &filename[(dot + 1)..];
-                if let Ok(n) = suffix.parse::<usize>() {
-                    numbers.push(n);
-                } else {
-                    continue;
-                }
-            } else {
-                // We don't consider the current (suffix-less) log file.
-            }
-        }
+        let mut numbers = scan_suffixes(&*self.fs, basepath, &self.naming)
+            .iter()
+            .filter_map(|suffix| parse_number(suffix))
+            .collect::<Vec<_>>();
         // Sort descending - the largest numbers are the oldest and thus should come first
         numbers.sort_by(|x, y| y.cmp(x));
         numbers
             .iter()
-            .map(|n| PathBuf::from(format!("{}.{}", basepath.display(), n)))
+            .map(|n| self.naming.path(basepath, &n.to_string()))
+            .collect::<Vec<_>>()
+    }
+    fn separator(&self) -> &str {
+        self.naming.separator()
+    }
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        self.naming.path(basepath, suffix)
+    }
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        self.naming.suffix(basepath, file_name)
+    }
+    fn recognizes(&self, suffix: &str) -> bool {
+        parse_number(unpadded(suffix)).is_some()
+    }
+    fn resembles(&self, suffix: &str) -> bool {
+        starts_with_digit(suffix)
+    }
+    fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        self.deleted.take_done()
+    }
+    fn discards(&self) -> bool {
+        self.max_files == 0
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        self.deleted.protect(paths)
+    }
+    fn use_fs_ops(&mut self, fs: Arc<dyn FsOps>) {
+        self.fs = fs;
+    }
+    fn take_moved(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        std::mem::take(&mut self.moved)
+    }
+}
+
+/// Rotated log files get a number as suffix, like with [CountSuffix], but the greater the number,
+/// the newer. The oldest files are deleted.
+///
+/// Rotating with [CountSuffix] renames every existing file to make room for `.1`, which costs
+/// `max_files` renames per rotation. Here each rotation costs exactly one rename (the current log
+/// file to the next number) plus at most one deletion, at the price of `.1` not being the newest
+/// file. Numbers are never reused.
+///
+/// Like with [CountSuffix], zero-padded numbers are renamed to the plain number, and numbers with a
+/// sign or too big for `usize` are not recognized.
+pub struct SequenceSuffix {
+    max_files: usize,
+    naming: Naming,
+    /// None means that we don't know the files, and a scan is necessary.
+    numbers: Option<VecDeque<usize>>,
+    errors: Vec<Error>,
+    deleted: Deletions,
+    /// The file system operations, see [SuffixScheme::use_fs_ops]
+    fs: Arc<dyn FsOps>,
+}
+
+impl SequenceSuffix {
+    /// New SequenceSuffix, keeping `max_files` rotated files. With 0, rotation empties the log file
+    /// and keeps nothing.
+    pub fn new(max_files: usize) -> Self {
+        Self {
+            max_files,
+            naming: Naming::default(),
+            numbers: None,
+            errors: Vec::new(),
+            deleted: Deletions::default(),
+            fs: Arc::new(RealFs),
+        }
+    }
+    /// Use `separator` instead of `"."` between the base name and the number.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        assert!(!separator.is_empty());
+        self.naming = Naming::Separator(separator.to_string());
+        self
+    }
+    /// Name the rotated files after `template` instead of appending the separator and suffix.
+    pub fn with_template(mut self, template: NameTemplate) -> Self {
+        self.naming = Naming::Template(template);
+        self
+    }
+    /// Scan files in the log directory to construct the list of files
+    fn ensure_number_list(&mut self, basepath: &Path) {
+        if self.numbers.is_none() {
+            let mut numbers = scan_suffixes(&*self.fs, basepath, &self.naming)
+                .iter()
+                .filter_map(|suffix| parse_number(suffix))
+                .collect::<Vec<_>>();
+            // Sort ascending - the smallest numbers are the oldest
+            numbers.sort_unstable();
+            self.numbers = Some(numbers.into());
+        }
+    }
+    /// Like [SequenceSuffix::ensure_number_list], but only keeps the newest `max_files` numbers
+    /// and deletes the older files right away, which rotation would do anyway.
+    fn ensure_newest_numbers(&mut self, basepath: &Path) {
+        if self.numbers.is_none() {
+            let max_files = self.max_files;
+            let naming = &self.naming;
+            let fs = &*self.fs;
+            let errors = &mut self.errors;
+            let deleted = &mut self.deleted;
+            let numbers = keep_greatest(
+                iter_suffixes(fs, basepath, naming).filter_map(|suffix| parse_number(&suffix)),
+                max_files,
+                |oldest| {
+                    let path = naming.path(basepath, &oldest.to_string());
+                    delete_rotated(
+                        fs,
+                        &path,
+                        format_args!("more than {} files", max_files),
+                        errors,
+                        deleted,
+                    );
+                },
+            );
+            self.numbers = Some(numbers.into());
+        }
+    }
+}
+
+impl SuffixScheme for SequenceSuffix {
+    fn rotate(&mut self, basepath: &Path) -> String {
+        if self.numbers.is_none() {
+            renumber_padded(&*self.fs, basepath, &self.naming, &mut self.errors);
+        }
+        self.ensure_newest_numbers(basepath);
+        let numbers = self.numbers.as_mut().unwrap();
+        let next = numbers.back().map(|n| n + 1).unwrap_or(1);
+        numbers.push_back(next);
+        while numbers.len() > self.max_files {
+            let oldest = numbers.pop_front().unwrap();
+            let path = self.naming.path(basepath, &oldest.to_string());
+            delete_rotated(
+                &*self.fs,
+                &path,
+                format_args!("more than {} files", self.max_files),
+                &mut self.errors,
+                &mut self.deleted,
+            );
+        }
+        next.to_string()
+    }
+    fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
+        self.ensure_number_list(basepath);
+        self.numbers
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|n| self.naming.path(basepath, &n.to_string()))
             .collect::<Vec<_>>()
     }
+    fn separator(&self) -> &str {
+        self.naming.separator()
+    }
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        self.naming.path(basepath, suffix)
+    }
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        self.naming.suffix(basepath, file_name)
+    }
+    fn recognizes(&self, suffix: &str) -> bool {
+        parse_number(unpadded(suffix)).is_some()
+    }
+    fn resembles(&self, suffix: &str) -> bool {
+        starts_with_digit(suffix)
+    }
+    fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        self.deleted.take_done()
+    }
+    fn defer_deletions(&mut self) -> bool {
+        self.deleted.defer()
+    }
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        self.deleted.take_deferred()
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        self.deleted.protect(paths)
+    }
+    fn use_fs_ops(&mut self, fs: Arc<dyn FsOps>) {
+        self.fs = fs;
+    }
+    fn discards(&self) -> bool {
+        self.max_files == 0
+    }
 }
 
+/// If several files are rotated at the same timestamp, `.1` (and up) is appended to the timestamp.
+/// This number is always separated by `"."`, regardless of the configured separator.
+///
+/// Sub-second fields in `format`, like `%3f` or `%.6f`, make such collisions unlikely.
+///
+/// If the clock goes backwards, e.g. when it's adjusted or when daylight saving time ends with a
+/// format without offset, the timestamp of the newest file is reused with such a number, so that
+/// the new file doesn't look older than the existing ones.
+///
+/// With [TimestampSuffix::with_origin], the timestamp is preceded by fields like the host name, so
+/// that files from different hosts don't collide.
+///
+/// Formats with a time zone offset, like `"%Y-%m-%dT%H:%M:%S%z"`, make the files self-describing.
+/// Their files are ordered by the point in time, so that a change of the offset (e.g. daylight
+/// saving time) doesn't mix up the order. Files without the offset, e.g. from before it was added to
+/// the format, are still found and taken to be in local time.
+///
+/// Files are ordered by their parsed time rather than the suffix as text, so files named after
+/// earlier formats (see [TimestampSuffix::with_previous_formats]) take their right place.
+///
 /// Current limitations:
-///  - Neither `format` or the base filename can include the character `"."`.
-///  - The `format` should ensure that the lexical and chronological orderings are the same
+///  - `format` can't include the separator, unless it is `"."`.
 #[cfg(feature = "chrono04")]
 pub struct TimestampSuffix {
-    /// None means that we don't know the files, and a scan is necessary.
-    pub(crate) suffixes: Option<VecDeque<(String, Option<usize>)>>,
-    format: &'static str,
+    /// Timestamp, number and origin of the files. None means that we don't know the files, and a
+    /// scan is necessary.
+    pub(crate) suffixes: Option<VecDeque<(String, Option<usize>, String)>>,
+    /// `suffixes` only has the newest files, which is all that rotation needs with
+    /// [FileLimit::Unlimited].
+    only_newest: bool,
+    format: Cow<'static, str>,
+    previous_formats: Vec<String>,
     file_limit: FileLimit,
+    naming: Naming,
+    date_from: DateFrom,
+    clock: Box<dyn Clock>,
+    origin: Vec<String>,
+    only_own_origin: bool,
+    errors: Vec<Error>,
+    deleted: Deletions,
+    /// The file system operations, see [SuffixScheme::use_fs_ops]
+    fs: Arc<dyn FsOps>,
 }
 
 #[cfg(feature = "chrono04")]
@@ -108,95 +1056,258 @@ impl TimestampSuffix {
     pub fn default(file_limit: FileLimit) -> Self {
         Self {
             suffixes: None,
-            format: "%Y%m%dT%H%M%S",
+            only_newest: false,
+            format: "%Y%m%dT%H%M%S".into(),
+            previous_formats: Vec::new(),
             file_limit,
+            naming: Naming::default(),
+            date_from: DateFrom::Now,
+            clock: Box::new(SystemClock),
+            origin: Vec::new(),
+            only_own_origin: false,
+            errors: Vec::new(),
+            deleted: Deletions::default(),
+            fs: Arc::new(RealFs),
         }
     }
     /// Create new TimestampSuffix suffix scheme
-    pub fn with_format(format: &'static str, file_limit: FileLimit) -> Self {
+    ///
+    /// `format` isn't checked here, use [TimestampSuffix::try_with_format] for a format that isn't
+    /// known to work.
+    pub fn with_format<F: Into<Cow<'static, str>>>(format: F, file_limit: FileLimit) -> Self {
         Self {
             suffixes: None,
-            format,
+            only_newest: false,
+            format: format.into(),
+            previous_formats: Vec::new(),
             file_limit,
+            naming: Naming::default(),
+            date_from: DateFrom::Now,
+            clock: Box::new(SystemClock),
+            origin: Vec::new(),
+            only_own_origin: false,
+            errors: Vec::new(),
+            deleted: Deletions::default(),
+            fs: Arc::new(RealFs),
+        }
+    }
+    /// Like [TimestampSuffix::with_format], but checks that `format` can be used: It must be
+    /// valid, render to something that sorts chronologically, can be parsed back and doesn't
+    /// contain a path separator.
+    ///
+    /// ISO weeks like `"%G-W%V"` can be used, a week without a weekday is taken as its Monday.
+    pub fn try_with_format<F: Into<Cow<'static, str>>>(
+        format: F,
+        file_limit: FileLimit,
+    ) -> Result<Self, FormatError> {
+        let format = format.into();
+        check_format(&format)?;
+        Ok(Self::with_format(format, file_limit))
+    }
+    /// Also recognize files named after `formats`, e.g. after changing the format from `"%Y%m%d"`
+    /// to `"%Y%m%dT%H%M%S"`. They are ordered by time among the other files and count against
+    /// the [FileLimit], but new files always get the current format.
+    pub fn with_previous_formats<I: IntoIterator<Item = String>>(mut self, formats: I) -> Self {
+        self.previous_formats = formats.into_iter().collect();
+        self.suffixes = None;
+        self
+    }
+    /// Choose which point in time the timestamp of a rotated file is taken from.
+    pub fn with_date_from(mut self, date_from: DateFrom) -> Self {
+        self.date_from = date_from;
+        self
+    }
+    /// Use `separator` instead of `"."` between the base name and the timestamp.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        assert!(!separator.is_empty());
+        debug_assert!(
+            !renders_separator(&self.format, separator),
+            "the timestamp format {:?} renders the separator {:?}",
+            self.format,
+            separator
+        );
+        self.naming = Naming::Separator(separator.to_string());
+        self
+    }
+    /// Name the rotated files after `template` instead of appending the separator and suffix.
+    pub fn with_template(mut self, template: NameTemplate) -> Self {
+        self.naming = Naming::Template(template);
+        self
+    }
+    /// Take the current time from `clock` instead of the system clock.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+    /// Put `fields`, e.g. the host name and process id, between the separator and the timestamp.
+    ///
+    /// ```
+    /// # use file_rotate::suffix::{FileLimit, TimestampSuffix};
+    /// // Rotates to e.g. `my-log-file.web-03.12345.20240601T120000`
+    /// let suffix = TimestampSuffix::default(FileLimit::MaxFiles(10))
+    ///     .with_origin(vec!["web-03".to_string(), std::process::id().to_string()]);
+    /// ```
+    ///
+    /// The separator and path separators in the fields are replaced by `_`. Files with any values
+    /// in these fields are found, and count against the [FileLimit], unless
+    /// [TimestampSuffix::only_own_origin] is set. They are ordered by timestamp regardless.
+    pub fn with_origin<I: IntoIterator<Item = String>>(mut self, fields: I) -> Self {
+        self.origin = fields.into_iter().collect();
+        self
+    }
+    /// Only consider files with the same fields as given to [TimestampSuffix::with_origin], e.g.
+    /// to leave the files of other hosts alone. `false` by default.
+    pub fn only_own_origin(mut self, only_own_origin: bool) -> Self {
+        self.only_own_origin = only_own_origin;
+        self
+    }
+    /// The fields of [TimestampSuffix::with_origin], each followed by the separator.
+    fn own_origin(&self) -> String {
+        self.origin
+            .iter()
+            .map(|field| {
+                let field = field.replace(self.naming.separator(), "_");
+                let field = field.replace(['/', '\\'], "_");
+                field + self.naming.separator()
+            })
+            .collect()
+    }
+    /// Split `suffix` into the origin fields (with their separators) and the rest.
+    fn split_origin<'a>(&self, suffix: &'a str) -> Option<(&'a str, &'a str)> {
+        let mut rest = suffix;
+        for _ in 0..self.origin.len() {
+            let end = rest.find(self.naming.separator())?;
+            rest = &rest[end + self.naming.separator().len()..];
+        }
+        let origin = &suffix[..suffix.len() - rest.len()];
+        if self.only_own_origin && origin != self.own_origin() {
+            return None;
         }
+        Some((origin, rest))
+    }
+    /// The point in time of `timestamp`, in the current or a previous format.
+    fn time_of(&self, timestamp: &str) -> Option<DateTime<FixedOffset>> {
+        std::iter::once(&*self.format)
+            .chain(self.previous_formats.iter().map(String::as_str))
+            .find_map(|format| timestamp_instant(timestamp, format))
+    }
+    /// Chronological order of two timestamps, and the lexical order if they are at the same time.
+    fn cmp_timestamps(&self, a: &str, b: &str) -> Ordering {
+        (self.time_of(a), a).cmp(&(self.time_of(b), b))
     }
     /// NOTE: For future use in RotationMode::Custom
     pub fn should_rotate(&self, age: Duration) -> impl Fn(&str) -> bool {
         let format = self.format.to_string();
-        move |suffix| {
-            let old_timestamp = (Local::now() - age).format(&format).to_string();
-            suffix < old_timestamp.as_str()
-        }
+        let old_timestamp = (self.clock.now() - age).format(&format).to_string();
+        move |suffix| suffix < old_timestamp.as_str()
     }
-    pub(crate) fn suffix_to_string(&self, suffix: &(String, Option<usize>)) -> String {
+    pub(crate) fn suffix_to_string(&self, suffix: &(String, Option<usize>, String)) -> String {
         match suffix.1 {
-            Some(n) => format!("{}.{}", suffix.0, n),
-            None => suffix.0.clone(),
+            Some(n) => format!("{}{}.{}", suffix.2, suffix.0, n),
+            None => format!("{}{}", suffix.2, suffix.0),
         }
     }
     pub(crate) fn suffix_to_path(
         &self,
         basepath: &Path,
-        suffix: &(String, Option<usize>),
+        suffix: &(String, Option<usize>, String),
     ) -> PathBuf {
-        PathBuf::from(format!(
-            "{}.{}",
-            basepath.display(),
-            self.suffix_to_string(suffix)
-        ))
+        self.naming.path(basepath, &self.suffix_to_string(suffix))
+    }
+    /// Split `suffix` into timestamp, number and origin, if it is one of ours.
+    fn parse_suffix(&self, suffix: &str) -> Option<(String, Option<usize>, String)> {
+        let (origin, suffix) = self.split_origin(suffix)?;
+        // Find the optional `.` separating the timestamp from the number. The timestamp itself may
+        // contain a `.`, e.g. with `%.3f`.
+        let (timestamp, n) = if self.time_of(suffix).is_some() {
+            (suffix, None)
+        } else {
+            let dot = suffix.rfind('.')?;
+            let n = suffix[(dot + 1)..].parse::<usize>().ok()?;
+            (&suffix[..dot], Some(n))
+        };
+        self.time_of(timestamp)?;
+        Some((timestamp.to_string(), n, origin.to_string()))
     }
     /// Scan files in the log directory to construct the list of files
     fn ensure_suffix_list(&mut self, basepath: &Path) {
+        if self.only_newest {
+            self.suffixes = None;
+            self.only_newest = false;
+        }
         if self.suffixes.is_none() {
-            let mut suffixes = VecDeque::new();
-            let filename_prefix = &*basepath
-                .file_name()
-                .expect("basepath.file_name()")
-                .to_string_lossy();
-            let parent = basepath.parent().unwrap();
-            let filenames = std::fs::read_dir(parent)
-                .unwrap()
-                .filter_map(|entry| entry.ok())
-                .filter(|entry| entry.path().is_file())
-                .map(|entry| entry.file_name());
-            for filename in filenames {
-                let filename = filename.to_string_lossy();
-                if !filename.starts_with(&filename_prefix) {
-                    continue;
-                }
-                // Find the up to two `.` in the filename
-                if let Some(first_dot) = filename.find('.') {
-                    let suffix = &filename[(first_dot + 1)..];
-                    let (timestamp_str, n) = if let Some(second_dot) = suffix.find('.') {
-                        if let Ok(n) = suffix[(second_dot + 1)..].parse::<usize>() {
-                            (&suffix[..second_dot], Some(n))
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        (suffix, None)
-                    };
-                    if NaiveDateTime::parse_from_str(timestamp_str, self.format).is_ok() {
-                        suffixes.push_back((timestamp_str.to_string(), n))
-                    }
-                } else {
-                    // We don't consider the current (suffix-less) log file.
-                }
-            }
-            // Sort in Ascending order (higher value (most recent) first)
+            let mut suffixes = scan_suffixes(&*self.fs, basepath, &self.naming)
+                .iter()
+                .filter_map(|suffix| self.parse_suffix(suffix))
+                .collect::<VecDeque<_>>();
+            // Sort in ascending order (oldest first). Sorting the tuples rather than the strings
+            // keeps `.10` after `.9`.
             suffixes
                 .make_contiguous()
-                .sort_by_key(|suffix| self.suffix_to_string(suffix));
+                .sort_by_cached_key(|suffix| (self.time_of(&suffix.0), suffix.clone()));
             self.suffixes = Some(suffixes);
         }
     }
-}
-#[cfg(feature = "chrono04")]
-impl SuffixScheme for TimestampSuffix {
+    /// Like [TimestampSuffix::ensure_suffix_list], but only keeps what rotation needs: The newest
+    /// `max_files` files with [FileLimit::MaxFiles], deleting the older ones right away, and the
+    /// files with the newest timestamp with [FileLimit::Unlimited].
+    fn ensure_rotation_list(&mut self, basepath: &Path) {
+        if self.suffixes.is_some() {
+            return;
+        }
+        let max_files = match self.file_limit {
+            FileLimit::MaxFiles(max_files) => Some(max_files),
+            FileLimit::Unlimited => None,
+            _ => return self.ensure_suffix_list(basepath),
+        };
+        let reason = self.file_limit.deletion_reason();
+        let mut errors = std::mem::take(&mut self.errors);
+        let mut deleted = std::mem::take(&mut self.deleted);
+        let parsed = iter_suffixes(&*self.fs, basepath, &self.naming).filter_map(|suffix| {
+            let suffix = self.parse_suffix(&suffix)?;
+            Some((self.time_of(&suffix.0), suffix))
+        });
+        let mut suffixes = match max_files {
+            Some(max_files) => keep_greatest(parsed, max_files, |(_, oldest)| {
+                let path = self.suffix_to_path(basepath, &oldest);
+                delete_rotated(
+                    &*self.fs,
+                    &path,
+                    format_args!("{}", reason),
+                    &mut errors,
+                    &mut deleted,
+                );
+            }),
+            None => all_greatest(parsed, |(time, suffix)| (*time, suffix.0.clone())),
+        };
+        suffixes.sort();
+        self.errors = errors;
+        self.deleted = deleted;
+        self.only_newest = max_files.is_none();
+        self.suffixes = Some(suffixes.into_iter().map(|(_, suffix)| suffix).collect());
+    }
+}
+#[cfg(feature = "chrono04")]
+impl SuffixScheme for TimestampSuffix {
     fn rotate(&mut self, basepath: &Path) -> String {
-        let now = Local::now().format(self.format).to_string();
+        let clock_now = self.clock.now();
+        let now = match self.date_from {
+            DateFrom::Now => clock_now,
+            DateFrom::Custom(offset) => clock_now - offset,
+        };
+        let mut now = now.format(&self.format).to_string();
 
-        self.ensure_suffix_list(basepath);
+        self.ensure_rotation_list(basepath);
+        let origin = self.own_origin();
+
+        // If the clock went backwards (e.g. adjusted by NTP), `now` would sort before the newest
+        // file and be treated as older than it. Reuse the newest timestamp instead, the number
+        // appended below keeps the order right.
+        if let Some(newest) = self.suffixes.as_ref().unwrap().back() {
+            if self.cmp_timestamps(&newest.0, &now) == Ordering::Greater {
+                now = newest.0.clone();
+            }
+        }
 
         // For all existing suffixes that equals `now`, take the max `n`, and add one
         let n = self
@@ -204,49 +1315,114 @@ impl SuffixScheme for TimestampSuffix {
             .as_ref()
             .unwrap()
             .iter()
-            .filter(|suffix| suffix.0 == now)
+            .filter(|suffix| suffix.0 == now && suffix.2 == origin)
             .map(|suffix| suffix.1.unwrap_or(0))
             .max()
             .map(|n| n + 1);
 
         // Register the selected suffix as taken
-        self.suffixes.as_mut().unwrap().push_back((now.clone(), n));
+        let suffix = (now, n, origin);
+        self.suffixes.as_mut().unwrap().push_back(suffix.clone());
 
         // Remove old files
         // Note that the oldest are the first in the list
         let to_delete = match self.file_limit {
             FileLimit::MaxFiles(max_files) => {
                 let n_files = self.suffixes.as_ref().unwrap().len();
-                if n_files > max_files {
-                    n_files - max_files
-                } else {
-                    0
-                }
+                n_files.saturating_sub(max_files)
             }
-            FileLimit::Age(age) => {
+            FileLimit::Age(age) | FileLimit::AgeWithMinFiles { age, .. } => {
+                // An age too large to look back that far keeps all files
+                let old = Duration::from_std(age)
+                    .ok()
+                    .and_then(|age| clock_now.checked_sub_signed(age));
                 let mut to_delete = 0;
-                for suffix in self.suffixes.as_ref().unwrap().iter() {
-                    let old_timestamp = (Local::now() - age).format(self.format).to_string();
-                    let delete = suffix.0 < old_timestamp;
-                    if delete {
-                        let _ = std::fs::remove_file(self.suffix_to_path(basepath, suffix));
-                        to_delete += 1;
-                    } else {
-                        // Remember that `suffixes` has the oldest entries in the front, we can `break`
-                        // once we find an entry that doesn't have to deleted
-                        break;
+                if let Some(old) = old {
+                    let old_timestamp = old.format(&self.format).to_string();
+                    for suffix in self.suffixes.as_ref().unwrap().iter() {
+                        // A timestamp equal to `old_timestamp` may be older or newer than `old`, if
+                        // the format is coarse like `%Y-%m-%d`. The modification time tells, if
+                        // available.
+                        let delete = match self.cmp_timestamps(&suffix.0, &old_timestamp) {
+                            Ordering::Less => true,
+                            Ordering::Equal => self
+                                .fs
+                                .metadata(&self.suffix_to_path(basepath, suffix))
+                                .and_then(|metadata| metadata.modified())
+                                .map(|modified| DateTime::<Local>::from(modified) < old)
+                                .unwrap_or(false),
+                            Ordering::Greater => false,
+                        };
+                        if delete {
+                            to_delete += 1;
+                        } else {
+                            // Remember that `suffixes` has the oldest entries in the front, we can
+                            // `break` once we find an entry that doesn't have to deleted
+                            break;
+                        }
                     }
                 }
                 to_delete
             }
+            FileLimit::KeepPerPeriod {
+                recent,
+                per_period,
+                period,
+            } => {
+                let suffixes = self.suffixes.take().unwrap();
+                let times = suffixes
+                    .iter()
+                    .map(|suffix| {
+                        self.time_of(&suffix.0)
+                            .map(|time| time.with_timezone(&Local).naive_local())
+                    })
+                    .collect::<Vec<_>>();
+                let keep = keep_per_period(&times, clock_now, recent, per_period, period);
+                let mut errors = Vec::new();
+                let mut deleted = std::mem::take(&mut self.deleted);
+                let suffixes = suffixes
+                    .into_iter()
+                    .zip(keep)
+                    .filter(|(suffix, keep)| {
+                        if !keep {
+                            let path = self.suffix_to_path(basepath, suffix);
+                            delete_rotated(
+                                &*self.fs,
+                                &path,
+                                format_args!("not kept per {:?}", period),
+                                &mut errors,
+                                &mut deleted,
+                            );
+                        }
+                        *keep
+                    })
+                    .map(|(suffix, _)| suffix)
+                    .collect();
+                self.suffixes = Some(suffixes);
+                self.errors.append(&mut errors);
+                self.deleted = deleted;
+                0
+            }
+            FileLimit::Unlimited => 0,
         };
+        let n_files = self.suffixes.as_ref().unwrap().len();
+        let to_delete = to_delete.min(n_files.saturating_sub(self.file_limit.min_files()));
 
-        // Delete respective entries
+        // Delete respective files and entries
         for _ in 0..to_delete {
-            self.suffixes.as_mut().unwrap().pop_front();
+            let suffix = self.suffixes.as_mut().unwrap().pop_front().unwrap();
+            let path = self.suffix_to_path(basepath, &suffix);
+            let reason = self.file_limit.deletion_reason();
+            delete_rotated(
+                &*self.fs,
+                &path,
+                format_args!("{}", reason),
+                &mut self.errors,
+                &mut self.deleted,
+            );
         }
 
-        self.suffix_to_string(&(now, n))
+        self.suffix_to_string(&suffix)
     }
     fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
         self.ensure_suffix_list(basepath);
@@ -254,22 +1430,1028 @@ impl SuffixScheme for TimestampSuffix {
             .as_ref()
             .unwrap()
             .iter()
-            .map(|suffix| {
-                PathBuf::from(format!(
-                    "{}.{}",
-                    basepath.display(),
-                    self.suffix_to_string(suffix)
-                ))
-            })
+            .map(|suffix| self.suffix_to_path(basepath, suffix))
             .collect::<Vec<_>>()
     }
+    fn separator(&self) -> &str {
+        self.naming.separator()
+    }
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        self.naming.path(basepath, suffix)
+    }
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        let suffix = self.naming.suffix(basepath, file_name)?;
+        // Files of other origins are none of our business
+        if self.only_own_origin && !suffix.starts_with(&self.own_origin()) {
+            return None;
+        }
+        Some(suffix)
+    }
+    fn recognizes(&self, suffix: &str) -> bool {
+        self.parse_suffix(suffix).is_some()
+    }
+    fn resembles(&self, suffix: &str) -> bool {
+        starts_with_timestamp(suffix, |timestamp| self.time_of(timestamp).is_some())
+    }
+    fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        self.deleted.take_done()
+    }
+    fn defer_deletions(&mut self) -> bool {
+        self.deleted.defer()
+    }
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        self.deleted.take_deferred()
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        self.deleted.protect(paths)
+    }
+    fn use_fs_ops(&mut self, fs: Arc<dyn FsOps>) {
+        self.fs = fs;
+    }
 }
 
-/// How to determine if a file should be deleted, in the case of TimestampSuffix.
+/// Smallest suffix recognized by [EpochSuffix] (2001-09-09T01:46:40Z).
+///
+/// Smaller numbers are far more likely to be [CountSuffix] files, e.g. after migrating from one scheme
+/// to the other, so they are ignored rather than treated as very old timestamps.
+pub const MIN_EPOCH_SECS: u64 = 1_000_000_000;
+
+/// Rotated log files get the number of seconds since the Unix epoch as suffix, like
+/// `my-log-file.1717243200`. This sorts naturally, is trivial to parse and doesn't depend on any
+/// time zone.
+///
+/// If several files are rotated within the same second, `.1` (and up) is appended, like with
+/// [TimestampSuffix]. Numbers below [MIN_EPOCH_SECS] are not considered to be rotated files.
 #[cfg(feature = "chrono04")]
+pub struct EpochSuffix {
+    /// None means that we don't know the files, and a scan is necessary.
+    suffixes: Option<VecDeque<(u64, Option<usize>)>>,
+    /// `suffixes` only has the newest files, which is all that rotation needs with
+    /// [FileLimit::Unlimited].
+    only_newest: bool,
+    file_limit: FileLimit,
+    naming: Naming,
+    clock: Box<dyn Clock>,
+    errors: Vec<Error>,
+    deleted: Deletions,
+    /// The file system operations, see [SuffixScheme::use_fs_ops]
+    fs: Arc<dyn FsOps>,
+}
+
+#[cfg(feature = "chrono04")]
+impl EpochSuffix {
+    /// Create new EpochSuffix suffix scheme
+    pub fn new(file_limit: FileLimit) -> Self {
+        Self {
+            suffixes: None,
+            only_newest: false,
+            file_limit,
+            naming: Naming::default(),
+            clock: Box::new(SystemClock),
+            errors: Vec::new(),
+            deleted: Deletions::default(),
+            fs: Arc::new(RealFs),
+        }
+    }
+    /// Use `separator` instead of `"."` between the base name and the number of seconds.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        assert!(!separator.is_empty());
+        self.naming = Naming::Separator(separator.to_string());
+        self
+    }
+    /// Name the rotated files after `template` instead of appending the separator and suffix.
+    pub fn with_template(mut self, template: NameTemplate) -> Self {
+        self.naming = Naming::Template(template);
+        self
+    }
+    /// Take the current time from `clock` instead of the system clock.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+    fn suffix_to_string(suffix: &(u64, Option<usize>)) -> String {
+        match suffix.1 {
+            Some(n) => format!("{}.{}", suffix.0, n),
+            None => suffix.0.to_string(),
+        }
+    }
+    fn parse(suffix: &str) -> Option<(u64, Option<usize>)> {
+        let (secs, n) = match suffix.find('.') {
+            Some(dot) => (
+                &suffix[..dot],
+                Some(suffix[(dot + 1)..].parse::<usize>().ok()?),
+            ),
+            None => (suffix, None),
+        };
+        let secs = secs.parse::<u64>().ok()?;
+        if secs >= MIN_EPOCH_SECS {
+            Some((secs, n))
+        } else {
+            None
+        }
+    }
+    /// Scan files in the log directory to construct the list of files
+    fn ensure_suffix_list(&mut self, basepath: &Path) {
+        if self.only_newest {
+            self.suffixes = None;
+            self.only_newest = false;
+        }
+        if self.suffixes.is_none() {
+            let mut suffixes = scan_suffixes(&*self.fs, basepath, &self.naming)
+                .iter()
+                .filter_map(|suffix| Self::parse(suffix))
+                .collect::<Vec<_>>();
+            // Sort in ascending order (oldest first)
+            suffixes.sort();
+            self.suffixes = Some(suffixes.into());
+        }
+    }
+    /// Like [EpochSuffix::ensure_suffix_list], but only keeps what rotation needs: The newest
+    /// `max_files` files with [FileLimit::MaxFiles], deleting the older ones right away, and the
+    /// files with the newest timestamp with [FileLimit::Unlimited].
+    fn ensure_rotation_list(&mut self, basepath: &Path) {
+        if self.suffixes.is_some() {
+            return;
+        }
+        let max_files = match self.file_limit {
+            FileLimit::MaxFiles(max_files) => Some(max_files),
+            FileLimit::Unlimited => None,
+            _ => return self.ensure_suffix_list(basepath),
+        };
+        let reason = self.file_limit.deletion_reason();
+        let naming = &self.naming;
+        let fs = &*self.fs;
+        let errors = &mut self.errors;
+        let deleted = &mut self.deleted;
+        let parsed = iter_suffixes(fs, basepath, naming).filter_map(|suffix| Self::parse(&suffix));
+        let mut suffixes = match max_files {
+            Some(max_files) => keep_greatest(parsed, max_files, |oldest| {
+                let path = naming.path(basepath, &Self::suffix_to_string(&oldest));
+                delete_rotated(fs, &path, format_args!("{}", reason), errors, deleted);
+            }),
+            None => all_greatest(parsed, |suffix| suffix.0),
+        };
+        self.only_newest = max_files.is_none();
+        suffixes.sort();
+        self.suffixes = Some(suffixes.into());
+    }
+}
+
+#[cfg(feature = "chrono04")]
+impl SuffixScheme for EpochSuffix {
+    fn rotate(&mut self, basepath: &Path) -> String {
+        let clock_now = self.clock.now();
+        let now = clock_now.timestamp().max(0) as u64;
+
+        self.ensure_rotation_list(basepath);
+        let suffixes = self.suffixes.as_mut().unwrap();
+
+        // If the clock went backwards, reuse the newest timestamp so that the order stays right
+        let now = suffixes.back().map_or(now, |newest| newest.0.max(now));
+
+        // For all existing suffixes that equals `now`, take the max `n`, and add one
+        let n = suffixes
+            .iter()
+            .filter(|suffix| suffix.0 == now)
+            .map(|suffix| suffix.1.unwrap_or(0))
+            .max()
+            .map(|n| n + 1);
+        suffixes.push_back((now, n));
+
+        // Remove old files. The oldest are the first in the list.
+        let to_delete = match self.file_limit {
+            FileLimit::MaxFiles(max_files) => suffixes.len().saturating_sub(max_files),
+            FileLimit::Age(age) | FileLimit::AgeWithMinFiles { age, .. } => {
+                // Round up, since the suffixes are whole seconds
+                let age = age.as_secs() + u64::from(age.subsec_nanos() > 0);
+                let oldest = now.saturating_sub(age);
+                suffixes
+                    .iter()
+                    .take_while(|suffix| suffix.0 < oldest)
+                    .count()
+            }
+            FileLimit::KeepPerPeriod {
+                recent,
+                per_period,
+                period,
+            } => {
+                // Use the offset of the clock rather than `Local`, so that a clock in UTC works
+                // without a local time zone
+                let offset = *clock_now.offset();
+                let times = suffixes
+                    .iter()
+                    .map(|suffix| {
+                        offset
+                            .timestamp_opt(suffix.0 as i64, 0)
+                            .single()
+                            .map(|time| time.naive_local())
+                    })
+                    .collect::<Vec<_>>();
+                let mut keep =
+                    keep_per_period(&times, clock_now, recent, per_period, period).into_iter();
+                let naming = &self.naming;
+                let fs = &*self.fs;
+                let errors = &mut self.errors;
+                let deleted = &mut self.deleted;
+                suffixes.retain(|suffix| {
+                    let keep = keep.next().unwrap();
+                    if !keep {
+                        let path = naming.path(basepath, &Self::suffix_to_string(suffix));
+                        delete_rotated(
+                            fs,
+                            &path,
+                            format_args!("not kept per {:?}", period),
+                            errors,
+                            deleted,
+                        );
+                    }
+                    keep
+                });
+                0
+            }
+            FileLimit::Unlimited => 0,
+        };
+        let to_delete = to_delete.min(suffixes.len().saturating_sub(self.file_limit.min_files()));
+        for suffix in suffixes.drain(..to_delete) {
+            let path = self.naming.path(basepath, &Self::suffix_to_string(&suffix));
+            let reason = self.file_limit.deletion_reason();
+            delete_rotated(
+                &*self.fs,
+                &path,
+                format_args!("{}", reason),
+                &mut self.errors,
+                &mut self.deleted,
+            );
+        }
+
+        Self::suffix_to_string(&(now, n))
+    }
+    fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
+        self.ensure_suffix_list(basepath);
+        self.suffixes
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|suffix| self.naming.path(basepath, &Self::suffix_to_string(suffix)))
+            .collect::<Vec<_>>()
+    }
+    fn separator(&self) -> &str {
+        self.naming.separator()
+    }
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        self.naming.path(basepath, suffix)
+    }
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        self.naming.suffix(basepath, file_name)
+    }
+    fn recognizes(&self, suffix: &str) -> bool {
+        Self::parse(suffix).is_some()
+    }
+    fn resembles(&self, suffix: &str) -> bool {
+        starts_with_digit(suffix)
+    }
+    fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        self.deleted.take_done()
+    }
+    fn defer_deletions(&mut self) -> bool {
+        self.deleted.defer()
+    }
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        self.deleted.take_deferred()
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        self.deleted.protect(paths)
+    }
+    fn use_fs_ops(&mut self, fs: Arc<dyn FsOps>) {
+        self.fs = fs;
+    }
+}
+
+/// What [IntervalSuffix] appends to the name of the file that is being written.
+pub const PART_SUFFIX: &str = ".part";
+
+/// Names each log file after the interval it covers, like
+/// `my-log-file.20240601T120000-20240601T130000`, for
+/// [RotateMechanics::DateInName](crate::RotateMechanics::DateInName).
+///
+/// The current file is named after the time it was opened, followed by [PART_SUFFIX], like
+/// `my-log-file.20240601T120000.part`, and renamed to the interval when a rotation closes it. If
+/// several files cover the same interval, `.1` (and up) is appended.
+///
+/// ```
+/// use file_rotate::{suffix::{FileLimit, IntervalSuffix}, ContentLimit, FileRotate, RotateMechanics};
+/// # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+/// # let directory = directory.path();
+/// let log = FileRotate::builder(directory.join("my-log-file"), IntervalSuffix::new(FileLimit::MaxFiles(24)), ContentLimit::Lines(1000))
+///     .rotate_mechanics(RotateMechanics::DateInName { symlink: false })
+///     .build();
+/// assert!(log.current_path().to_str().unwrap().ends_with(".part"));
+/// ```
+///
+/// `.part` files don't count against the [FileLimit] and are never deleted by it. A `.part` file
+/// is left behind when the process ends, e.g. by a crash, or when its rename fails. The next
+/// rotation renames it, with its modification time as the end of the interval, unless
+/// [OpenBehavior::Append](crate::OpenBehavior::Append) continues it first when starting. The
+/// [FileLimit::Age] of a file counts from the end of its interval.
+#[cfg(feature = "chrono04")]
+pub struct IntervalSuffix {
+    format: Cow<'static, str>,
+    file_limit: FileLimit,
+    naming: Naming,
+    clock: Box<dyn Clock>,
+    errors: Vec<Error>,
+    deleted: Deletions,
+    /// The file system operations, see [SuffixScheme::use_fs_ops]
+    fs: Arc<dyn FsOps>,
+}
+
+/// A file of [IntervalSuffix].
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Interval {
+    open: String,
+    /// The end and the number, None for a `.part` file
+    close: Option<(String, Option<usize>)>,
+}
+
+#[cfg(feature = "chrono04")]
+impl IntervalSuffix {
+    /// With format `"%Y%m%dT%H%M%S"`
+    pub fn new(file_limit: FileLimit) -> Self {
+        Self::with_format("%Y%m%dT%H%M%S", file_limit)
+    }
+    /// Use `format` for both ends of the interval. `format` isn't checked here, use
+    /// [IntervalSuffix::try_with_format] for a format that isn't known to work.
+    pub fn with_format<F: Into<Cow<'static, str>>>(format: F, file_limit: FileLimit) -> Self {
+        Self {
+            format: format.into(),
+            file_limit,
+            naming: Naming::default(),
+            clock: Box::new(SystemClock),
+            errors: Vec::new(),
+            deleted: Deletions::default(),
+            fs: Arc::new(RealFs),
+        }
+    }
+    /// Like [IntervalSuffix::with_format], but checks that `format` can be used, like
+    /// [TimestampSuffix::try_with_format] does.
+    pub fn try_with_format<F: Into<Cow<'static, str>>>(
+        format: F,
+        file_limit: FileLimit,
+    ) -> Result<Self, FormatError> {
+        let format = format.into();
+        check_format(&format)?;
+        Ok(Self::with_format(format, file_limit))
+    }
+    /// Name the files after `template` instead of appending the separator and suffix, e.g.
+    /// `app.{suffix}.log` for `app.20240601T120000-20240601T130000.log`.
+    pub fn with_template(mut self, template: NameTemplate) -> Self {
+        self.naming = Naming::Template(template);
+        self
+    }
+    /// Take the current time from `clock` instead of the system clock.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+    fn time_of(&self, timestamp: &str) -> Option<DateTime<FixedOffset>> {
+        timestamp_instant(timestamp, &self.format)
+    }
+    /// Split `interval` at the `-` that separates two timestamps. The format may contain `-`
+    /// itself.
+    fn split_interval<'a>(&self, interval: &'a str) -> Option<(&'a str, &'a str)> {
+        interval.match_indices('-').find_map(|(idx, _)| {
+            let (open, close) = (&interval[..idx], &interval[idx + 1..]);
+            self.time_of(open)?;
+            self.time_of(close)?;
+            Some((open, close))
+        })
+    }
+    fn parse_suffix(&self, suffix: &str) -> Option<Interval> {
+        if let Some(open) = suffix.strip_suffix(PART_SUFFIX) {
+            self.time_of(open)?;
+            return Some(Interval {
+                open: open.to_string(),
+                close: None,
+            });
+        }
+        let (interval, n) = match self.split_interval(suffix) {
+            Some(_) => (suffix, None),
+            None => {
+                let dot = suffix.rfind('.')?;
+                let n = suffix[(dot + 1)..].parse::<usize>().ok()?;
+                (&suffix[..dot], Some(n))
+            }
+        };
+        let (open, close) = self.split_interval(interval)?;
+        Some(Interval {
+            open: open.to_string(),
+            close: Some((close.to_string(), n)),
+        })
+    }
+    fn suffix_to_string(interval: &Interval) -> String {
+        match interval.close {
+            None => format!("{}{}", interval.open, PART_SUFFIX),
+            Some((ref close, None)) => format!("{}-{}", interval.open, close),
+            Some((ref close, Some(n))) => format!("{}-{}.{}", interval.open, close, n),
+        }
+    }
+    fn interval_path(&self, basepath: &Path, interval: &Interval) -> PathBuf {
+        self.naming
+            .path(basepath, &Self::suffix_to_string(interval))
+    }
+    /// The files in the log directory, oldest first.
+    fn scan(&self, basepath: &Path) -> Vec<Interval> {
+        let mut intervals = iter_suffixes(&*self.fs, basepath, &self.naming)
+            .filter_map(|suffix| self.parse_suffix(&suffix))
+            .collect::<Vec<_>>();
+        intervals.sort_by_cached_key(|interval| {
+            let close = interval.close.as_ref();
+            (
+                self.time_of(&interval.open),
+                close.map(|close| self.time_of(&close.0)),
+                close.map(|close| close.1),
+                interval.open.clone(),
+            )
+        });
+        intervals
+    }
+    /// The file opened at `open` when it's closed at `closed`, with a number that's not taken.
+    fn close(&self, basepath: &Path, open: &str, closed: DateTime<Local>) -> Interval {
+        let mut close = closed.format(&self.format).to_string();
+        // Don't end before the start, e.g. if the clock went backwards
+        if self.time_of(&close) < self.time_of(open) {
+            close = open.to_string();
+        }
+        let mut interval = Interval {
+            open: open.to_string(),
+            close: Some((close.clone(), None)),
+        };
+        let mut n = 0;
+        while exists(&*self.fs, &self.interval_path(basepath, &interval)) {
+            n += 1;
+            interval.close = Some((close.clone(), Some(n)));
+        }
+        interval
+    }
+}
+
+#[cfg(feature = "chrono04")]
+impl SuffixScheme for IntervalSuffix {
+    fn rotate(&mut self, basepath: &Path) -> String {
+        let now = self.clock.now();
+        // `FileRotate` renamed its own file before, so the `.part` files left are of earlier runs
+        let mut closed = Vec::new();
+        for interval in self.scan(basepath) {
+            if interval.close.is_some() {
+                closed.push(interval);
+                continue;
+            }
+            let from = self.interval_path(basepath, &interval);
+            let modified = self
+                .fs
+                .metadata(&from)
+                .and_then(|metadata| metadata.modified())
+                .map(DateTime::<Local>::from)
+                .unwrap_or(now);
+            let finished = self.close(basepath, &interval.open, modified);
+            let to = self.interval_path(basepath, &finished);
+            match self.fs.rename(&from, &to) {
+                Ok(()) => {
+                    event!(Info, "renamed {} to {}", from.display(), to.display());
+                    closed.push(finished);
+                }
+                Err(source) => self.errors.push(Error::Rotate { from, to, source }),
+            }
+        }
+        // Renamed files may be out of order
+        closed.sort_by_cached_key(|interval| {
+            let close = interval.close.as_ref().unwrap();
+            (
+                self.time_of(&interval.open),
+                self.time_of(&close.0),
+                close.1,
+            )
+        });
+
+        // Remove old files, the oldest are the first in the list
+        let close_time = |interval: &Interval| {
+            self.time_of(&interval.close.as_ref().unwrap().0)
+                .map(|time| time.with_timezone(&Local))
+        };
+        let mut delete = vec![false; closed.len()];
+        match self.file_limit {
+            FileLimit::MaxFiles(max_files) => {
+                let to_delete = closed.len().saturating_sub(max_files);
+                delete[..to_delete].fill(true);
+            }
+            FileLimit::Age(age) | FileLimit::AgeWithMinFiles { age, .. } => {
+                // An age too large to look back that far keeps all files
+                if let Some(old) = Duration::from_std(age)
+                    .ok()
+                    .and_then(|age| now.checked_sub_signed(age))
+                {
+                    for (interval, delete) in closed.iter().zip(&mut delete) {
+                        *delete = close_time(interval).is_some_and(|close| close < old);
+                    }
+                }
+            }
+            FileLimit::KeepPerPeriod {
+                recent,
+                per_period,
+                period,
+            } => {
+                let times = closed
+                    .iter()
+                    .map(|interval| close_time(interval).map(|time| time.naive_local()))
+                    .collect::<Vec<_>>();
+                let keep = keep_per_period(&times, now, recent, per_period, period);
+                for (delete, keep) in delete.iter_mut().zip(keep) {
+                    *delete = !keep;
+                }
+            }
+            FileLimit::Unlimited => {}
+        }
+        // Keep the newest files of those to delete for the minimum
+        let mut keep = self.file_limit.min_files();
+        for (interval, delete) in closed.iter().zip(delete).rev() {
+            if !delete {
+                keep = keep.saturating_sub(1);
+            } else if keep > 0 {
+                keep -= 1;
+            } else {
+                let path = self.interval_path(basepath, interval);
+                let reason = self.file_limit.deletion_reason();
+                delete_rotated(
+                    &*self.fs,
+                    &path,
+                    format_args!("{}", reason),
+                    &mut self.errors,
+                    &mut self.deleted,
+                );
+            }
+        }
+
+        Self::suffix_to_string(&Interval {
+            open: now.format(&self.format).to_string(),
+            close: None,
+        })
+    }
+    fn log_paths(&mut self, basepath: &Path) -> Vec<PathBuf> {
+        self.scan(basepath)
+            .iter()
+            .map(|interval| self.interval_path(basepath, interval))
+            .collect()
+    }
+    fn separator(&self) -> &str {
+        self.naming.separator()
+    }
+    fn rotated_path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        self.naming.path(basepath, suffix)
+    }
+    fn suffix_of(&self, basepath: &Path, file_name: &OsStr) -> Option<String> {
+        self.naming.suffix(basepath, file_name)
+    }
+    fn recognizes(&self, suffix: &str) -> bool {
+        self.parse_suffix(suffix).is_some()
+    }
+    fn resembles(&self, suffix: &str) -> bool {
+        starts_with_timestamp(suffix, |timestamp| self.time_of(timestamp).is_some())
+    }
+    fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+    fn take_deleted(&mut self) -> Vec<Deleted> {
+        self.deleted.take_done()
+    }
+    fn defer_deletions(&mut self) -> bool {
+        self.deleted.defer()
+    }
+    fn take_deferred(&mut self) -> Vec<Deleted> {
+        self.deleted.take_deferred()
+    }
+    fn protect(&mut self, paths: &[PathBuf]) -> bool {
+        self.deleted.protect(paths)
+    }
+    fn use_fs_ops(&mut self, fs: Arc<dyn FsOps>) {
+        self.fs = fs;
+    }
+    fn closed_suffix(&self, basepath: &Path, suffix: &str) -> Option<String> {
+        let interval = self.parse_suffix(suffix)?;
+        if interval.close.is_some() {
+            return None;
+        }
+        let closed = self.close(basepath, &interval.open, self.clock.now());
+        Some(Self::suffix_to_string(&closed))
+    }
+}
+
+/// Which point in time the timestamp of a rotated file is taken from, in the case of
+/// TimestampSuffix.
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DateFrom {
+    /// The time of rotation.
+    Now,
+    /// The time of rotation minus the given duration. For example, when rotating every 15 minutes,
+    /// `Custom(Duration::minutes(15))` names each file after the start of the interval it covers.
+    /// Negative durations are allowed.
+    Custom(#[cfg_attr(feature = "serde", serde(with = "crate::config::duration"))] Duration),
+}
+
+/// Parse `timestamp`, which has to be exactly what `format` produces, into local time.
+///
+/// Formats without any time fields are parsed as a date, at midnight. Anything that doesn't render
+/// back to the very same string is rejected, so that e.g. trailing garbage or unpadded numbers
+/// don't cause unrelated files to be counted against or deleted by the `FileLimit`.
+///
+/// If `format` has a time zone offset, timestamps without it are accepted as well.
+#[cfg(feature = "chrono04")]
+pub(crate) fn parse_timestamp(timestamp: &str, format: &str) -> Option<NaiveDateTime> {
+    if has_offset_fields(format) {
+        return match parse_with_offset(timestamp, format) {
+            Some(datetime) => Some(datetime.with_timezone(&Local).naive_local()),
+            None => parse_local(timestamp, &without_offset_fields(format)),
+        };
+    }
+    parse_local(timestamp, format)
+}
+
+/// The point in time of `timestamp`, see [parse_timestamp].
+#[cfg(feature = "chrono04")]
+fn timestamp_instant(timestamp: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    if let Some(datetime) = parse_with_offset(timestamp, format) {
+        return Some(datetime);
+    }
+    let local = parse_local(timestamp, &without_offset_fields(format))?;
+    // Local times skipped by a daylight saving time change don't exist, take them as UTC then
+    let local = Local
+        .from_local_datetime(&local)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&local));
+    Some(local.into())
+}
+
+/// [parse_timestamp] for a `format` with a time zone offset.
+#[cfg(feature = "chrono04")]
+fn parse_with_offset(timestamp: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    let datetime = DateTime::parse_from_str(timestamp, format).ok()?;
+    if datetime.format(format).to_string() == timestamp {
+        Some(datetime)
+    } else {
+        None
+    }
+}
+
+/// [parse_timestamp] for a `format` without a time zone offset.
+#[cfg(feature = "chrono04")]
+fn parse_local(timestamp: &str, format: &str) -> Option<NaiveDateTime> {
+    let datetime = if has_time_fields(format) {
+        NaiveDateTime::parse_from_str(timestamp, format).ok()?
+    } else {
+        parse_date(timestamp, format)?.and_hms_opt(0, 0, 0)?
+    };
+    if datetime.format(format).to_string() == timestamp {
+        Some(datetime)
+    } else {
+        None
+    }
+}
+
+/// A date rendered with `format`. A week without a weekday, like `2024-W23` with `%G-W%V`, is
+/// taken as its Monday.
+#[cfg(feature = "chrono04")]
+fn parse_date(timestamp: &str, format: &str) -> Option<NaiveDate> {
+    match NaiveDate::parse_from_str(timestamp, format) {
+        Ok(date) => Some(date),
+        Err(err) if err.kind() == chrono::format::ParseErrorKind::NotEnough => {
+            let monday = format!("{} 1", timestamp);
+            NaiveDate::parse_from_str(&monday, &format!("{} %u", format)).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Error from [TimestampSuffix::try_with_format] and [IntervalSuffix::try_with_format].
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError {
+    format: String,
+    kind: FormatErrorKind,
+}
+
+/// What is wrong with the format of a [FormatError].
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FormatErrorKind {
+    /// The format contains an unknown or incomplete specifier.
+    Invalid,
+    /// Later times don't always render to lexically greater strings, e.g. `"%d-%m-%Y"`.
+    NotSortable,
+    /// The rendered timestamp can't be parsed back, e.g. because the date is missing.
+    NotParsable,
+    /// The rendered timestamp contains a path separator.
+    PathSeparator,
+}
+
+#[cfg(feature = "chrono04")]
+impl FormatError {
+    /// The format that was rejected.
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+    /// What is wrong with the format.
+    pub fn kind(&self) -> &FormatErrorKind {
+        &self.kind
+    }
+}
+
+#[cfg(feature = "chrono04")]
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let problem = match self.kind {
+            FormatErrorKind::Invalid => "is invalid",
+            FormatErrorKind::NotSortable => "doesn't sort chronologically",
+            FormatErrorKind::NotParsable => "can't be parsed back",
+            FormatErrorKind::PathSeparator => "renders a path separator",
+        };
+        write!(f, "timestamp format {:?} {}", self.format, problem)
+    }
+}
+
+#[cfg(feature = "chrono04")]
+impl std::error::Error for FormatError {}
+
+/// Check that timestamps rendered with `format` can be used as suffixes.
+#[cfg(feature = "chrono04")]
+fn check_format(format: &str) -> Result<(), FormatError> {
+    let error = |kind| {
+        Err(FormatError {
+            format: format.to_string(),
+            kind,
+        })
+    };
+    if chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error) {
+        return error(FormatErrorKind::Invalid);
+    }
+    let samples = render_samples(format);
+    if samples.windows(2).any(|pair| pair[0] > pair[1]) || samples[0] == samples[samples.len() - 1]
+    {
+        return error(FormatErrorKind::NotSortable);
+    }
+    if samples
+        .iter()
+        .any(|sample| sample.contains('/') || sample.contains(std::path::MAIN_SEPARATOR))
+    {
+        return error(FormatErrorKind::PathSeparator);
+    }
+    if samples
+        .iter()
+        .any(|sample| parse_timestamp(sample, format).is_none())
+    {
+        return error(FormatErrorKind::NotParsable);
+    }
+    Ok(())
+}
+
+/// Whether timestamps rendered with `format` may contain `separator`, which would make the
+/// suffix of a rotated file ambiguous.
+#[cfg(feature = "chrono04")]
+pub(crate) fn renders_separator(format: &str, separator: &str) -> bool {
+    render_samples(format)
+        .iter()
+        .any(|sample| sample.contains(separator))
+}
+
+/// Render chronologically increasing times, where each one increments a field from 9 to 10 (to
+/// catch unpadded fields) or rolls over into the next bigger field (to catch fields in the wrong
+/// order). The first two are in different ISO week years, to catch `%V` with `%Y` instead of
+/// `%G`.
+#[cfg(feature = "chrono04")]
+fn render_samples(format: &str) -> Vec<String> {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    [
+        date(2008, 12, 28).and_hms_milli_opt(0, 0, 0, 0),
+        date(2008, 12, 29).and_hms_milli_opt(0, 0, 0, 0),
+        date(2009, 9, 9).and_hms_milli_opt(9, 9, 9, 9),
+        date(2009, 9, 9).and_hms_milli_opt(9, 9, 9, 10),
+        date(2009, 9, 9).and_hms_milli_opt(9, 9, 10, 0),
+        date(2009, 9, 9).and_hms_milli_opt(9, 10, 0, 0),
+        date(2009, 9, 9).and_hms_milli_opt(10, 0, 0, 0),
+        date(2009, 9, 10).and_hms_milli_opt(0, 0, 0, 0),
+        date(2009, 10, 1).and_hms_milli_opt(0, 0, 0, 0),
+        date(2010, 1, 1).and_hms_milli_opt(0, 0, 0, 0),
+    ]
+    .iter()
+    .map(|time| time.unwrap().and_utc().format(format).to_string())
+    .collect()
+}
+
+/// Whether rendering with `format` depends on the time of day.
+#[cfg(feature = "chrono04")]
+fn has_time_fields(format: &str) -> bool {
+    let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let start = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end = date
+        .and_hms_nano_opt(23, 59, 59, 999_999_999)
+        .unwrap()
+        .and_utc();
+    start.format(format).to_string() != end.format(format).to_string()
+}
+
+/// Whether `format` contains a time zone offset, like `%z` or `%:z`.
+#[cfg(feature = "chrono04")]
+fn has_offset_fields(format: &str) -> bool {
+    use chrono::format::{Fixed, Item, StrftimeItems};
+    StrftimeItems::new(format).any(|item| {
+        matches!(
+            item,
+            Item::Fixed(
+                Fixed::TimezoneOffset
+                    | Fixed::TimezoneOffsetColon
+                    | Fixed::TimezoneOffsetDoubleColon
+                    | Fixed::TimezoneOffsetTripleColon
+                    | Fixed::TimezoneOffsetColonZ
+                    | Fixed::TimezoneOffsetZ
+            )
+        )
+    })
+}
+
+/// `format` without its time zone offsets, like `%z` or `%:z`.
+#[cfg(feature = "chrono04")]
+fn without_offset_fields(format: &str) -> String {
+    let mut stripped = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        stripped.push_str(&rest[..start]);
+        let spec = &rest[start..];
+        // A specifier is `%`, flags like `:`, `#` or `-`, and the conversion character
+        let conversion = spec[1..].trim_start_matches(|c: char| {
+            matches!(c, ':' | '#' | '-' | '_' | '0' | '.' | '3' | '6' | '9')
+        });
+        let end =
+            spec.len() - conversion.len() + conversion.chars().next().map_or(0, char::len_utf8);
+        if !spec[..end].ends_with('z') {
+            stripped.push_str(&spec[..end]);
+        }
+        rest = &spec[end..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// How to determine if a file should be deleted, in the case of TimestampSuffix and EpochSuffix.
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum FileLimit {
     /// Delete the oldest files if number of files is too high
     MaxFiles(usize),
     /// Delete files that have too old timestamp
-    Age(Duration),
+    ///
+    /// With [TimestampSuffix], files whose timestamp is too coarse to tell (e.g. the date, with
+    /// `"%Y-%m-%d"`) are judged by their modification time. [EpochSuffix] only has whole seconds,
+    /// so it rounds a sub-second age up to the next second. [FileLimit::age] converts from a
+    /// `chrono::Duration`.
+    Age(
+        #[cfg_attr(feature = "serde", serde(with = "crate::config::std_duration"))]
+        std::time::Duration,
+    ),
+    /// Like [FileLimit::Age], but always keep the `min_files` newest files, however old they are,
+    /// e.g. so that the history before a long downtime isn't deleted by the first rotation after.
+    AgeWithMinFiles {
+        /// Files older than this are deleted
+        #[cfg_attr(feature = "serde", serde(with = "crate::config::std_duration"))]
+        age: std::time::Duration,
+        /// How many rotated files to keep regardless of their age
+        min_files: usize,
+    },
+    /// Keep all files newer than `recent`, but of the older files only keep the `per_period` most
+    /// recent ones within each calendar `period`.
+    KeepPerPeriod {
+        /// Files newer than this are always kept
+        #[cfg_attr(feature = "serde", serde(with = "crate::config::duration"))]
+        recent: Duration,
+        /// How many files to keep per period, beyond `recent`
+        per_period: usize,
+        /// The calendar period
+        period: Period,
+    },
+    /// Never delete any files
+    Unlimited,
+}
+
+#[cfg(feature = "chrono04")]
+impl FileLimit {
+    /// Why a file is deleted because of this limit, for logging.
+    fn deletion_reason(&self) -> String {
+        match self {
+            FileLimit::MaxFiles(max_files) => format!("more than {} files", max_files),
+            FileLimit::Age(age) | FileLimit::AgeWithMinFiles { age, .. } => {
+                format!("older than {:?}", age)
+            }
+            FileLimit::KeepPerPeriod { period, .. } => format!("not kept per {:?}", period),
+            FileLimit::Unlimited => "unlimited".to_string(),
+        }
+    }
+    /// How many of the newest files are kept regardless of this limit.
+    fn min_files(&self) -> usize {
+        match self {
+            FileLimit::AgeWithMinFiles { min_files, .. } => *min_files,
+            _ => 0,
+        }
+    }
+    /// Parse a duration like `"30d"` or `"12h"` into [FileLimit::Age].
+    ///
+    /// Units are `ms`, `s`, `m`/`min`, `h`, `d` and `w`, case-insensitive. A bare number is a
+    /// number of seconds.
+    pub fn age_from_str(input: &str) -> Result<Self, crate::ParseError> {
+        crate::parse::parse_duration(input)?
+            .to_std()
+            .map(FileLimit::Age)
+            .map_err(|_| crate::ParseError::new(input, crate::ParseErrorKind::OutOfRange))
+    }
+
+    /// [FileLimit::Age] from a `chrono::Duration`. A negative `age` is taken as zero, which
+    /// deletes all rotated files.
+    pub fn age(age: Duration) -> Self {
+        FileLimit::Age(age.to_std().unwrap_or_default())
+    }
+}
+
+/// Calendar period used by `FileLimit::KeepPerPeriod`, in local time.
+#[cfg(feature = "chrono04")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Period {
+    /// From one full hour to the next
+    Hour,
+    /// From one midnight to the next
+    Day,
+}
+
+#[cfg(feature = "chrono04")]
+impl Period {
+    /// The start of the period containing `time`.
+    fn start(self, time: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Period::Hour => time.date().and_hms_opt(time.hour(), 0, 0).unwrap(),
+            Period::Day => time.date().and_hms_opt(0, 0, 0).unwrap(),
+        }
+    }
+}
+
+/// Decide which files to keep with `FileLimit::KeepPerPeriod`, given the local time of each file,
+/// oldest first. Files without a known time are kept.
+#[cfg(feature = "chrono04")]
+fn keep_per_period(
+    times: &[Option<NaiveDateTime>],
+    now: DateTime<Local>,
+    recent: Duration,
+    per_period: usize,
+    period: Period,
+) -> Vec<bool> {
+    let cutoff = now.naive_local() - recent;
+    let mut keep = vec![true; times.len()];
+    let mut current_period = None;
+    let mut kept_in_period = 0;
+    // Go from newest to oldest, so that the newest files of each period are the ones kept
+    for (idx, time) in times.iter().enumerate().rev() {
+        let time = match time {
+            Some(time) if *time < cutoff => *time,
+            _ => continue,
+        };
+        let start = period.start(time);
+        if current_period != Some(start) {
+            current_period = Some(start);
+            kept_in_period = 0;
+        }
+        if kept_in_period < per_period {
+            kept_in_period += 1;
+        } else {
+            keep[idx] = false;
+        }
+    }
+    keep
 }
@@ -0,0 +1,234 @@
+//! Naming rotated files after a template, instead of appending the suffix to the file name.
+//!
+//! ```
+//! use file_rotate::{suffix::CountSuffix, template::NameTemplate, ContentLimit, FileRotate};
+//! use std::io::Write;
+//!
+//! # let directory = tempdir::TempDir::new("rotation-doc-test").unwrap();
+//! # let directory = directory.path();
+//! // Rotates `app.log` to `app-1.log`, `app-2.log`, ...
+//! let template = "{stem}-{suffix}.{ext}".parse::<NameTemplate>().unwrap();
+//! let mut log = FileRotate::new(
+//!     directory.join("app.log"),
+//!     CountSuffix::new(3).with_template(template),
+//!     ContentLimit::Lines(1),
+//! );
+//! writeln!(log, "Hello World!");
+//! assert!(directory.join("app-1.log").exists());
+//! ```
+
+use std::{
+    error::Error,
+    ffi::{OsStr, OsString},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Template for the file names of rotated files.
+///
+/// Placeholders are `{base}` (the file name of the log file), `{stem}` (the file name without its
+/// extension), `{ext}` (the extension, without the dot) and `{suffix}` (what the suffix scheme
+/// produces). `{{` and `}}` stand for literal braces. The template must contain `{suffix}` once,
+/// as well as `{base}` or `{stem}`, and placeholders must be separated by some text, so that the
+/// suffix can be found in a file name again. The default naming is `"{base}.{suffix}"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Base,
+    Stem,
+    Ext,
+    Suffix,
+}
+
+impl NameTemplate {
+    /// Parse and check `template`.
+    pub fn new(template: &str) -> Result<Self, TemplateError> {
+        let error = |kind| TemplateError {
+            template: template.to_string(),
+            kind,
+        };
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| error(TemplateErrorKind::Unclosed))?;
+                    let part = match &rest[..end] {
+                        "base" => Part::Base,
+                        "stem" => Part::Stem,
+                        "ext" => Part::Ext,
+                        "suffix" => Part::Suffix,
+                        name => {
+                            return Err(error(TemplateErrorKind::UnknownPlaceholder(
+                                name.to_string(),
+                            )))
+                        }
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    } else if !parts.is_empty() {
+                        return Err(error(TemplateErrorKind::AdjacentPlaceholders));
+                    }
+                    parts.push(part);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(error(TemplateErrorKind::Unclosed)),
+                '/' | '\\' => return Err(error(TemplateErrorKind::PathSeparator)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        match parts.iter().filter(|part| **part == Part::Suffix).count() {
+            0 => return Err(error(TemplateErrorKind::MissingSuffix)),
+            1 => {}
+            _ => return Err(error(TemplateErrorKind::RepeatedSuffix)),
+        }
+        if !parts
+            .iter()
+            .any(|part| *part == Part::Base || *part == Part::Stem)
+        {
+            return Err(error(TemplateErrorKind::MissingBase));
+        }
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// The template as given.
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// The rendered file name before and after the suffix.
+    pub(crate) fn around_suffix(&self, basepath: &Path) -> (OsString, OsString) {
+        let base = basepath.file_name().expect("basepath.file_name()");
+        let stem = basepath.file_stem().unwrap_or(base);
+        let ext = basepath.extension().unwrap_or_else(|| OsStr::new(""));
+        let mut before = OsString::new();
+        let mut after = OsString::new();
+        let mut current = &mut before;
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => current.push(literal),
+                Part::Base => current.push(base),
+                Part::Stem => current.push(stem),
+                Part::Ext => current.push(ext),
+                Part::Suffix => current = &mut after,
+            }
+        }
+        (before, after)
+    }
+
+    /// Path of the rotated file with `suffix`, next to `basepath`.
+    pub fn path(&self, basepath: &Path, suffix: &str) -> PathBuf {
+        let (mut name, after) = self.around_suffix(basepath);
+        name.push(suffix);
+        name.push(after);
+        basepath.with_file_name(name)
+    }
+
+    /// The suffix of the rotated file `file_name` belongs to `basepath`, if it is one.
+    pub fn suffix<'a>(&self, basepath: &Path, file_name: &'a OsStr) -> Option<&'a str> {
+        if Some(file_name) == basepath.file_name() {
+            return None;
+        }
+        let (before, after) = self.around_suffix(basepath);
+        let suffix = file_name
+            .as_encoded_bytes()
+            .strip_prefix(before.as_encoded_bytes())?
+            .strip_suffix(after.as_encoded_bytes())?;
+        match std::str::from_utf8(suffix) {
+            Ok(suffix) if !suffix.is_empty() => Some(suffix),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = TemplateError;
+
+    fn from_str(template: &str) -> Result<Self, TemplateError> {
+        Self::new(template)
+    }
+}
+
+/// Error from [NameTemplate::new].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateError {
+    template: String,
+    kind: TemplateErrorKind,
+}
+
+/// What is wrong with the template of a [TemplateError].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateErrorKind {
+    /// `{suffix}` is missing.
+    MissingSuffix,
+    /// `{suffix}` appears more than once.
+    RepeatedSuffix,
+    /// Neither `{base}` nor `{stem}` appear, so files of other logs would be taken for ours.
+    MissingBase,
+    /// A placeholder other than `base`, `stem`, `ext` and `suffix`.
+    UnknownPlaceholder(String),
+    /// A `{` or `}` without its counterpart.
+    Unclosed,
+    /// Two placeholders without text between them, like `{stem}{suffix}`.
+    AdjacentPlaceholders,
+    /// The template contains a path separator.
+    PathSeparator,
+}
+
+impl TemplateError {
+    /// The template that was rejected.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+    /// What is wrong with the template.
+    pub fn kind(&self) -> &TemplateErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problem = match &self.kind {
+            TemplateErrorKind::MissingSuffix => "lacks {suffix}".to_string(),
+            TemplateErrorKind::RepeatedSuffix => "has more than one {suffix}".to_string(),
+            TemplateErrorKind::MissingBase => "lacks {base} or {stem}".to_string(),
+            TemplateErrorKind::UnknownPlaceholder(name) => {
+                format!("has an unknown placeholder {{{}}}", name)
+            }
+            TemplateErrorKind::Unclosed => "has an unmatched brace".to_string(),
+            TemplateErrorKind::AdjacentPlaceholders => {
+                "has placeholders without text between them".to_string()
+            }
+            TemplateErrorKind::PathSeparator => "contains a path separator".to_string(),
+        };
+        write!(f, "name template {:?} {}", self.template, problem)
+    }
+}
+
+impl Error for TemplateError {}
@@ -0,0 +1,49 @@
+//! Events about what the crate does, logged with the `log` crate if the `log-internals` feature is
+//! enabled.
+
+#[cfg(feature = "log-internals")]
+use std::{cell::Cell, fmt};
+
+/// Log an event with the [log] crate, target `file_rotate`, if the `log-internals` feature is
+/// enabled. Otherwise the arguments are only type-checked.
+#[cfg(feature = "log-internals")]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        $crate::trace::log(log::Level::$level, format_args!($($arg)+))
+    };
+}
+
+#[cfg(not(feature = "log-internals"))]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{
+        let _ = format_args!($($arg)+);
+    }};
+}
+
+#[cfg(feature = "log-internals")]
+thread_local! {
+    /// Whether an event is being logged by this thread
+    static LOGGING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Log `args`, unless this thread is logging an event already: The logger might write to a
+/// [FileRotate](crate::FileRotate), whose events would recurse.
+#[cfg(feature = "log-internals")]
+pub(crate) fn log(level: log::Level, args: fmt::Arguments) {
+    /// Clears the flag when done, even if the logger panics
+    struct Logging;
+    impl Drop for Logging {
+        fn drop(&mut self) {
+            LOGGING.with(|logging| logging.set(false));
+        }
+    }
+
+    if !log::log_enabled!(target: "file_rotate", level) {
+        return;
+    }
+    if LOGGING.with(|logging| logging.replace(true)) {
+        return;
+    }
+    let _logging = Logging;
+    log::log!(target: "file_rotate", level, "{}", args);
+}